@@ -0,0 +1,218 @@
+use halo2_learning::{
+    poseidon::{self, PoseidonChip, PoseidonConfig},
+    range::{RangeCheckChip, RangeCheckConfig, LOOKUP_BITS},
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::Fp,
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Expression, Instance, Selector},
+    poly::Rotation,
+};
+
+/// Minimum age the circuit proves.
+const THRESHOLD: u64 = 18;
+
+#[derive(Clone, Debug)]
+struct AgeConfig {
+    instance: Column<Instance>,
+    advice: [Column<Advice>; 3],
+    s_age: Selector,
+    poseidon: PoseidonConfig<Fp>,
+    range: RangeCheckConfig,
+}
+
+/// Proves `current_year - birth_year >= THRESHOLD` for the birth year in a
+/// public commitment `Poseidon(birth_year, salt)`. The excess is taken in
+/// one row:
+///
+/// current_year birth_year excess
+///
+/// with `excess = current_year - birth_year - THRESHOLD`. Range checking
+/// both `birth_year` and `excess` to 16 bits rules out the wrap-around of
+/// a birth year after the current one.
+struct AgeChip {
+    config: AgeConfig,
+}
+
+impl AgeChip {
+    fn construct(config: AgeConfig) -> Self {
+        AgeChip { config }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> AgeConfig {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let advice = [(); 3].map(|_| meta.advice_column());
+        for column in advice {
+            meta.enable_equality(column);
+        }
+        let s_age = meta.selector();
+
+        meta.create_gate("age excess", |meta| {
+            let [current_year, birth_year, excess] =
+                advice.map(|column| meta.query_advice(column, Rotation::cur()));
+            let s_age = meta.query_selector(s_age);
+            let threshold = Expression::Constant(Fp::from(THRESHOLD));
+            vec![s_age * (current_year - birth_year - threshold - excess)]
+        });
+
+        // Also enables the constant column the range check needs.
+        let poseidon = PoseidonChip::configure(meta);
+        let range = RangeCheckChip::configure(meta);
+
+        AgeConfig {
+            instance,
+            advice,
+            s_age,
+            poseidon,
+            range,
+        }
+    }
+
+    fn load_private(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        value: Value<Fp>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        layouter.assign_region(
+            || "load private",
+            |mut region| region.assign_advice(|| "value", self.config.advice[0], 0, || value),
+        )
+    }
+
+    /// Instance: `[Poseidon(birth_year, salt), current_year]`.
+    fn assign(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        birth_year: Value<Fp>,
+        salt: Value<Fp>,
+    ) -> Result<(), Error> {
+        let config = &self.config;
+        let poseidon = PoseidonChip::construct(config.poseidon.clone());
+        let range = RangeCheckChip::construct(config.range.clone());
+
+        let birth_year = self.load_private(layouter.namespace(|| "birth year"), birth_year)?;
+        let salt = self.load_private(layouter.namespace(|| "salt"), salt)?;
+        let commitment = poseidon.hash(
+            layouter.namespace(|| "commitment"),
+            &[birth_year.clone(), salt],
+        )?;
+        layouter.constrain_instance(commitment.cell(), config.instance, 0)?;
+
+        let excess = layouter.assign_region(
+            || "age excess",
+            |mut region| {
+                config.s_age.enable(&mut region, 0)?;
+                let current_year = region.assign_advice_from_instance(
+                    || "current year",
+                    config.instance,
+                    1,
+                    config.advice[0],
+                    0,
+                )?;
+                let birth_year =
+                    birth_year.copy_advice(|| "birth year", &mut region, config.advice[1], 0)?;
+                let excess = current_year.value().copied()
+                    - birth_year.value()
+                    - Value::known(Fp::from(THRESHOLD));
+                region.assign_advice(|| "excess", config.advice[2], 0, || excess)
+            },
+        )?;
+
+        range.range_check(
+            layouter.namespace(|| "years"),
+            &[birth_year, excess],
+            LOOKUP_BITS,
+        )
+    }
+}
+
+#[derive(Default)]
+struct AgeCircuit {
+    birth_year: Value<Fp>,
+    salt: Value<Fp>,
+}
+
+impl AgeCircuit {
+    fn new(birth_year: u64, salt: Fp) -> Self {
+        AgeCircuit {
+            birth_year: Value::known(Fp::from(birth_year)),
+            salt: Value::known(salt),
+        }
+    }
+}
+
+impl Circuit<Fp> for AgeCircuit {
+    type Config = AgeConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        AgeChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = AgeChip::construct(config.clone());
+        RangeCheckChip::construct(config.range).load_table(layouter.namespace(|| "range table"))?;
+        chip.assign(layouter.namespace(|| "age"), self.birth_year, self.salt)
+    }
+}
+
+/// What an issuer publishes for a user born in `birth_year`.
+fn commitment(birth_year: u64, salt: Fp) -> Fp {
+    poseidon::hash([Fp::from(birth_year), salt])
+}
+
+fn test_age() {
+    let k = 17;
+    let salt = Fp::from(0x5a17_5a17_5a17);
+    let current_year = Fp::from(2026);
+    let run = |circuit: &AgeCircuit, commitment: Fp, current_year: Fp| {
+        MockProver::run(k, circuit, vec![vec![commitment, current_year]])
+            .unwrap()
+            .verify()
+    };
+
+    // Adults, down to exactly THRESHOLD years.
+    for birth_year in [1950, 2000, 2008] {
+        let circuit = AgeCircuit::new(birth_year, salt);
+        assert_eq!(
+            run(&circuit, commitment(birth_year, salt), current_year),
+            Ok(())
+        );
+    }
+
+    // Underage users, including one born "after" the current year.
+    for birth_year in [2009, 2020, 2030] {
+        let circuit = AgeCircuit::new(birth_year, salt);
+        assert!(run(&circuit, commitment(birth_year, salt), current_year).is_err());
+    }
+
+    // A wrong salt does not open the commitment.
+    let circuit = AgeCircuit::new(2000, salt + Fp::one());
+    assert!(run(&circuit, commitment(2000, salt), current_year).is_err());
+
+    // Neither does another birth year, even an older one.
+    let circuit = AgeCircuit::new(1990, salt);
+    assert!(run(&circuit, commitment(2000, salt), current_year).is_err());
+
+    // The same proof checked against an earlier year fails once too young.
+    let circuit = AgeCircuit::new(2000, salt);
+    assert_eq!(
+        run(&circuit, commitment(2000, salt), Fp::from(2018)),
+        Ok(())
+    );
+    assert!(run(&circuit, commitment(2000, salt), Fp::from(2017)).is_err());
+}
+
+fn main() {
+    test_age();
+}
@@ -1,155 +1,699 @@
-// use std::marker::PhantomData;
-
-// use halo2_proofs::{
-//     arithmetic::Field,
-//     circuit::{AssignedCell, Layouter, SimpleFloorPlanner},
-//     dev::MockProver,
-//     pairing::{
-//         bls12_381::{Fr, G1Affine, G2Affine, G1, G2},
-//         group::Group,
-//     },
-//     plonk::{Advice, Circuit, Column, Error, Instance, Selector},
-//     poly::Rotation,
-// };
-// use rand_core::OsRng;
-
-// #[derive(Clone, Debug)]
-// struct BLSConfig {
-//     instance: Column<Instance>,
-//     advice: [Column<Advice>; 2],
-//     selector: Selector,
-// }
-
-// #[derive(Clone, Debug)]
-// struct BLSChip<F: Field> {
-//     config: BLSConfig,
-//     _phantom: PhantomData<F>,
-// }
-
-// struct ACell<F: Field>(AssignedCell<F, F>);
-
-// impl<F: Field> BLSChip<F> {
-//     fn construct(config: BLSConfig) -> Self {
-//         BLSChip {
-//             config,
-//             _phantom: PhantomData,
-//         }
-//     }
-
-//     fn configure(meta: &mut halo2_proofs::plonk::ConstraintSystem<F>) -> BLSConfig {
-//         let advice = [meta.advice_column(), meta.advice_column()];
-//         let instance = meta.instance_column();
-//         let selector = meta.selector();
-//         for c in advice {
-//             meta.enable_equality(c);
-//         }
-//         meta.enable_equality(instance);
-
-//         meta.create_gate("BLS gate", |meta| {
-//             let pub_key = meta.query_advice(advice[0], Rotation::cur());
-//             let msg_hash = meta.query_advice(advice[1], Rotation::cur());
-//             let g1 = meta.query_advice(advice[0], Rotation::next());
-//             let sig = meta.query_advice(advice[1], Rotation::next());
-
-//             let selector = meta.query_selector(selector);
-
-//             vec![selector * (pairing(pub_key, msg_hash) - pairing(g1, sig))]
-//         });
-
-//         BLSConfig {
-//             advice,
-//             instance,
-//             selector,
-//         }
-//     }
-
-//     fn assign(
-//         &self,
-//         g1: G1Affine,
-//         signature: G2Affine,
-//         pubkey: G1Affine,
-//         msg_hash: G2Affine,
-//         mut layouter: impl halo2_proofs::circuit::Layouter<F>,
-//     ) -> Result<(), Error> {
-//         let config = &self.config;
-
-//         let cells = layouter
-//             .assign_region(
-//                 || "load private",
-//                 |mut region| {
-//                     let g1 = region.assign_advice(|| "load g1", config.advice[0], 0, || g1)?;
-//                     let signature =
-//                         region.assign_advice(|| "load signature", config.advice[0], 1, || signature)?;
-//                     let pubkey =
-//                         region.assign_advice(|| "load pubkey", config.advice[0], 2, || pubkey)?;
-//                     let msg_hash =
-//                         region.assign_advice(|| "load msg_hash", config.advice[0], 3, || msg_hash)?;
-//                     Ok((g1, signature, pubkey, msg_hash))
-//                 },
-//             )
-//             .unwrap();
-
-//         Ok(())
-//     }
-// }
-
-// #[derive(Clone, Debug, Default)]
-// struct BLSCircuit<F: Field> {
-//     g1: G1Affine,
-//     signature: G2Affine,
-//     pubkey: G1Affine,
-//     msg_hash: G2Affine,
-//     _phantom: PhantomData<F>,
-// }
-
-// impl<F: Field> Circuit<F> for BLSCircuit<F> {
-//     type Config = BLSConfig;
-
-//     type FloorPlanner = SimpleFloorPlanner;
-
-//     fn without_witnesses(&self) -> Self {
-//         Self::default()
-//     }
-
-//     fn configure(meta: &mut halo2_proofs::plonk::ConstraintSystem<F>) -> Self::Config {
-//         BLSChip::configure(meta)
-//     }
-
-//     fn synthesize(
-//         &self,
-//         config: Self::Config,
-//         mut layouter: impl halo2_proofs::circuit::Layouter<F>,
-//     ) -> Result<(), Error> {
-//         let chip = BLSChip::construct(config);
-//         let out = BLSChip::assign(
-//             &chip,
-//             self.g1,
-//             self.msg_hash,
-//             self.pubkey,
-//             self.signature,
-//             layouter.namespace(|| "BLS"),
-//         )?;
-
-//         Ok(())
-//     }
-// }
-
-// fn main() {
-//     let msg_hash = G2Affine::from(G2::random(&mut OsRng));
-//     let sk = Fr::random(OsRng);
-//     let signature = G2Affine::from(msg_hash * sk);
-//     let pubkey = G1Affine::from(G1Affine::generator() * sk);
-//     let g1 = G1::generator();
-
-//     let circuit = BLSCircuit {
-//         g1: g1.into(),
-//         signature,
-//         pubkey,
-//         msg_hash,
-//         _phantom: PhantomData,
-//     };
-
-//     let k = 10;
-
-//     let prover = MockProver::run(k, &circuit, vec![]).unwrap();
-// }
+use bls12_381::{pairing, G1Affine, G1Projective, G2Affine, G2Projective, Scalar};
+use halo2_gadgets::poseidon::primitives::{P128Pow5T3, Spec};
+use halo2_learning::{
+    bls::{
+        fq::{FqConfig, NUM_LIMBS},
+        g1::{offset_point, AssignedG1, G1Chip},
+        g1_to_instance,
+        g2::AssignedG2,
+        g2_to_instance,
+        native::{self, TEST_VECTORS},
+        pairing::PairingChip,
+    },
+    poseidon::{self, PoseidonChip, PoseidonConfig},
+    range::LOOKUP_BITS,
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::{group::ff::PrimeField, Fp},
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Constraints, Error, Instance, Selector},
+    poly::Rotation,
+};
+
+/// Native cells per G1 point, and so per key in the key set commitment.
+const KEY_LIMBS: usize = 2 * NUM_LIMBS;
+
+#[derive(Clone, Debug)]
+struct BLSConfig<F: PrimeField> {
+    instance: Column<Instance>,
+    fq: FqConfig,
+    advice: [Column<Advice>; 3],
+    s_count: Selector,
+    s_threshold: Selector,
+    poseidon: PoseidonConfig<F>,
+}
+
+#[derive(Clone, Debug)]
+struct BLSChip<F: PrimeField> {
+    config: BLSConfig<F>,
+    g1: G1Chip<F>,
+    pairing: PairingChip<F>,
+}
+
+impl<F: PrimeField> BLSChip<F>
+where
+    P128Pow5T3: Spec<F, 3, 2>,
+{
+    fn construct(config: BLSConfig<F>) -> Self {
+        let g1 = G1Chip::construct(config.fq.clone());
+        let pairing = PairingChip::construct(config.fq.clone());
+        BLSChip {
+            config,
+            g1,
+            pairing,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> BLSConfig<F> {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let fq = PairingChip::configure(meta);
+
+        let advice = [(); 3].map(|_| meta.advice_column());
+        for column in advice {
+            meta.enable_equality(column);
+        }
+        let s_count = meta.selector();
+        let s_threshold = meta.selector();
+
+        // count' = count + bit, one participation bit per row.
+        meta.create_gate("s_count", |meta| {
+            let bit = meta.query_advice(advice[0], Rotation::cur());
+            let count = meta.query_advice(advice[1], Rotation::cur());
+            let next = meta.query_advice(advice[1], Rotation::next());
+            let s_count = meta.query_selector(s_count);
+            Constraints::with_selector(s_count, vec![count + bit - next])
+        });
+
+        // diff = count - threshold; the caller range checks `diff`.
+        meta.create_gate("s_threshold", |meta| {
+            let count = meta.query_advice(advice[0], Rotation::cur());
+            let threshold = meta.query_advice(advice[1], Rotation::cur());
+            let diff = meta.query_advice(advice[2], Rotation::cur());
+            let s_threshold = meta.query_selector(s_threshold);
+            Constraints::with_selector(s_threshold, vec![count - threshold - diff])
+        });
+
+        let poseidon = PoseidonChip::configure(meta);
+
+        BLSConfig {
+            instance,
+            fq,
+            advice,
+            s_count,
+            s_threshold,
+            poseidon,
+        }
+    }
+
+    fn load_table(&self, layouter: impl Layouter<F>) -> Result<(), Error> {
+        self.g1.fq().load_table(layouter)
+    }
+
+    /// Computes `sk * G1` for a private `sk`.
+    fn assign(
+        &self,
+        sk: Value<Scalar>,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<AssignedG1<F>, Error> {
+        let bits = self
+            .g1
+            .assign_scalar(layouter.namespace(|| "sk bits"), sk)?;
+        self.g1.fixed_base_mul(
+            layouter.namespace(|| "sk * G1"),
+            &bits,
+            &G1Affine::generator(),
+        )
+    }
+
+    /// Constrains `pubkey` to the point in the instance column starting at `row`.
+    fn expose_public(
+        &self,
+        mut layouter: impl Layouter<F>,
+        pubkey: &AssignedG1<F>,
+        row: usize,
+    ) -> Result<(), Error> {
+        let expected =
+            self.g1
+                .load_instance(layouter.namespace(|| "pubkey"), self.config.instance, row)?;
+        self.g1
+            .assert_equal(layouter.namespace(|| "pubkey = sk * G1"), pubkey, &expected)
+    }
+
+    /// Checks `e(pubkey, msg_hash) = e(G1, signature)` with the public key at
+    /// instance row `row` and the message hash right after it.
+    fn verify(
+        &self,
+        mut layouter: impl Layouter<F>,
+        signature: Value<G2Affine>,
+        row: usize,
+    ) -> Result<(), Error> {
+        let instance = self.config.instance;
+        let pubkey = self
+            .g1
+            .load_instance(layouter.namespace(|| "pubkey"), instance, row)?;
+        let msg_hash = self.pairing.g2().load_instance(
+            layouter.namespace(|| "msg hash"),
+            instance,
+            row + KEY_LIMBS,
+        )?;
+
+        self.verify_signature(layouter, &pubkey, &msg_hash, signature)
+    }
+
+    /// Checks `e(pubkey, msg_hash) = e(G1, signature)` as
+    /// `e(pubkey, msg_hash) * e(-G1, signature) = 1`.
+    fn verify_signature(
+        &self,
+        mut layouter: impl Layouter<F>,
+        pubkey: &AssignedG1<F>,
+        msg_hash: &AssignedG2<F>,
+        signature: Value<G2Affine>,
+    ) -> Result<(), Error> {
+        let g2 = self.pairing.g2();
+
+        // The public inputs are validated by the verifier; the signature is
+        // private, so it must be shown to be a point of G2.
+        let signature = g2.load_private(layouter.namespace(|| "signature"), signature)?;
+        g2.assert_on_curve(layouter.namespace(|| "signature on curve"), &signature)?;
+        g2.assert_in_subgroup(layouter.namespace(|| "signature in G2"), &signature)?;
+
+        let neg_g1 = self
+            .g1
+            .load_constant(layouter.namespace(|| "-G1"), &-G1Affine::generator())?;
+        self.pairing.assert_product_is_one(
+            layouter.namespace(|| "pairing check"),
+            &[(pubkey, msg_hash), (&neg_g1, &signature)],
+        )
+    }
+
+    /// Checks an aggregate signature by the keys selected in `participation`.
+    ///
+    /// The instance column holds, from `row`: the key set commitment, the
+    /// number of signers, the threshold and the message hash.
+    fn verify_aggregate(
+        &self,
+        mut layouter: impl Layouter<F>,
+        pubkeys: &[Value<G1Affine>],
+        participation: Value<Vec<bool>>,
+        signature: Value<G2Affine>,
+        row: usize,
+    ) -> Result<(), Error> {
+        let instance = self.config.instance;
+
+        // The keys are private, so they must be shown to be points of the
+        // curve for the addition formulas to hold. Subgroup membership is left
+        // to registration, see `AggregateCircuit`.
+        let pubkeys = pubkeys
+            .iter()
+            .enumerate()
+            .map(|(i, pubkey)| {
+                let mut layouter = layouter.namespace(|| format!("pubkey {}", i));
+                let pubkey = self
+                    .g1
+                    .load_private(layouter.namespace(|| "load"), *pubkey)?;
+                self.g1
+                    .assert_on_curve(layouter.namespace(|| "on curve"), &pubkey)?;
+                Ok(pubkey)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let commitment = self.commit_keys(layouter.namespace(|| "commit keys"), &pubkeys)?;
+        layouter.constrain_instance(commitment.cell(), instance, row)?;
+
+        let bits = self.g1.fq().assign_bits(
+            layouter.namespace(|| "participation"),
+            participation,
+            pubkeys.len(),
+        )?;
+        let count = self.count_signers(layouter.namespace(|| "count signers"), &bits)?;
+        layouter.constrain_instance(count.cell(), instance, row + 1)?;
+        self.assert_threshold(layouter.namespace(|| "threshold"), &count, row + 2)?;
+
+        let msg_hash = self.pairing.g2().load_instance(
+            layouter.namespace(|| "msg hash"),
+            instance,
+            row + 3,
+        )?;
+        let aggregate = self.aggregate(layouter.namespace(|| "aggregate"), &pubkeys, &bits)?;
+        self.verify_signature(layouter, &aggregate, &msg_hash, signature)
+    }
+
+    /// Returns the sum of the keys whose bit is set. At least one bit must be
+    /// set.
+    ///
+    /// As in scalar multiplication, the accumulator starts at `offset_point()`
+    /// so it is never the identity, and the offset is removed at the end.
+    fn aggregate(
+        &self,
+        mut layouter: impl Layouter<F>,
+        pubkeys: &[AssignedG1<F>],
+        bits: &[AssignedCell<F, F>],
+    ) -> Result<AssignedG1<F>, Error> {
+        let g1 = &self.g1;
+        let offset = offset_point();
+        let mut acc = g1.load_constant(layouter.namespace(|| "offset"), &offset)?;
+        for (i, (pubkey, bit)) in pubkeys.iter().zip(bits).enumerate() {
+            let mut layouter = layouter.namespace(|| format!("key {}", i));
+            let sum = g1.add(layouter.namespace(|| "acc + pubkey"), &acc, pubkey)?;
+            acc = g1.select(layouter.namespace(|| "select"), bit, &sum, &acc)?;
+        }
+
+        let neg_offset = g1.load_constant(layouter.namespace(|| "-offset"), &(-offset))?;
+        g1.add(layouter.namespace(|| "remove offset"), &acc, &neg_offset)
+    }
+
+    /// Returns the number of set bits in `bits`.
+    fn count_signers(
+        &self,
+        mut layouter: impl Layouter<F>,
+        bits: &[AssignedCell<F, F>],
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let config = &self.config;
+
+        layouter.assign_region(
+            || "count signers",
+            |mut region| {
+                let mut count =
+                    region.assign_advice_from_constant(|| "count", config.advice[1], 0, F::ZERO)?;
+                for (offset, bit) in bits.iter().enumerate() {
+                    config.s_count.enable(&mut region, offset)?;
+                    bit.copy_advice(|| "bit", &mut region, config.advice[0], offset)?;
+
+                    let value = count.value().copied() + bit.value().copied();
+                    count =
+                        region.assign_advice(|| "count", config.advice[1], offset + 1, || value)?;
+                }
+                Ok(count)
+            },
+        )
+    }
+
+    /// Constrains `count >= threshold` for the threshold at instance row
+    /// `row`. Both are kept below `2^LOOKUP_BITS`, so the difference cannot
+    /// wrap around the field.
+    fn assert_threshold(
+        &self,
+        mut layouter: impl Layouter<F>,
+        count: &AssignedCell<F, F>,
+        row: usize,
+    ) -> Result<(), Error> {
+        let config = &self.config;
+
+        let (threshold, diff) = layouter.assign_region(
+            || "count - threshold",
+            |mut region| {
+                config.s_threshold.enable(&mut region, 0)?;
+                count.copy_advice(|| "count", &mut region, config.advice[0], 0)?;
+                let threshold = region.assign_advice_from_instance(
+                    || "threshold",
+                    config.instance,
+                    row,
+                    config.advice[1],
+                    0,
+                )?;
+
+                let value = count.value().copied() - threshold.value().copied();
+                let diff = region.assign_advice(|| "diff", config.advice[2], 0, || value)?;
+                Ok((threshold, diff))
+            },
+        )?;
+
+        self.g1.fq().range_chip().range_check(
+            layouter.namespace(|| "threshold, count - threshold"),
+            &[threshold, diff],
+            LOOKUP_BITS,
+        )
+    }
+
+    /// Poseidon commitment to an ordered key set, see `key_set_commitment`.
+    fn commit_keys(
+        &self,
+        mut layouter: impl Layouter<F>,
+        pubkeys: &[AssignedG1<F>],
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let chip = PoseidonChip::construct(self.config.poseidon.clone());
+        let mut commitment: Option<AssignedCell<F, F>> = None;
+        for (i, pubkey) in pubkeys.iter().enumerate() {
+            let mut layouter = layouter.namespace(|| format!("key {}", i));
+            let limbs: [AssignedCell<F, F>; KEY_LIMBS] =
+                [pubkey.x.limbs.clone(), pubkey.y.limbs.clone()]
+                    .concat()
+                    .try_into()
+                    .unwrap();
+            let key_hash = chip.hash(layouter.namespace(|| "hash key"), &limbs)?;
+
+            commitment = Some(match commitment {
+                None => key_hash,
+                Some(acc) => chip.hash(layouter.namespace(|| "chain"), &[acc, key_hash])?,
+            });
+        }
+
+        Ok(commitment.expect("empty key set"))
+    }
+}
+
+/// Proves `e(pubkey, msg_hash) = e(G1, signature)` for a private signature.
+#[derive(Default)]
+struct BLSCircuit {
+    signature: Value<G2Affine>,
+}
+
+impl<F: PrimeField> Circuit<F> for BLSCircuit
+where
+    P128Pow5T3: Spec<F, 3, 2>,
+{
+    type Config = BLSConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        BLSChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let chip = BLSChip::construct(config);
+        chip.load_table(layouter.namespace(|| "range table"))?;
+
+        chip.verify(layouter.namespace(|| "verify"), self.signature, 0)
+    }
+}
+
+/// Proves that at least `threshold` keys of a committed key set signed a
+/// message, given their aggregate signature. The keys, the participation
+/// bitmap and the signature are private.
+///
+/// Every key in the committed set must have passed a proof-of-possession
+/// check, which also validates it as a point of G1, before the commitment
+/// is published. All signers sign the same message, so a rogue key
+/// `pk_a = sk_a * G1 - (pk_1 + ... + pk_n)` would otherwise aggregate with
+/// the honest keys to `sk_a * G1`, letting its owner alone forge a signature
+/// for the whole set. The commitment binds the keys, not knowledge of their
+/// secret keys.
+struct AggregateCircuit<const N: usize> {
+    pubkeys: [Value<G1Affine>; N],
+    participation: Value<[bool; N]>,
+    signature: Value<G2Affine>,
+}
+
+impl<F: PrimeField, const N: usize> Circuit<F> for AggregateCircuit<N>
+where
+    P128Pow5T3: Spec<F, 3, 2>,
+{
+    type Config = BLSConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        AggregateCircuit {
+            pubkeys: [Value::unknown(); N],
+            participation: Value::unknown(),
+            signature: Value::unknown(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        BLSChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let chip = BLSChip::construct(config);
+        chip.load_table(layouter.namespace(|| "range table"))?;
+
+        chip.verify_aggregate(
+            layouter.namespace(|| "verify aggregate"),
+            &self.pubkeys,
+            self.participation.map(|bits| bits.to_vec()),
+            self.signature,
+            0,
+        )
+    }
+}
+
+/// Proves knowledge of `sk` with `pubkey = sk * G1`.
+#[derive(Default)]
+struct KeyCircuit {
+    sk: Value<Scalar>,
+}
+
+impl<F: PrimeField> Circuit<F> for KeyCircuit
+where
+    P128Pow5T3: Spec<F, 3, 2>,
+{
+    type Config = BLSConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        BLSChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let chip = BLSChip::construct(config);
+        chip.load_table(layouter.namespace(|| "range table"))?;
+
+        let pubkey = chip.assign(self.sk, layouter.namespace(|| "pubkey"))?;
+        chip.expose_public(layouter.namespace(|| "expose pubkey"), &pubkey, 0)
+    }
+}
+
+/// Checks the variable-base path: `k * point`, with `point` private and
+/// checked to lie on the curve.
+#[derive(Default)]
+struct MulCircuit {
+    point: Value<G1Affine>,
+    k: Value<Scalar>,
+}
+
+impl<F: PrimeField> Circuit<F> for MulCircuit
+where
+    P128Pow5T3: Spec<F, 3, 2>,
+{
+    type Config = BLSConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        BLSChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let chip = BLSChip::construct(config);
+        chip.load_table(layouter.namespace(|| "range table"))?;
+
+        let g1 = &chip.g1;
+        let point = g1.load_private(layouter.namespace(|| "point"), self.point)?;
+        g1.assert_on_curve(layouter.namespace(|| "on curve"), &point)?;
+        let bits = g1.assign_scalar(layouter.namespace(|| "k bits"), self.k)?;
+        let product = g1.variable_base_mul(layouter.namespace(|| "k * point"), &bits, &point)?;
+        chip.expose_public(layouter.namespace(|| "expose product"), &product, 0)
+    }
+}
+
+/// Native counterpart of `BLSChip::commit_keys`: every key is hashed from
+/// its instance limbs, and the key hashes are chained with a 2-to-1 hash.
+fn key_set_commitment(pubkeys: &[G1Affine]) -> Fp {
+    pubkeys
+        .iter()
+        .map(|pubkey| {
+            let limbs: [Fp; KEY_LIMBS] = g1_to_instance(pubkey).try_into().unwrap();
+            poseidon::hash(limbs)
+        })
+        .reduce(|acc, key_hash| poseidon::hash([acc, key_hash]))
+        .expect("empty key set")
+}
+
+fn test_pubkey() {
+    let k = 17;
+    let sk = Scalar::from_raw([
+        0x1234_5678_9abc_def0,
+        0x0fed_cba9_8765_4321,
+        0x1111_2222_3333_4444,
+        0x0555_6666_7777_8888,
+    ]);
+    let pubkey = G1Affine::from(G1Affine::generator() * sk);
+
+    let circuit = KeyCircuit {
+        sk: Value::known(sk),
+    };
+
+    let prover = MockProver::run(k, &circuit, vec![g1_to_instance::<Fp>(&pubkey)]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let wrong_pubkey = G1Affine::from(G1Affine::generator() * (sk + Scalar::one()));
+    let prover = MockProver::run(k, &circuit, vec![g1_to_instance::<Fp>(&wrong_pubkey)]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+fn test_variable_base() {
+    let k = 18;
+    let point = G1Affine::from(G1Affine::generator() * Scalar::from(0xdead_beef_u64));
+    let scalar = Scalar::from_raw([0xfeed_f00d, 0xc0ff_ee00, 0xbad_cafe, 0x1234]);
+    let product = G1Affine::from(point * scalar);
+
+    let circuit = MulCircuit {
+        point: Value::known(point),
+        k: Value::known(scalar),
+    };
+
+    let prover = MockProver::run(k, &circuit, vec![g1_to_instance::<Fp>(&product)]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let prover = MockProver::run(k, &circuit, vec![g1_to_instance::<Fp>(&point)]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+fn test_native() {
+    for vector in TEST_VECTORS.iter() {
+        let (sk, pubkey) = native::keygen(vector.ikm);
+        let signature = native::sign(&sk, vector.msg);
+        assert_eq!(native::scalar_to_hex(&sk), vector.sk);
+        assert_eq!(native::to_hex(&pubkey.to_compressed()), vector.pubkey);
+        assert_eq!(native::to_hex(&signature.to_compressed()), vector.signature);
+
+        assert!(native::verify(&pubkey, vector.msg, &signature));
+        assert!(!native::verify(&pubkey, b"another message", &signature));
+        assert_eq!(
+            pairing(&pubkey, &native::hash_to_g2(vector.msg)),
+            pairing(&G1Affine::generator(), &signature)
+        );
+    }
+
+    let keys: Vec<_> = TEST_VECTORS.iter().map(|v| native::keygen(v.ikm)).collect();
+    let pubkeys: Vec<_> = keys.iter().map(|(_, pubkey)| *pubkey).collect();
+
+    // Same message.
+    let msg = b"one message";
+    let signatures: Vec<_> = keys.iter().map(|(sk, _)| native::sign(sk, msg)).collect();
+    let aggregate = native::aggregate_signatures(&signatures);
+    assert!(native::fast_aggregate_verify(&pubkeys, msg, &aggregate));
+    assert!(!native::fast_aggregate_verify(
+        &pubkeys[1..],
+        msg,
+        &aggregate
+    ));
+
+    // One message per key.
+    let msgs: Vec<&[u8]> = TEST_VECTORS.iter().map(|v| v.msg).collect();
+    let signatures: Vec<_> = keys
+        .iter()
+        .zip(&msgs)
+        .map(|((sk, _), msg)| native::sign(sk, msg))
+        .collect();
+    let aggregate = native::aggregate_signatures(&signatures);
+    assert!(native::aggregate_verify(&pubkeys, &msgs, &aggregate));
+    let mut swapped = msgs.clone();
+    swapped.swap(0, 1);
+    assert!(!native::aggregate_verify(&pubkeys, &swapped, &aggregate));
+}
+
+fn test_verify() {
+    let k = 18;
+    let vector = &TEST_VECTORS[1];
+    let (sk, pubkey) = native::keygen(vector.ikm);
+    let msg_hash = native::hash_to_g2(vector.msg);
+    let signature = native::sign(&sk, vector.msg);
+
+    let public_inputs = |pubkey: &G1Affine, msg_hash: &G2Affine| {
+        let mut cells = g1_to_instance::<Fp>(pubkey);
+        cells.extend(g2_to_instance::<Fp>(msg_hash));
+        vec![cells]
+    };
+
+    // The native check the circuit mirrors.
+    assert!(native::verify(&pubkey, vector.msg, &signature));
+    let circuit = BLSCircuit {
+        signature: Value::known(signature),
+    };
+    let prover = MockProver::run(k, &circuit, public_inputs(&pubkey, &msg_hash)).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // A signature over a different message.
+    assert!(!native::verify(&pubkey, b"another message", &signature));
+    let other_hash = native::hash_to_g2(b"another message");
+    let prover = MockProver::run(k, &circuit, public_inputs(&pubkey, &other_hash)).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+fn test_aggregate() {
+    let k = 18;
+    let sks = [0x5eed_0001_u64, 0x5eed_0002, 0x5eed_0003, 0x5eed_0004]
+        .map(|seed| Scalar::from_raw([seed, seed << 7, seed << 13, seed >> 3]));
+    let pubkeys = sks.map(|sk| G1Affine::from(G1Affine::generator() * sk));
+    let msg_hash = G2Affine::from(G2Affine::generator() * Scalar::from(0x0061_6767_7265_6761_u64));
+    let participation = [true, false, true, true];
+
+    let signature = sks
+        .iter()
+        .zip(participation)
+        .filter(|(_, signed)| *signed)
+        .map(|(sk, _)| msg_hash * sk)
+        .sum::<G2Projective>();
+    let signature = G2Affine::from(signature);
+
+    // Native check against the aggregate key.
+    let aggregate_key = pubkeys
+        .iter()
+        .zip(participation)
+        .filter(|(_, signed)| *signed)
+        .map(|(pubkey, _)| G1Projective::from(pubkey))
+        .sum::<G1Projective>();
+    assert_eq!(
+        pairing(&G1Affine::from(aggregate_key), &msg_hash),
+        pairing(&G1Affine::generator(), &signature)
+    );
+
+    let commitment = key_set_commitment(&pubkeys);
+    let public_inputs = |commitment: Fp, count: u64, threshold: u64| {
+        let mut cells = vec![commitment, Fp::from(count), Fp::from(threshold)];
+        cells.extend(g2_to_instance::<Fp>(&msg_hash));
+        vec![cells]
+    };
+
+    let circuit = AggregateCircuit {
+        pubkeys: pubkeys.map(Value::known),
+        participation: Value::known(participation),
+        signature: Value::known(signature),
+    };
+    let prover = MockProver::run(k, &circuit, public_inputs(commitment, 3, 3)).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // Three signers do not meet a threshold of four.
+    let prover = MockProver::run(k, &circuit, public_inputs(commitment, 3, 4)).unwrap();
+    assert!(prover.verify().is_err());
+
+    // A different key set.
+    let other_commitment = key_set_commitment(&pubkeys[..3]);
+    let prover = MockProver::run(k, &circuit, public_inputs(other_commitment, 3, 3)).unwrap();
+    assert!(prover.verify().is_err());
+
+    // The bitmap claims a signer that did not sign.
+    let circuit = AggregateCircuit {
+        participation: Value::known([true; 4]),
+        ..circuit
+    };
+    let prover = MockProver::run(k, &circuit, public_inputs(commitment, 4, 3)).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+fn main() {
+    test_native();
+    test_pubkey();
+    test_variable_base();
+    test_verify();
+    test_aggregate();
+}
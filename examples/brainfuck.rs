@@ -0,0 +1,1112 @@
+use halo2_learning::poseidon::{self, PoseidonChip, PoseidonConfig};
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::Fp,
+    plonk::{
+        Advice, Circuit, Column, ConstraintSystem, Constraints, Error, Expression, Fixed, Instance,
+        Selector, TableColumn,
+    },
+    poly::Rotation,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Instruction {
+    Inc,
+    Dec,
+    Right,
+    Left,
+    /// `[`: jumps past the matching `]` if the current cell is zero.
+    JumpIfZero(usize),
+    /// `]`: jumps back past the matching `[` unless the current cell is zero.
+    JumpUnlessZero(usize),
+    Output,
+    Input,
+    /// Appended after the last command. Stays put, which pads the trace.
+    Halt,
+}
+
+/// Number of opcodes, which is also the number of flag columns.
+const OPCODES: usize = 9;
+
+impl Instruction {
+    /// Opcodes start at 1, so that the all-zero row of the program table
+    /// matches no instruction.
+    fn opcode(&self) -> u64 {
+        match self {
+            Instruction::Inc => 1,
+            Instruction::Dec => 2,
+            Instruction::Right => 3,
+            Instruction::Left => 4,
+            Instruction::JumpIfZero(_) => 5,
+            Instruction::JumpUnlessZero(_) => 6,
+            Instruction::Output => 7,
+            Instruction::Input => 8,
+            Instruction::Halt => 9,
+        }
+    }
+
+    fn target(&self) -> u64 {
+        match self {
+            Instruction::JumpIfZero(target) | Instruction::JumpUnlessZero(target) => *target as u64,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum BfError {
+    /// The position of the bracket among the commands.
+    UnmatchedBracket(usize),
+    TapeUnderflow {
+        ip: usize,
+    },
+    InputExhausted {
+        ip: usize,
+    },
+    /// The program had not halted after the given number of steps.
+    StepLimit,
+}
+
+/// Keeps the eight commands, drops everything else as a comment, resolves
+/// the brackets and appends `Halt`.
+fn parse(source: &str) -> Result<Vec<Instruction>, BfError> {
+    let commands: Vec<char> = source.chars().filter(|c| "+-><[].,".contains(*c)).collect();
+    let mut program = Vec::with_capacity(commands.len() + 1);
+    let mut open = vec![];
+    for (ip, command) in commands.iter().enumerate() {
+        let instruction = match command {
+            '+' => Instruction::Inc,
+            '-' => Instruction::Dec,
+            '>' => Instruction::Right,
+            '<' => Instruction::Left,
+            '.' => Instruction::Output,
+            ',' => Instruction::Input,
+            '[' => {
+                open.push(ip);
+                // Patched at the matching `]`.
+                Instruction::JumpIfZero(0)
+            }
+            _ => {
+                let start = open.pop().ok_or(BfError::UnmatchedBracket(ip))?;
+                program[start] = Instruction::JumpIfZero(ip + 1);
+                Instruction::JumpUnlessZero(start + 1)
+            }
+        };
+        program.push(instruction);
+    }
+    if let Some(ip) = open.pop() {
+        return Err(BfError::UnmatchedBracket(ip));
+    }
+    program.push(Instruction::Halt);
+    Ok(program)
+}
+
+/// The state before executing `instruction`: the instruction pointer, the
+/// memory pointer and the value of the current cell.
+#[derive(Clone, Debug)]
+struct Row {
+    ip: usize,
+    instruction: Instruction,
+    mp: usize,
+    mv: u8,
+}
+
+#[derive(Clone, Debug)]
+struct Execution {
+    rows: Vec<Row>,
+    output: Vec<u8>,
+}
+
+/// Runs `program` on a tape of wrapping bytes that starts zeroed, and
+/// returns exactly `steps` rows: the execution, then copies of the halting
+/// row. The whole input has to be read.
+fn run(program: &[Instruction], input: &[u8], steps: usize) -> Result<Execution, BfError> {
+    let (mut ip, mut mp) = (0, 0);
+    let mut tape = vec![0u8];
+    let mut input = input.iter();
+    let mut rows = Vec::with_capacity(steps);
+    let mut output = vec![];
+
+    while rows.len() < steps {
+        let instruction = program[ip];
+        rows.push(Row {
+            ip,
+            instruction,
+            mp,
+            mv: tape[mp],
+        });
+        ip = match instruction {
+            Instruction::Inc => {
+                tape[mp] = tape[mp].wrapping_add(1);
+                ip + 1
+            }
+            Instruction::Dec => {
+                tape[mp] = tape[mp].wrapping_sub(1);
+                ip + 1
+            }
+            Instruction::Right => {
+                mp += 1;
+                if mp == tape.len() {
+                    tape.push(0);
+                }
+                ip + 1
+            }
+            Instruction::Left => {
+                mp = mp.checked_sub(1).ok_or(BfError::TapeUnderflow { ip })?;
+                ip + 1
+            }
+            Instruction::JumpIfZero(target) if tape[mp] == 0 => target,
+            Instruction::JumpUnlessZero(target) if tape[mp] != 0 => target,
+            Instruction::JumpIfZero(_) | Instruction::JumpUnlessZero(_) => ip + 1,
+            Instruction::Output => {
+                output.push(tape[mp]);
+                ip + 1
+            }
+            Instruction::Input => {
+                tape[mp] = *input.next().ok_or(BfError::InputExhausted { ip })?;
+                ip + 1
+            }
+            Instruction::Halt => ip,
+        };
+    }
+
+    match rows.last() {
+        Some(row) if row.instruction == Instruction::Halt => Ok(Execution { rows, output }),
+        _ => Err(BfError::StepLimit),
+    }
+}
+
+/// The memory table: `(clk, mp, mv)` of every row, sorted by `mp`, then
+/// `clk`.
+fn memory_rows(rows: &[Row]) -> Vec<(usize, usize, u8)> {
+    let mut memory: Vec<_> = rows
+        .iter()
+        .enumerate()
+        .map(|(clk, row)| (clk, row.mp, row.mv))
+        .collect();
+    memory.sort_by_key(|(clk, mp, _)| (*mp, *clk));
+    memory
+}
+
+/// `clk + 2^32 * mp + 2^64 * mv`, injective while `clk` and `mp` are below
+/// `2^32`.
+fn element(clk: usize, mp: usize, mv: u8) -> Fp {
+    let shift = Fp::from(1 << 32);
+    Fp::from(clk as u64) + shift * (Fp::from(mp as u64) + shift * Fp::from(mv as u64))
+}
+
+/// Derives the challenge from both tables and the public streams,
+/// Fiat-Shamir style: `Poseidon([Poseidon(processor), Poseidon(memory),
+/// Poseidon(input), Poseidon(output)])`, the streams hashed with
+/// `hash_slice`.
+fn challenge<const STEPS: usize>(rows: &[Row], input: &[Fp], output: &[Fp]) -> Fp {
+    let processor: Vec<Fp> = rows
+        .iter()
+        .enumerate()
+        .map(|(clk, row)| element(clk, row.mp, row.mv))
+        .collect();
+    let memory: Vec<Fp> = memory_rows(rows)
+        .into_iter()
+        .map(|(clk, mp, mv)| element(clk, mp, mv))
+        .collect();
+    poseidon::hash([
+        poseidon::hash::<_, STEPS>(processor.try_into().unwrap()),
+        poseidon::hash::<_, STEPS>(memory.try_into().unwrap()),
+        poseidon::hash_slice(input),
+        poseidon::hash_slice(output),
+    ])
+}
+
+/// Cells of the trace region that the circuit ties to the rest.
+struct TraceCells {
+    processor: Vec<AssignedCell<Fp, Fp>>,
+    memory: Vec<AssignedCell<Fp, Fp>>,
+    gamma: AssignedCell<Fp, Fp>,
+    input: AssignedCell<Fp, Fp>,
+    output: AssignedCell<Fp, Fp>,
+}
+
+#[derive(Clone, Debug)]
+struct BrainfuckConfig {
+    input: Column<Instance>,
+    output: Column<Instance>,
+    clk: Column<Fixed>,
+    ip: Column<Advice>,
+    target: Column<Advice>,
+    flags: [Column<Advice>; OPCODES],
+    mp: Column<Advice>,
+    mv: Column<Advice>,
+    inv: Column<Advice>,
+    wrap: Column<Advice>,
+    element: Column<Advice>,
+    m_clk: Column<Advice>,
+    m_mp: Column<Advice>,
+    m_mv: Column<Advice>,
+    m_element: Column<Advice>,
+    z: Column<Advice>,
+    in_acc: Column<Advice>,
+    out_acc: Column<Advice>,
+    gamma: Column<Advice>,
+    s_row: Selector,
+    s_first: Selector,
+    s_next: Selector,
+    s_last: Selector,
+    s_io: Selector,
+    table_ip: TableColumn,
+    table_opcode: TableColumn,
+    table_target: TableColumn,
+    table_byte: TableColumn,
+    table_index: TableColumn,
+    poseidon: PoseidonConfig<Fp>,
+}
+
+/// The three tables of the execution, side by side, one row per step:
+///
+/// clk ip target flags mp mv inv wrap element | m_clk m_mp m_mv m_element | z in_acc out_acc gamma
+///
+/// - Instruction table: the program, fixed at keygen. Each processor row
+///   looks up `(ip, opcode, target)` in it, with the opcode one-hot in
+///   `flags`.
+/// - Processor table: the transition gate relates each row to the previous
+///   one. `wrap` marks a byte wrapping around and `inv` is the inverse of
+///   `mv`, if any, for the zero test of the jumps. A move or an input leaves
+///   the new `mv` unconstrained here.
+/// - Memory table: the `(clk, mp, mv)` of the processor rows sorted by
+///   `mp`, then `clk`. Within a cell, `mv` may only change from one clock
+///   cycle to the next, where the processor constrains it, and a new cell
+///   starts at zero.
+///
+/// The memory table is a permutation of the processor's, checked by the
+/// running product `z` of `(gamma - element) / (gamma - m_element)`, and
+/// the input and output streams are checked against the instance columns by
+/// evaluating them at `gamma`, each instance value looked up among the
+/// bytes. `gamma` is the Poseidon hash of both tables and of both public
+/// streams, so the prover can choose neither the trace nor the claimed
+/// streams after seeing it.
+struct BrainfuckChip {
+    config: BrainfuckConfig,
+}
+
+impl BrainfuckChip {
+    fn construct(config: BrainfuckConfig) -> Self {
+        BrainfuckChip { config }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> BrainfuckConfig {
+        let input = meta.instance_column();
+        let output = meta.instance_column();
+        let clk = meta.fixed_column();
+        let ip = meta.advice_column();
+        let target = meta.advice_column();
+        let flags = [(); OPCODES].map(|_| meta.advice_column());
+        let mp = meta.advice_column();
+        let mv = meta.advice_column();
+        let inv = meta.advice_column();
+        let wrap = meta.advice_column();
+        let element = meta.advice_column();
+        let m_clk = meta.advice_column();
+        let m_mp = meta.advice_column();
+        let m_mv = meta.advice_column();
+        let m_element = meta.advice_column();
+        let z = meta.advice_column();
+        let in_acc = meta.advice_column();
+        let out_acc = meta.advice_column();
+        let gamma = meta.advice_column();
+        for column in [input, output] {
+            meta.enable_equality(column);
+        }
+        for column in [mv, element, m_element, z, in_acc, out_acc, gamma] {
+            meta.enable_equality(column);
+        }
+
+        let s_row = meta.complex_selector();
+        let s_first = meta.selector();
+        let s_next = meta.complex_selector();
+        let s_last = meta.selector();
+        let s_io = meta.complex_selector();
+        let table_ip = meta.lookup_table_column();
+        let table_opcode = meta.lookup_table_column();
+        let table_target = meta.lookup_table_column();
+        let table_byte = meta.lookup_table_column();
+        let table_index = meta.lookup_table_column();
+
+        let one = || Expression::Constant(Fp::one());
+        let constant = |value: u64| Expression::Constant(Fp::from(value));
+        let pack = |clk: Expression<Fp>, mp: Expression<Fp>, mv: Expression<Fp>| {
+            clk + constant(1 << 32) * (mp + constant(1 << 32) * mv)
+        };
+
+        meta.create_gate("decode", |meta| {
+            let s_row = meta.query_selector(s_row);
+            let flags = flags.map(|flag| meta.query_advice(flag, Rotation::cur()));
+            let clk = meta.query_fixed(clk, Rotation::cur());
+            let mp = meta.query_advice(mp, Rotation::cur());
+            let mv = meta.query_advice(mv, Rotation::cur());
+            let inv = meta.query_advice(inv, Rotation::cur());
+            let wrap = meta.query_advice(wrap, Rotation::cur());
+            let element = meta.query_advice(element, Rotation::cur());
+            let m_clk = meta.query_advice(m_clk, Rotation::cur());
+            let m_mp = meta.query_advice(m_mp, Rotation::cur());
+            let m_mv = meta.query_advice(m_mv, Rotation::cur());
+            let m_element = meta.query_advice(m_element, Rotation::cur());
+
+            let mut constraints: Vec<_> = flags
+                .iter()
+                .map(|flag| flag.clone() * (one() - flag.clone()))
+                .collect();
+            let sum = flags
+                .iter()
+                .fold(Expression::Constant(Fp::zero()), |acc, flag| {
+                    acc + flag.clone()
+                });
+            constraints.push(sum - one());
+            constraints.push(wrap.clone() * (one() - wrap));
+            // If `mv` is nonzero, `inv` has to be its inverse.
+            constraints.push(mv.clone() * (one() - mv.clone() * inv));
+            constraints.push(element - pack(clk, mp, mv));
+            constraints.push(m_element - pack(m_clk, m_mp, m_mv));
+            Constraints::with_selector(s_row, constraints)
+        });
+
+        meta.lookup(|meta| {
+            let s_row = meta.query_selector(s_row);
+            let ip = meta.query_advice(ip, Rotation::cur());
+            let target = meta.query_advice(target, Rotation::cur());
+            let opcode = flags.iter().enumerate().fold(
+                Expression::Constant(Fp::zero()),
+                |acc, (i, flag)| {
+                    acc + constant(i as u64 + 1) * meta.query_advice(*flag, Rotation::cur())
+                },
+            );
+            vec![
+                (s_row.clone() * ip, table_ip),
+                (s_row.clone() * opcode, table_opcode),
+                (s_row * target, table_target),
+            ]
+        });
+
+        for (column, table) in [
+            (mv, table_byte),
+            (m_mv, table_byte),
+            (mp, table_index),
+            (m_mp, table_index),
+            (m_clk, table_index),
+        ] {
+            meta.lookup(|meta| {
+                let s_row = meta.query_selector(s_row);
+                let value = meta.query_advice(column, Rotation::cur());
+                vec![(s_row * value, table)]
+            });
+        }
+
+        meta.create_gate("first row", |meta| {
+            let s_first = meta.query_selector(s_first);
+            let is_output = meta.query_advice(flags[6], Rotation::cur());
+            let [ip, mp, mv, m_mp, m_mv, element, m_element, z, in_acc, out_acc, gamma] = [
+                ip, mp, mv, m_mp, m_mv, element, m_element, z, in_acc, out_acc, gamma,
+            ]
+            .map(|column| meta.query_advice(column, Rotation::cur()));
+
+            Constraints::with_selector(
+                s_first,
+                vec![
+                    ip,
+                    mp,
+                    mv.clone(),
+                    m_mp,
+                    m_mv,
+                    z * (gamma.clone() - m_element) - (gamma - element),
+                    in_acc,
+                    out_acc - is_output * (one() + mv),
+                ],
+            )
+        });
+
+        meta.create_gate("transition", |meta| {
+            let s_next = meta.query_selector(s_next);
+            let [is_inc, is_dec, is_right, is_left, is_jz, is_jnz, is_output, is_input, is_halt] =
+                flags.map(|flag| meta.query_advice(flag, Rotation::prev()));
+            let is_output_cur = meta.query_advice(flags[6], Rotation::cur());
+            let [ip_prev, mp_prev, mv_prev, z_prev, in_prev, out_prev] =
+                [ip, mp, mv, z, in_acc, out_acc]
+                    .map(|column| meta.query_advice(column, Rotation::prev()));
+            let [ip_cur, mp_cur, mv_cur, z_cur, in_cur, out_cur] = [ip, mp, mv, z, in_acc, out_acc]
+                .map(|column| meta.query_advice(column, Rotation::cur()));
+            let target = meta.query_advice(target, Rotation::prev());
+            let inv = meta.query_advice(inv, Rotation::prev());
+            let wrap = meta.query_advice(wrap, Rotation::prev());
+            let element = meta.query_advice(element, Rotation::cur());
+            let m_element = meta.query_advice(m_element, Rotation::cur());
+            let gamma_prev = meta.query_advice(gamma, Rotation::prev());
+            let gamma = meta.query_advice(gamma, Rotation::cur());
+
+            let is_zero = one() - mv_prev.clone() * inv;
+            let advance = is_inc.clone()
+                + is_dec.clone()
+                + is_right.clone()
+                + is_left.clone()
+                + is_output.clone()
+                + is_input.clone();
+            let next_ip = ip_prev.clone() + one();
+            let expected_ip = advance * next_ip.clone()
+                + is_jz.clone()
+                    * (is_zero.clone() * target.clone()
+                        + (one() - is_zero.clone()) * next_ip.clone())
+                + is_jnz.clone() * (is_zero.clone() * next_ip + (one() - is_zero) * target)
+                + is_halt.clone() * ip_prev;
+            let byte = constant(256);
+            let unchanged = is_jz + is_jnz + is_output + is_halt;
+
+            Constraints::with_selector(
+                s_next,
+                vec![
+                    ip_cur - expected_ip,
+                    mp_cur - mp_prev - is_right + is_left,
+                    is_inc
+                        * (mv_cur.clone() - mv_prev.clone() - one() + byte.clone() * wrap.clone()),
+                    is_dec * (mv_cur.clone() - mv_prev.clone() + one() - byte * wrap),
+                    unchanged * (mv_cur.clone() - mv_prev),
+                    z_cur * (gamma.clone() - m_element) - z_prev * (gamma.clone() - element),
+                    in_cur
+                        - in_prev.clone()
+                        - is_input
+                            * (gamma.clone() * in_prev.clone() + one() + mv_cur.clone() - in_prev),
+                    out_cur
+                        - out_prev.clone()
+                        - is_output_cur
+                            * (gamma.clone() * out_prev.clone() + one() + mv_cur - out_prev),
+                    gamma - gamma_prev,
+                ],
+            )
+        });
+
+        meta.create_gate("memory", |meta| {
+            let s_next = meta.query_selector(s_next);
+            let [clk_prev, mp_prev, mv_prev] =
+                [m_clk, m_mp, m_mv].map(|column| meta.query_advice(column, Rotation::prev()));
+            let [clk_cur, mp_cur, mv_cur] =
+                [m_clk, m_mp, m_mv].map(|column| meta.query_advice(column, Rotation::cur()));
+
+            let new_cell = mp_cur - mp_prev;
+            Constraints::with_selector(
+                s_next,
+                vec![
+                    new_cell.clone() * (one() - new_cell.clone()),
+                    new_cell.clone() * mv_cur.clone(),
+                    (one() - new_cell) * (clk_cur - clk_prev - one()) * (mv_cur - mv_prev),
+                ],
+            )
+        });
+
+        // Within a cell, the clock strictly increases.
+        meta.lookup(|meta| {
+            let s_next = meta.query_selector(s_next);
+            let [clk_prev, mp_prev] =
+                [m_clk, m_mp].map(|column| meta.query_advice(column, Rotation::prev()));
+            let [clk_cur, mp_cur] =
+                [m_clk, m_mp].map(|column| meta.query_advice(column, Rotation::cur()));
+            let gap = (one() - mp_cur + mp_prev) * (clk_cur - clk_prev - one());
+            vec![(s_next * gap, table_index)]
+        });
+
+        meta.create_gate("halted", |meta| {
+            let s_last = meta.query_selector(s_last);
+            let is_halt = meta.query_advice(flags[OPCODES - 1], Rotation::cur());
+            Constraints::with_selector(s_last, vec![one() - is_halt])
+        });
+
+        // Horner evaluation of an instance stream at `gamma`, one byte per row,
+        // with `in_acc` as the accumulator.
+        meta.create_gate("io", |meta| {
+            let s_io = meta.query_selector(s_io);
+            let acc_cur = meta.query_advice(in_acc, Rotation::cur());
+            let acc_next = meta.query_advice(in_acc, Rotation::next());
+            let value = meta.query_advice(mv, Rotation::next());
+            let gamma = meta.query_advice(gamma, Rotation::cur());
+            Constraints::with_selector(s_io, vec![acc_next - acc_cur * gamma - one() - value])
+        });
+
+        meta.lookup(|meta| {
+            let s_io = meta.query_selector(s_io);
+            let value = meta.query_advice(mv, Rotation::next());
+            vec![(s_io * value, table_byte)]
+        });
+
+        let poseidon = PoseidonChip::configure(meta);
+
+        BrainfuckConfig {
+            input,
+            output,
+            clk,
+            ip,
+            target,
+            flags,
+            mp,
+            mv,
+            inv,
+            wrap,
+            element,
+            m_clk,
+            m_mp,
+            m_mv,
+            m_element,
+            z,
+            in_acc,
+            out_acc,
+            gamma,
+            s_row,
+            s_first,
+            s_next,
+            s_last,
+            s_io,
+            table_ip,
+            table_opcode,
+            table_target,
+            table_byte,
+            table_index,
+            poseidon,
+        }
+    }
+
+    fn poseidon(&self) -> PoseidonChip<Fp> {
+        PoseidonChip::construct(self.config.poseidon.clone())
+    }
+
+    /// Loads the program, after an all-zero row for disabled lookups, the
+    /// bytes and the indices `0..=steps`, which bound pointers and clocks.
+    fn load_tables(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        program: &[Instruction],
+        steps: usize,
+    ) -> Result<(), Error> {
+        let config = &self.config;
+        layouter.assign_table(
+            || "program",
+            |mut table| {
+                let rows = std::iter::once((0, 0, 0)).chain(program.iter().enumerate().map(
+                    |(ip, instruction)| (ip as u64, instruction.opcode(), instruction.target()),
+                ));
+                for (row, (ip, opcode, target)) in rows.enumerate() {
+                    for (column, value) in [
+                        (config.table_ip, ip),
+                        (config.table_opcode, opcode),
+                        (config.table_target, target),
+                    ] {
+                        table.assign_cell(
+                            || "program",
+                            column,
+                            row,
+                            || Value::known(Fp::from(value)),
+                        )?;
+                    }
+                }
+                Ok(())
+            },
+        )?;
+
+        for (name, column, len) in [
+            ("byte", config.table_byte, 256),
+            ("index", config.table_index, steps + 1),
+        ] {
+            layouter.assign_table(
+                || name,
+                |mut table| {
+                    for value in 0..len {
+                        table.assign_cell(
+                            || name,
+                            column,
+                            value,
+                            || Value::known(Fp::from(value as u64)),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Assigns the processor and memory tables of `rows` side by side, with
+    /// the running product and the stream evaluations at `gamma`. The
+    /// product has to end at one.
+    fn assign_trace(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        rows: &Value<Vec<Row>>,
+        steps: usize,
+        gamma: Value<Fp>,
+    ) -> Result<TraceCells, Error> {
+        let config = &self.config;
+        let memory = rows.as_ref().map(|rows| memory_rows(rows));
+
+        layouter.assign_region(
+            || "trace",
+            |mut region| {
+                let mut processor = Vec::with_capacity(steps);
+                let mut memory_cells = Vec::with_capacity(steps);
+                let mut gamma_cell = None;
+                let mut accumulators = None;
+                let mut z = Value::known(Fp::one());
+                let mut in_acc = Value::known(Fp::zero());
+                let mut out_acc = Value::known(Fp::zero());
+
+                for clk in 0..steps {
+                    config.s_row.enable(&mut region, clk)?;
+                    if clk == 0 {
+                        config.s_first.enable(&mut region, clk)?;
+                    } else {
+                        config.s_next.enable(&mut region, clk)?;
+                    }
+                    if clk + 1 == steps {
+                        config.s_last.enable(&mut region, clk)?;
+                    }
+                    region.assign_fixed(
+                        || "clk",
+                        config.clk,
+                        clk,
+                        || Value::known(Fp::from(clk as u64)),
+                    )?;
+
+                    let row = rows.as_ref().map(|rows| rows[clk].clone());
+                    let instruction = row.as_ref().map(|row| row.instruction);
+                    let mv = row.as_ref().map(|row| Fp::from(row.mv as u64));
+                    region.assign_advice(
+                        || "ip",
+                        config.ip,
+                        clk,
+                        || row.as_ref().map(|row| Fp::from(row.ip as u64)),
+                    )?;
+                    region.assign_advice(
+                        || "target",
+                        config.target,
+                        clk,
+                        || instruction.map(|instruction| Fp::from(instruction.target())),
+                    )?;
+                    for (i, flag) in config.flags.iter().enumerate() {
+                        region.assign_advice(
+                            || "flag",
+                            *flag,
+                            clk,
+                            || {
+                                instruction.map(|instruction| {
+                                    Fp::from(instruction.opcode() == i as u64 + 1)
+                                })
+                            },
+                        )?;
+                    }
+                    region.assign_advice(
+                        || "mp",
+                        config.mp,
+                        clk,
+                        || row.as_ref().map(|row| Fp::from(row.mp as u64)),
+                    )?;
+                    region.assign_advice(|| "mv", config.mv, clk, || mv)?;
+                    region.assign_advice(
+                        || "inv",
+                        config.inv,
+                        clk,
+                        || mv.map(|mv| mv.invert().unwrap_or(Fp::zero())),
+                    )?;
+                    region.assign_advice(
+                        || "wrap",
+                        config.wrap,
+                        clk,
+                        || {
+                            row.as_ref().map(|row| {
+                                Fp::from(match row.instruction {
+                                    Instruction::Inc => row.mv == u8::MAX,
+                                    Instruction::Dec => row.mv == 0,
+                                    _ => false,
+                                })
+                            })
+                        },
+                    )?;
+                    let e = row.as_ref().map(|row| element(clk, row.mp, row.mv));
+                    processor.push(region.assign_advice(
+                        || "element",
+                        config.element,
+                        clk,
+                        || e,
+                    )?);
+
+                    let m = memory.as_ref().map(|memory| memory[clk]);
+                    region.assign_advice(
+                        || "m_clk",
+                        config.m_clk,
+                        clk,
+                        || m.map(|(clk, _, _)| Fp::from(clk as u64)),
+                    )?;
+                    region.assign_advice(
+                        || "m_mp",
+                        config.m_mp,
+                        clk,
+                        || m.map(|(_, mp, _)| Fp::from(mp as u64)),
+                    )?;
+                    region.assign_advice(
+                        || "m_mv",
+                        config.m_mv,
+                        clk,
+                        || m.map(|(_, _, mv)| Fp::from(mv as u64)),
+                    )?;
+                    let m_e = m.map(|(clk, mp, mv)| element(clk, mp, mv));
+                    memory_cells.push(region.assign_advice(
+                        || "m_element",
+                        config.m_element,
+                        clk,
+                        || m_e,
+                    )?);
+
+                    z = z.zip(gamma).zip(e).zip(m_e).map(|(((z, gamma), e), m_e)| {
+                        z * (gamma - e) * (gamma - m_e).invert().unwrap_or(Fp::zero())
+                    });
+                    if clk > 0 {
+                        let read = rows
+                            .as_ref()
+                            .map(|rows| rows[clk - 1].instruction == Instruction::Input);
+                        in_acc = absorb(in_acc, gamma, mv, read);
+                    }
+                    let written = instruction.map(|instruction| instruction == Instruction::Output);
+                    out_acc = absorb(out_acc, gamma, mv, written);
+
+                    let cells = [
+                        (config.z, z),
+                        (config.in_acc, in_acc),
+                        (config.out_acc, out_acc),
+                    ]
+                    .map(|(column, value)| {
+                        region.assign_advice(|| "accumulator", column, clk, || value)
+                    });
+                    let [z, input, output] = cells;
+                    accumulators = Some((z?, input?, output?));
+                    let gamma = region.assign_advice(|| "gamma", config.gamma, clk, || gamma)?;
+                    gamma_cell.get_or_insert(gamma);
+                }
+
+                let (z, input, output) = accumulators.expect("at least one step");
+                region.constrain_constant(z.cell(), Fp::one())?;
+                Ok(TraceCells {
+                    processor,
+                    memory: memory_cells,
+                    gamma: gamma_cell.expect("at least one step"),
+                    input,
+                    output,
+                })
+            },
+        )
+    }
+
+    /// Copies the first `len` rows of an instance column into advice.
+    fn load_stream(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        column: Column<Instance>,
+        len: usize,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        // `MockProver` cannot locate failures next to an empty region.
+        if len == 0 {
+            return Ok(vec![]);
+        }
+        let config = &self.config;
+        layouter.assign_region(
+            || "stream",
+            |mut region| {
+                (0..len)
+                    .map(|i| {
+                        region.assign_advice_from_instance(|| "value", column, i, config.mv, i)
+                    })
+                    .collect()
+            },
+        )
+    }
+
+    /// Evaluates a stream at `gamma`, as the trace does for its streams,
+    /// checking that every value is a byte.
+    fn evaluate(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        stream: &[AssignedCell<Fp, Fp>],
+        gamma: &AssignedCell<Fp, Fp>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "evaluate",
+            |mut region| {
+                let mut acc =
+                    region.assign_advice_from_constant(|| "acc", config.in_acc, 0, Fp::zero())?;
+                for (i, value) in stream.iter().enumerate() {
+                    config.s_io.enable(&mut region, i)?;
+                    gamma.copy_advice(|| "gamma", &mut region, config.gamma, i)?;
+                    let value = value.copy_advice(|| "value", &mut region, config.mv, i + 1)?;
+                    let next = absorb(
+                        acc.value().copied(),
+                        gamma.value().copied(),
+                        value.value().copied(),
+                        Value::known(true),
+                    );
+                    acc = region.assign_advice(|| "acc", config.in_acc, i + 1, || next)?;
+                }
+                Ok(acc)
+            },
+        )
+    }
+}
+
+/// One Horner step of a stream evaluation, `gamma * acc + 1 + byte`. The
+/// offset keeps zero bytes from vanishing at the front of a stream.
+fn absorb(acc: Value<Fp>, gamma: Value<Fp>, byte: Value<Fp>, absorb: Value<bool>) -> Value<Fp> {
+    acc.zip(gamma)
+        .zip(byte)
+        .zip(absorb)
+        .map(|(((acc, gamma), byte), absorb)| {
+            if absorb {
+                gamma * acc + Fp::one() + byte
+            } else {
+                acc
+            }
+        })
+}
+
+/// Proves that `program`, fixed at keygen, reads the public input and
+/// writes the public output within `STEPS` steps. Stream lengths are fixed
+/// at keygen too.
+struct BrainfuckCircuit<const STEPS: usize> {
+    program: Vec<Instruction>,
+    input_len: usize,
+    output_len: usize,
+    rows: Value<Vec<Row>>,
+}
+
+impl<const STEPS: usize> Circuit<Fp> for BrainfuckCircuit<STEPS> {
+    type Config = BrainfuckConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        BrainfuckCircuit {
+            program: self.program.clone(),
+            input_len: self.input_len,
+            output_len: self.output_len,
+            rows: Value::unknown(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        BrainfuckChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = BrainfuckChip::construct(config.clone());
+        chip.load_tables(layouter.namespace(|| "tables"), &self.program, STEPS)?;
+
+        let input = chip.load_stream(
+            layouter.namespace(|| "load input"),
+            config.input,
+            self.input_len,
+        )?;
+        let output = chip.load_stream(
+            layouter.namespace(|| "load output"),
+            config.output,
+            self.output_len,
+        )?;
+
+        let values = |stream: &[AssignedCell<Fp, Fp>]| -> Value<Vec<Fp>> {
+            stream.iter().map(|cell| cell.value().copied()).collect()
+        };
+        let gamma = self
+            .rows
+            .as_ref()
+            .zip(values(&input))
+            .zip(values(&output))
+            .map(|((rows, input), output)| challenge::<STEPS>(rows, &input, &output));
+        let trace = chip.assign_trace(layouter.namespace(|| "trace"), &self.rows, STEPS, gamma)?;
+
+        let poseidon = chip.poseidon();
+        let processor = poseidon.hash::<STEPS>(
+            layouter.namespace(|| "hash processor"),
+            &trace.processor.try_into().unwrap(),
+        )?;
+        let memory = poseidon.hash::<STEPS>(
+            layouter.namespace(|| "hash memory"),
+            &trace.memory.try_into().unwrap(),
+        )?;
+        let input_hash = poseidon.hash_slice(layouter.namespace(|| "hash input"), &input)?;
+        let output_hash = poseidon.hash_slice(layouter.namespace(|| "hash output"), &output)?;
+        let gamma = poseidon.hash(
+            layouter.namespace(|| "gamma"),
+            &[processor, memory, input_hash, output_hash],
+        )?;
+
+        let input = chip.evaluate(layouter.namespace(|| "input"), &input, &trace.gamma)?;
+        let output = chip.evaluate(layouter.namespace(|| "output"), &output, &trace.gamma)?;
+
+        layouter.assign_region(
+            || "link",
+            |mut region| {
+                region.constrain_equal(gamma.cell(), trace.gamma.cell())?;
+                region.constrain_equal(input.cell(), trace.input.cell())?;
+                region.constrain_equal(output.cell(), trace.output.cell())
+            },
+        )
+    }
+}
+
+const HELLO_WORLD: &str = "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+
+fn bytes(bytes: &[u8]) -> Vec<Fp> {
+    bytes.iter().map(|byte| Fp::from(*byte as u64)).collect()
+}
+
+fn verify<const STEPS: usize>(
+    k: u32,
+    program: &[Instruction],
+    rows: Vec<Row>,
+    input: &[u8],
+    output: &[u8],
+) -> Result<(), Vec<halo2_proofs::dev::VerifyFailure>> {
+    verify_streams::<STEPS>(k, program, rows, bytes(input), bytes(output))
+}
+
+/// `verify` with streams of any field elements, bytes or not.
+fn verify_streams<const STEPS: usize>(
+    k: u32,
+    program: &[Instruction],
+    rows: Vec<Row>,
+    input: Vec<Fp>,
+    output: Vec<Fp>,
+) -> Result<(), Vec<halo2_proofs::dev::VerifyFailure>> {
+    let circuit = BrainfuckCircuit::<STEPS> {
+        program: program.to_vec(),
+        input_len: input.len(),
+        output_len: output.len(),
+        rows: Value::known(rows),
+    };
+    MockProver::run(k, &circuit, vec![input, output])
+        .unwrap()
+        .verify()
+}
+
+fn test_interpreter() {
+    use Instruction::*;
+    assert_eq!(
+        parse("+[-]. comment").unwrap(),
+        vec![Inc, JumpIfZero(4), Dec, JumpUnlessZero(2), Output, Halt]
+    );
+    assert_eq!(parse("[[]").unwrap_err(), BfError::UnmatchedBracket(0));
+    assert_eq!(parse("[]]").unwrap_err(), BfError::UnmatchedBracket(2));
+
+    let program = parse(HELLO_WORLD).unwrap();
+    assert_eq!(run(&program, &[], 1024).unwrap().output, b"Hello World!\n");
+    assert_eq!(run(&program, &[], 512).unwrap_err(), BfError::StepLimit);
+    assert_eq!(
+        run(&parse("<").unwrap(), &[], 4).unwrap_err(),
+        BfError::TapeUnderflow { ip: 0 }
+    );
+    assert_eq!(
+        run(&parse(",,").unwrap(), &[1], 4).unwrap_err(),
+        BfError::InputExhausted { ip: 1 }
+    );
+    // Bytes wrap around.
+    let rows = run(&parse("-.").unwrap(), &[], 4).unwrap();
+    assert_eq!(rows.output, vec![255]);
+}
+
+fn test_hello_world() {
+    const STEPS: usize = 1024;
+    let k = 17;
+    let program = parse(HELLO_WORLD).unwrap();
+    let execution = run(&program, &[], STEPS).unwrap();
+    let output = b"Hello World!\n";
+    assert_eq!(
+        verify::<STEPS>(k, &program, execution.rows.clone(), &[], output),
+        Ok(())
+    );
+
+    // A tampered output.
+    assert!(verify::<STEPS>(k, &program, execution.rows.clone(), &[], b"Hello World?\n").is_err());
+
+    // An output with the evaluation of the honest one at its `gamma`: `J`
+    // for `H`, and the last element making up the difference. `gamma`
+    // hashes the claimed output too, so it moves, and the last element is
+    // no byte anyway.
+    let honest = bytes(output);
+    let gamma = challenge::<STEPS>(&execution.rows, &[], &honest);
+    let evaluate = |stream: &[Fp]| {
+        stream
+            .iter()
+            .fold(Fp::zero(), |acc, value| gamma * acc + Fp::one() + value)
+    };
+    let mut claimed = honest.clone();
+    claimed[0] += Fp::from(2);
+    let last = claimed.len() - 1;
+    claimed[last] -= Fp::from(2) * gamma.pow_vartime([last as u64]);
+    assert_ne!(claimed, honest);
+    assert_eq!(evaluate(&claimed), evaluate(&honest));
+    assert!(verify_streams::<STEPS>(k, &program, execution.rows.clone(), vec![], claimed).is_err());
+
+    // A trace forged to print a lower-case `h`, consistently from there on.
+    let mut rows = execution.rows;
+    let first = rows
+        .iter()
+        .position(|row| row.instruction == Instruction::Output)
+        .unwrap();
+    let mp = rows[first].mp;
+    for row in &mut rows[first..] {
+        if row.mp == mp {
+            row.mv += b'h' - b'H';
+        }
+    }
+    let forged = run_output(&rows);
+    assert_eq!(&forged[..2], b"he");
+    assert!(verify::<STEPS>(k, &program, rows, &[], &forged).is_err());
+}
+
+/// The bytes written by a trace.
+fn run_output(rows: &[Row]) -> Vec<u8> {
+    rows.iter()
+        .filter(|row| row.instruction == Instruction::Output)
+        .map(|row| row.mv)
+        .collect()
+}
+
+fn test_io() {
+    const STEPS: usize = 64;
+    let k = 13;
+
+    // Echoes its input up to a zero byte.
+    let program = parse(",[.,]").unwrap();
+    let input = b"halo2\0";
+    let execution = run(&program, input, STEPS).unwrap();
+    assert_eq!(execution.output, b"halo2");
+    let rows = execution.rows;
+    assert_eq!(
+        verify::<STEPS>(k, &program, rows.clone(), input, b"halo2"),
+        Ok(())
+    );
+
+    // Claimed input that was not read, or read but not claimed.
+    assert!(verify::<STEPS>(k, &program, rows.clone(), b"halo3\0", b"halo2").is_err());
+    assert!(verify::<STEPS>(k, &program, rows.clone(), b"halo2\0\0", b"halo2").is_err());
+    assert!(verify::<STEPS>(k, &program, rows, b"halo2", b"halo2").is_err());
+
+    // A stale read: after moving back, the cell claims its old value.
+    let program = parse("+>+<.").unwrap();
+    let mut rows = run(&program, &[], 8).unwrap().rows;
+    assert_eq!(run_output(&rows), vec![1]);
+    assert_eq!(verify::<8>(10, &program, rows.clone(), &[], &[1]), Ok(()));
+    for row in &mut rows[4..] {
+        row.mv = 0;
+    }
+    assert!(verify::<8>(10, &program, rows, &[], &[0]).is_err());
+}
+
+fn main() {
+    test_interpreter();
+    test_io();
+    test_hello_world();
+}
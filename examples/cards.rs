@@ -0,0 +1,228 @@
+use halo2_learning::{
+    poseidon,
+    shuffle::{ShuffleChip, ShuffleConfig},
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::Fp,
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Instance},
+};
+
+const DECK: usize = 52;
+const K: u32 = 14;
+
+/// Cards are numbered `13 * suit + rank`, with the suits in the order
+/// spades, hearts, diamonds, clubs and the ranks from the ace up, so a fresh
+/// deck is `0..52`.
+fn fresh_deck() -> [u64; DECK] {
+    std::array::from_fn(|i| i as u64)
+}
+
+/// Fisher-Yates over a xorshift generator: a stand-in for the shuffler's
+/// private randomness.
+fn shuffle(deck: &[u64; DECK], seed: u64) -> [u64; DECK] {
+    let mut state = seed | 1;
+    let mut deck = *deck;
+    for i in (1..DECK).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        deck.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+    deck
+}
+
+/// `Poseidon(salt, deck...)`, hiding the order for a random salt.
+fn commitment(salt: Fp, deck: &[u64; DECK]) -> Fp {
+    let message: Vec<Fp> = std::iter::once(salt)
+        .chain(deck.iter().map(|card| Fp::from(*card)))
+        .collect();
+    poseidon::hash_slice(&message)
+}
+
+#[derive(Clone, Debug)]
+struct DeckConfig {
+    advice: Column<Advice>,
+    instance: Column<Instance>,
+    shuffle: ShuffleConfig<Fp>,
+}
+
+/// Proves that the deck committed to in instance row 1 is a permutation of
+/// the one committed to in row 0, both orders and salts private.
+///
+/// Each card is assigned once and copied both into its commitment and into
+/// the grand product of `ShuffleChip`, so the copy constraints tie the
+/// hash and the grand product together: the cards committed to are the ones
+/// shuffled.
+struct DeckCircuit {
+    input: [Value<u64>; DECK],
+    input_salt: Value<Fp>,
+    output: [Value<u64>; DECK],
+    output_salt: Value<Fp>,
+}
+
+impl DeckCircuit {
+    fn new(input: &[u64; DECK], input_salt: Fp, output: &[u64; DECK], output_salt: Fp) -> Self {
+        DeckCircuit {
+            input: input.map(Value::known),
+            input_salt: Value::known(input_salt),
+            output: output.map(Value::known),
+            output_salt: Value::known(output_salt),
+        }
+    }
+}
+
+/// Assigns `salt` followed by the cards of `deck`.
+fn load_deck(
+    mut layouter: impl Layouter<Fp>,
+    column: Column<Advice>,
+    salt: Value<Fp>,
+    deck: &[Value<u64>; DECK],
+) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+    layouter.assign_region(
+        || "deck",
+        |mut region| {
+            let salt = region.assign_advice(|| "salt", column, 0, || salt)?;
+            let cards = deck.iter().enumerate().map(|(i, card)| {
+                region.assign_advice(|| "card", column, i + 1, || card.map(Fp::from))
+            });
+            std::iter::once(Ok(salt)).chain(cards).collect()
+        },
+    )
+}
+
+impl Circuit<Fp> for DeckCircuit {
+    type Config = DeckConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        DeckCircuit {
+            input: [Value::unknown(); DECK],
+            input_salt: Value::unknown(),
+            output: [Value::unknown(); DECK],
+            output_salt: Value::unknown(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let advice = meta.advice_column();
+        meta.enable_equality(advice);
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let shuffle = ShuffleChip::configure(meta);
+        DeckConfig {
+            advice,
+            instance,
+            shuffle,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = ShuffleChip::construct(config.shuffle);
+        let poseidon = chip.poseidon();
+
+        let input = load_deck(
+            layouter.namespace(|| "input"),
+            config.advice,
+            self.input_salt,
+            &self.input,
+        )?;
+        let output = load_deck(
+            layouter.namespace(|| "output"),
+            config.advice,
+            self.output_salt,
+            &self.output,
+        )?;
+
+        for (row, deck) in [&input, &output].into_iter().enumerate() {
+            let mut layouter = layouter.namespace(|| format!("commitment {}", row));
+            let commitment = poseidon.hash_slice(layouter.namespace(|| "hash"), deck)?;
+            layouter.constrain_instance(commitment.cell(), config.instance, row)?;
+        }
+
+        // The salts are not cards.
+        chip.assert_shuffle(layouter.namespace(|| "shuffle"), &input[1..], &output[1..])
+    }
+}
+
+fn verify(
+    circuit: &DeckCircuit,
+    input: Fp,
+    output: Fp,
+) -> Result<(), Vec<halo2_proofs::dev::VerifyFailure>> {
+    MockProver::run(K, circuit, vec![vec![input, output]])
+        .unwrap()
+        .verify()
+}
+
+fn test_deck() {
+    let fresh = fresh_deck();
+    let deck = shuffle(&fresh, 0xdec4);
+    assert_ne!(deck, fresh);
+    let mut sorted = deck;
+    sorted.sort();
+    assert_eq!(sorted, fresh);
+
+    // Anyone can open the commitment to a fresh deck with a zero salt.
+    let fresh_commitment = commitment(Fp::zero(), &fresh);
+    let salt = Fp::from(0x5a17);
+    let circuit = DeckCircuit::new(&fresh, Fp::zero(), &deck, salt);
+    assert_eq!(
+        verify(&circuit, fresh_commitment, commitment(salt, &deck)),
+        Ok(())
+    );
+
+    // Leaving the order as it is still shuffles.
+    let circuit = DeckCircuit::new(&fresh, Fp::zero(), &fresh, salt);
+    assert_eq!(
+        verify(&circuit, fresh_commitment, commitment(salt, &fresh)),
+        Ok(())
+    );
+
+    // A second player reshuffles the committed deck.
+    let reshuffled = shuffle(&deck, 0xbeef);
+    let second_salt = Fp::from(0x5a18);
+    let circuit = DeckCircuit::new(&deck, salt, &reshuffled, second_salt);
+    assert_eq!(
+        verify(
+            &circuit,
+            commitment(salt, &deck),
+            commitment(second_salt, &reshuffled),
+        ),
+        Ok(())
+    );
+
+    // Commitments to anything else.
+    let circuit = DeckCircuit::new(&fresh, Fp::zero(), &deck, salt);
+    let other = shuffle(&fresh, 0xd0d0);
+    assert!(verify(&circuit, fresh_commitment, commitment(salt, &other)).is_err());
+    assert!(verify(&circuit, fresh_commitment, commitment(Fp::one(), &deck)).is_err());
+    assert!(verify(
+        &circuit,
+        commitment(Fp::one(), &fresh),
+        commitment(salt, &deck)
+    )
+    .is_err());
+
+    // Palming an ace: the ace of spades in place of the two of hearts.
+    let mut stacked = deck;
+    let two = stacked.iter().position(|card| *card == 14).unwrap();
+    stacked[two] = 0;
+    let circuit = DeckCircuit::new(&fresh, Fp::zero(), &stacked, salt);
+    assert!(verify(&circuit, fresh_commitment, commitment(salt, &stacked)).is_err());
+
+    // Swapping a card for one outside the deck.
+    let mut marked = deck;
+    marked[0] = 52;
+    let circuit = DeckCircuit::new(&fresh, Fp::zero(), &marked, salt);
+    assert!(verify(&circuit, fresh_commitment, commitment(salt, &marked)).is_err());
+}
+
+fn main() {
+    test_deck();
+}
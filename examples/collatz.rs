@@ -0,0 +1,330 @@
+use halo2_learning::{
+    poseidon::{self, PoseidonChip, PoseidonConfig},
+    range::{RangeCheckChip, RangeCheckConfig},
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::{group::ff::Field, Fp},
+    plonk::{
+        Advice, Circuit, Column, ConstraintSystem, Constraints, Error, Expression, Instance,
+        Selector,
+    },
+    poly::Rotation,
+};
+
+/// Width the quotients are range-checked to, which bounds every value of
+/// the sequence by `2^(QUOTIENT_BITS + 1)`.
+const QUOTIENT_BITS: usize = 64;
+
+fn next(x: u128) -> u128 {
+    match x {
+        1 => 1,
+        x if x % 2 == 0 => x / 2,
+        x => 3 * x + 1,
+    }
+}
+
+/// `start` and the next `steps` values, staying at 1 once it is reached.
+fn trajectory(start: u128, steps: usize) -> Vec<u128> {
+    let mut values = vec![start];
+    for _ in 0..steps {
+        values.push(next(*values.last().unwrap()));
+    }
+    values
+}
+
+fn to_field(x: u128) -> Fp {
+    Fp::from_raw([x as u64, (x >> 64) as u64, 0, 0])
+}
+
+#[derive(Clone, Debug)]
+struct CollatzConfig {
+    instance: Column<Instance>,
+    x: Column<Advice>,
+    parity: Column<Advice>,
+    quotient: Column<Advice>,
+    done: Column<Advice>,
+    inv: Column<Advice>,
+    s_step: Selector,
+    poseidon: PoseidonConfig<Fp>,
+    range: RangeCheckConfig,
+}
+
+/// One row per step, the next value in the row below:
+///
+/// x parity quotient done inv
+///
+/// with `parity` and `done` boolean, `x = 2 * quotient + parity` and `done`
+/// set exactly when `x = 1`, through `inv = 1 / (x - 1)` otherwise. The next
+/// value is then
+///
+/// - 1 when `done`: the padding once the sequence has terminated,
+/// - `3x + 1` when `x` is odd,
+/// - `quotient` when it is even.
+///
+/// The range check on `quotient` makes the split into parity and quotient
+/// unique. The last row must hold 1.
+struct CollatzChip {
+    config: CollatzConfig,
+}
+
+impl CollatzChip {
+    fn construct(config: CollatzConfig) -> Self {
+        CollatzChip { config }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> CollatzConfig {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let [x, parity, quotient, done, inv] = [(); 5].map(|_| meta.advice_column());
+        meta.enable_equality(x);
+        meta.enable_equality(quotient);
+        let s_step = meta.selector();
+
+        meta.create_gate("collatz step", |meta| {
+            let s_step = meta.query_selector(s_step);
+            let x_cur = meta.query_advice(x, Rotation::cur());
+            let x_next = meta.query_advice(x, Rotation::next());
+            let parity = meta.query_advice(parity, Rotation::cur());
+            let quotient = meta.query_advice(quotient, Rotation::cur());
+            let done = meta.query_advice(done, Rotation::cur());
+            let inv = meta.query_advice(inv, Rotation::cur());
+            let one = Expression::Constant(Fp::one());
+            let three = Expression::Constant(Fp::from(3));
+
+            let odd = three * x_cur.clone() + one.clone();
+            let step = parity.clone() * odd + (one.clone() - parity.clone()) * quotient.clone();
+            Constraints::with_selector(
+                s_step,
+                vec![
+                    ("parity", parity.clone() * (one.clone() - parity.clone())),
+                    ("done", done.clone() * (one.clone() - done.clone())),
+                    (
+                        "split",
+                        x_cur.clone() - quotient * Expression::Constant(Fp::from(2)) - parity,
+                    ),
+                    (
+                        "x = 1 iff done",
+                        (x_cur.clone() - one.clone()) * inv - (one.clone() - done.clone()),
+                    ),
+                    ("done only at 1", (x_cur - one.clone()) * done.clone()),
+                    ("next", x_next - done.clone() - (one - done) * step),
+                ],
+            )
+        });
+
+        // Also enables the constant column the range check needs.
+        let poseidon = PoseidonChip::configure(meta);
+        let range = RangeCheckChip::configure(meta);
+
+        CollatzConfig {
+            instance,
+            x,
+            parity,
+            quotient,
+            done,
+            inv,
+            s_step,
+            poseidon,
+            range,
+        }
+    }
+
+    fn load_private(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        value: Value<Fp>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        layouter.assign_region(
+            || "load private",
+            |mut region| region.assign_advice(|| "value", self.config.x, 0, || value),
+        )
+    }
+
+    /// Assigns `steps` steps from `start` through the values of `trace`,
+    /// and returns the quotients.
+    fn assign_steps(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        start: &AssignedCell<Fp, Fp>,
+        trace: &Value<Vec<u128>>,
+        steps: usize,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "collatz steps",
+            |mut region| {
+                let x_at = |row: usize| trace.as_ref().map(|trace| trace[row]);
+                start.copy_advice(|| "start", &mut region, config.x, 0)?;
+
+                let mut quotients = Vec::with_capacity(steps);
+                for row in 0..steps {
+                    config.s_step.enable(&mut region, row)?;
+                    let x = x_at(row);
+                    region.assign_advice(
+                        || "parity",
+                        config.parity,
+                        row,
+                        || x.map(|x| Fp::from((x % 2) as u64)),
+                    )?;
+                    quotients.push(region.assign_advice(
+                        || "quotient",
+                        config.quotient,
+                        row,
+                        || x.map(|x| to_field(x / 2)),
+                    )?);
+                    region.assign_advice(
+                        || "done",
+                        config.done,
+                        row,
+                        || x.map(|x| Fp::from(x == 1)),
+                    )?;
+                    region.assign_advice(
+                        || "inv",
+                        config.inv,
+                        row,
+                        || x.map(|x| (to_field(x) - Fp::one()).invert().unwrap_or(Fp::zero())),
+                    )?;
+                    // The sequence has reached 1 by the last row.
+                    if row + 1 == steps {
+                        region.assign_advice_from_constant(|| "one", config.x, steps, Fp::one())?;
+                    } else {
+                        let x_next = x_at(row + 1).map(to_field);
+                        region.assign_advice(|| "x", config.x, row + 1, || x_next)?;
+                    }
+                }
+                Ok(quotients)
+            },
+        )
+    }
+}
+
+/// Proves that the start value in the public commitment
+/// `Poseidon(start, salt)` reaches 1 within `STEPS` steps. `trace` holds the
+/// `STEPS + 1` values from the start on.
+#[derive(Default)]
+struct CollatzCircuit<const STEPS: usize> {
+    trace: Value<Vec<u128>>,
+    salt: Value<Fp>,
+}
+
+impl<const STEPS: usize> CollatzCircuit<STEPS> {
+    fn new(start: u128, salt: Fp) -> Self {
+        CollatzCircuit {
+            trace: Value::known(trajectory(start, STEPS)),
+            salt: Value::known(salt),
+        }
+    }
+}
+
+impl<const STEPS: usize> Circuit<Fp> for CollatzCircuit<STEPS> {
+    type Config = CollatzConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        CollatzChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = CollatzChip::construct(config.clone());
+        let range = RangeCheckChip::construct(config.range.clone());
+        let poseidon = PoseidonChip::construct(config.poseidon.clone());
+        range.load_table(layouter.namespace(|| "range table"))?;
+
+        let start = self.trace.as_ref().map(|trace| to_field(trace[0]));
+        let start = chip.load_private(layouter.namespace(|| "start"), start)?;
+        let quotients =
+            chip.assign_steps(layouter.namespace(|| "steps"), &start, &self.trace, STEPS)?;
+        range.range_check(
+            layouter.namespace(|| "quotients"),
+            &quotients,
+            QUOTIENT_BITS,
+        )?;
+
+        let salt = chip.load_private(layouter.namespace(|| "salt"), self.salt)?;
+        let commitment = poseidon.hash(layouter.namespace(|| "commitment"), &[start, salt])?;
+        layouter.constrain_instance(commitment.cell(), config.instance, 0)
+    }
+}
+
+fn commitment(start: u128, salt: Fp) -> Fp {
+    poseidon::hash([to_field(start), salt])
+}
+
+fn verify<const STEPS: usize>(
+    circuit: &CollatzCircuit<STEPS>,
+    commitment: Fp,
+) -> Result<(), Vec<halo2_proofs::dev::VerifyFailure>> {
+    MockProver::run(17, circuit, vec![vec![commitment]])
+        .unwrap()
+        .verify()
+}
+
+fn test_collatz() {
+    const STEPS: usize = 128;
+    let salt = Fp::from(0xc011a72);
+
+    // 27 takes 111 steps, peaking at 9232; 1 terminates straight away.
+    assert_eq!(trajectory(27, 111).last(), Some(&1));
+    assert_ne!(trajectory(27, 110).last(), Some(&1));
+    for start in [1, 2, 7, 27, 97] {
+        let circuit = CollatzCircuit::<STEPS>::new(start, salt);
+        assert_eq!(verify(&circuit, commitment(start, salt)), Ok(()));
+    }
+
+    // Large starts terminate too, as long as the quotients fit: 2^64 halves
+    // down in 64 steps, but 2^70 is out of range although it would also
+    // reach 1 in time.
+    let circuit = CollatzCircuit::<STEPS>::new(1 << 64, salt);
+    assert_eq!(verify(&circuit, commitment(1 << 64, salt)), Ok(()));
+    let circuit = CollatzCircuit::<STEPS>::new(1 << 70, salt);
+    assert!(verify(&circuit, commitment(1 << 70, salt)).is_err());
+
+    // Not within the bound: 27 needs 111 steps, 871 needs 178.
+    let circuit = CollatzCircuit::<110>::new(27, salt);
+    assert!(verify(&circuit, commitment(27, salt)).is_err());
+    let circuit = CollatzCircuit::<STEPS>::new(871, salt);
+    assert!(verify(&circuit, commitment(871, salt)).is_err());
+
+    // 0 never reaches 1.
+    let circuit = CollatzCircuit::<STEPS>::new(0, salt);
+    assert!(verify(&circuit, commitment(0, salt)).is_err());
+
+    // The proof does not open a commitment to another start or salt.
+    let circuit = CollatzCircuit::<STEPS>::new(27, salt);
+    assert!(verify(&circuit, commitment(7, salt)).is_err());
+    assert!(verify(&circuit, commitment(27, salt + Fp::one())).is_err());
+
+    // A shortcut: 27 -> 82 -> 41, skipping to 1 from 41.
+    let mut trace = trajectory(27, STEPS);
+    for x in trace.iter_mut().skip(3) {
+        *x = 1;
+    }
+    let shortcut = CollatzCircuit::<STEPS> {
+        trace: Value::known(trace),
+        salt: Value::known(salt),
+    };
+    assert!(verify(&shortcut, commitment(27, salt)).is_err());
+
+    // Running on past 1 instead of padding: 1 -> 4 -> 2 -> 1.
+    let mut trace = trajectory(8, STEPS);
+    trace[4..7].copy_from_slice(&[4, 2, 1]);
+    let unpadded = CollatzCircuit::<STEPS> {
+        trace: Value::known(trace),
+        salt: Value::known(salt),
+    };
+    assert!(verify(&unpadded, commitment(8, salt)).is_err());
+}
+
+fn main() {
+    test_collatz();
+}
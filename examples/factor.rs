@@ -0,0 +1,554 @@
+use halo2_learning::{
+    bls::fq::{big_to_fe, modulus, to_limbs, LIMB_BITS},
+    range::{RangeCheckChip, RangeCheckConfig, MAX_BITS},
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Region, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::{
+        group::ff::{Field, PrimeField},
+        Fp,
+    },
+    plonk::{
+        Advice, Circuit, Column, ConstraintSystem, Constraints, Error, Expression, Instance,
+        Selector,
+    },
+    poly::Rotation,
+};
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::{One, Zero};
+
+#[derive(Clone, Debug)]
+struct FactorConfig {
+    instance: Column<Instance>,
+    advice: [Column<Advice>; 7],
+    s_factor: Selector,
+    range: RangeCheckConfig,
+}
+
+/// Proves knowledge of `p, q` with `p * q = N` and `1 < p, q < N` for a
+/// public `N`, in one row:
+///
+/// n p q p_low q_low p_high q_high
+///
+/// with `p_low = p - 2` and `p_high = N - 1 - p`, and the same for `q`.
+///
+/// `p * q = N` alone holds in the field for `1 * N`, and for any `p` with
+/// `q = N / p`. Range checking the four bounds to `MAX_BITS` makes them hold
+/// over the integers, and keeps `p * q` below the modulus so the product
+/// does too. `N` must then be below `2^MAX_BITS`.
+struct FactorChip {
+    config: FactorConfig,
+}
+
+impl FactorChip {
+    fn construct(config: FactorConfig) -> Self {
+        FactorChip { config }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> FactorConfig {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let advice = [(); 7].map(|_| meta.advice_column());
+        for column in advice {
+            meta.enable_equality(column);
+        }
+        let s_factor = meta.selector();
+
+        meta.create_gate("factors", |meta| {
+            let s_factor = meta.query_selector(s_factor);
+            let [n, p, q, p_low, q_low, p_high, q_high] =
+                advice.map(|column| meta.query_advice(column, Rotation::cur()));
+            let one = Expression::Constant(Fp::one());
+            let two = Expression::Constant(Fp::from(2));
+
+            Constraints::with_selector(
+                s_factor,
+                vec![
+                    ("product", p.clone() * q.clone() - n.clone()),
+                    ("p > 1", p_low - (p.clone() - two.clone())),
+                    ("q > 1", q_low - (q.clone() - two)),
+                    ("p < N", p_high - (n.clone() - one.clone() - p)),
+                    ("q < N", q_high - (n - one - q)),
+                ],
+            )
+        });
+
+        let range = RangeCheckChip::configure(meta);
+
+        FactorConfig {
+            instance,
+            advice,
+            s_factor,
+            range,
+        }
+    }
+
+    /// Assigns the factors of `N` from instance row 0, and returns the four
+    /// bounds.
+    fn assign(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        p: Value<Fp>,
+        q: Value<Fp>,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "factors",
+            |mut region| {
+                config.s_factor.enable(&mut region, 0)?;
+                let n = region.assign_advice_from_instance(
+                    || "n",
+                    config.instance,
+                    0,
+                    config.advice[0],
+                    0,
+                )?;
+                let n = n.value().copied();
+                region.assign_advice(|| "p", config.advice[1], 0, || p)?;
+                region.assign_advice(|| "q", config.advice[2], 0, || q)?;
+
+                let two = Value::known(Fp::from(2));
+                let one = Value::known(Fp::one());
+                let bounds = [p - two, q - two, n - one - p, n - one - q];
+                bounds
+                    .into_iter()
+                    .zip(&config.advice[3..])
+                    .map(|(bound, column)| region.assign_advice(|| "bound", *column, 0, || bound))
+                    .collect()
+            },
+        )
+    }
+}
+
+#[derive(Default)]
+struct FactorCircuit {
+    p: Value<Fp>,
+    q: Value<Fp>,
+}
+
+impl Circuit<Fp> for FactorCircuit {
+    type Config = FactorConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let constant = meta.fixed_column();
+        meta.enable_constant(constant);
+        FactorChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = FactorChip::construct(config.clone());
+        let range = RangeCheckChip::construct(config.range);
+        range.load_table(layouter.namespace(|| "range table"))?;
+        let bounds = chip.assign(layouter.namespace(|| "factors"), self.p, self.q)?;
+        range.range_check(layouter.namespace(|| "bounds"), &bounds, MAX_BITS)
+    }
+}
+
+/// Limbs of each factor in the big-integer variant; `N` gets twice as many.
+const NUM_LIMBS: usize = 4;
+/// Width the multiplication carries are range checked to.
+const CARRY_BITS: usize = 112;
+
+#[derive(Clone, Debug)]
+struct BigFactorConfig {
+    instance: Column<Instance>,
+    a: [Column<Advice>; NUM_LIMBS],
+    b: [Column<Advice>; NUM_LIMBS],
+    n: [Column<Advice>; 2 * NUM_LIMBS],
+    carry: [Column<Advice>; 2 * NUM_LIMBS - 1],
+    s_mul: Selector,
+    s_bound: Selector,
+    range: RangeCheckConfig,
+}
+
+/// The same statement for `N` of up to `2 * NUM_LIMBS` limbs of
+/// `LIMB_BITS` bits, well past the native modulus, with every number held
+/// as little-endian limbs.
+///
+/// "big product" proves `p * q = N` over the integers in one row:
+///
+/// a: p limbs, b: q limbs, n: N limbs, carry: c_0..c_{2L-2}
+///
+/// with `sum(p_i * q_j, i + j = k) + c_{k-1} = n_k + c_k * 2^LIMB_BITS` for
+/// every column `k`, `c_{-1} = 0` and the last carry zero. The limbs are
+/// range checked to `LIMB_BITS` and the carries to `CARRY_BITS`, far below
+/// what could wrap around the modulus, so the columns add up to the same
+/// integer on both sides.
+///
+/// "at least two" then proves `x = d + 2` for `x` in `{p, q}`, with the
+/// limbs of `x` in `a`, those of `d` in `b` and boolean carries in `carry`.
+/// A non-negative `d` gives `x > 1`, and `x < N` follows from the product.
+struct BigFactorChip {
+    config: BigFactorConfig,
+}
+
+impl BigFactorChip {
+    fn construct(config: BigFactorConfig) -> Self {
+        BigFactorChip { config }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> BigFactorConfig {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let a = [(); NUM_LIMBS].map(|_| meta.advice_column());
+        let b = [(); NUM_LIMBS].map(|_| meta.advice_column());
+        let n = [(); 2 * NUM_LIMBS].map(|_| meta.advice_column());
+        let carry = [(); 2 * NUM_LIMBS - 1].map(|_| meta.advice_column());
+        for column in a.iter().chain(&b).chain(&n).chain(&carry) {
+            meta.enable_equality(*column);
+        }
+        let s_mul = meta.selector();
+        let s_bound = meta.selector();
+        let base = Expression::Constant(big_to_fe::<Fp>(&(BigUint::one() << LIMB_BITS)));
+
+        meta.create_gate("big product", |meta| {
+            let s_mul = meta.query_selector(s_mul);
+            let a = a.map(|column| meta.query_advice(column, Rotation::cur()));
+            let b = b.map(|column| meta.query_advice(column, Rotation::cur()));
+            let n = n.map(|column| meta.query_advice(column, Rotation::cur()));
+            let carry = carry.map(|column| meta.query_advice(column, Rotation::cur()));
+
+            let constraints = (0..2 * NUM_LIMBS).map(|k| {
+                let mut column = Expression::Constant(Fp::zero());
+                for i in k.saturating_sub(NUM_LIMBS - 1)..=k.min(NUM_LIMBS - 1) {
+                    column = column + a[i].clone() * b[k - i].clone();
+                }
+                if k > 0 {
+                    column = column + carry[k - 1].clone();
+                }
+                if let Some(carry) = carry.get(k) {
+                    column = column - carry.clone() * base.clone();
+                }
+                column - n[k].clone()
+            });
+            Constraints::with_selector(s_mul, constraints.collect::<Vec<_>>())
+        });
+
+        meta.create_gate("at least two", |meta| {
+            let s_bound = meta.query_selector(s_bound);
+            let one = Expression::Constant(Fp::one());
+            let mut constraints = vec![];
+            for i in 0..NUM_LIMBS {
+                let x = meta.query_advice(a[i], Rotation::cur());
+                let d = meta.query_advice(b[i], Rotation::cur());
+                let mut sum = d - x;
+                if i == 0 {
+                    sum = sum + Expression::Constant(Fp::from(2));
+                } else {
+                    sum = sum + meta.query_advice(carry[i - 1], Rotation::cur());
+                }
+                if i < NUM_LIMBS - 1 {
+                    let c = meta.query_advice(carry[i], Rotation::cur());
+                    constraints.push(c.clone() * (one.clone() - c.clone()));
+                    sum = sum - c * base.clone();
+                }
+                constraints.push(sum);
+            }
+            Constraints::with_selector(s_bound, constraints)
+        });
+
+        let range = RangeCheckChip::configure(meta);
+
+        BigFactorConfig {
+            instance,
+            a,
+            b,
+            n,
+            carry,
+            s_mul,
+            s_bound,
+            range,
+        }
+    }
+
+    /// Assigns the product row with `N` from instance rows
+    /// `0..2 * NUM_LIMBS`, and returns the limbs of `p` and `q`, then those
+    /// of `N`, then the carries, for range checking.
+    fn assign_product(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        p: &Value<BigUint>,
+        q: &Value<BigUint>,
+    ) -> Result<[Vec<AssignedCell<Fp, Fp>>; 4], Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "big product",
+            |mut region| {
+                config.s_mul.enable(&mut region, 0)?;
+                let p = assign_limbs(&mut region, &config.a, p)?;
+                let q = assign_limbs(&mut region, &config.b, q)?;
+                let n = config
+                    .n
+                    .iter()
+                    .enumerate()
+                    .map(|(k, column)| {
+                        region.assign_advice_from_instance(|| "n", config.instance, k, *column, 0)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let values = |cells: &[AssignedCell<Fp, Fp>]| -> Value<Vec<BigUint>> {
+                    cells
+                        .iter()
+                        .map(|cell| cell.value().map(fe_to_big))
+                        .collect()
+                };
+                let carries = values(&p)
+                    .zip(values(&q))
+                    .zip(values(&n))
+                    .map(|((p, q), n)| product_carries(&p, &q, &n));
+                let carries = config
+                    .carry
+                    .iter()
+                    .enumerate()
+                    .map(|(k, column)| {
+                        let carry = carries.as_ref().map(|carries| int_to_fe(&carries[k]));
+                        region.assign_advice(|| "carry", *column, 0, || carry)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok([p, q, n, carries])
+            },
+        )
+    }
+
+    /// Constrains `x > 1` and returns the limbs of `x - 2` for range
+    /// checking.
+    fn assert_at_least_two(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        x: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "at least two",
+            |mut region| {
+                config.s_bound.enable(&mut region, 0)?;
+                for (limb, column) in x.iter().zip(config.a) {
+                    limb.copy_advice(|| "x", &mut region, column, 0)?;
+                }
+                let x: Value<Vec<BigUint>> =
+                    x.iter().map(|cell| cell.value().map(fe_to_big)).collect();
+                let x = x.map(|limbs| from_limbs(&limbs));
+
+                // Below 2 there is no such d: zero stands in, and the gate
+                // fails.
+                let d = x.as_ref().map(|x| {
+                    let two = BigUint::from(2u32);
+                    if *x >= two {
+                        x - two
+                    } else {
+                        BigUint::zero()
+                    }
+                });
+                let d_limbs = d.as_ref().map(|d| to_limbs(d, NUM_LIMBS));
+                let d_cells = assign_limbs_from(&mut region, &config.b, &d_limbs)?;
+
+                let carries = x.zip(d_limbs).map(|(x, d)| {
+                    let x = to_limbs(&x, NUM_LIMBS);
+                    let mut carry = BigUint::from(2u32);
+                    let mut carries = vec![];
+                    for i in 0..NUM_LIMBS - 1 {
+                        carry = (&d[i] + &carry - &x[i]) >> LIMB_BITS;
+                        carries.push(carry.clone());
+                    }
+                    carries
+                });
+                for i in 0..NUM_LIMBS - 1 {
+                    let carry = carries.as_ref().map(|carries| big_to_fe::<Fp>(&carries[i]));
+                    region.assign_advice(|| "carry", config.carry[i], 0, || carry)?;
+                }
+                Ok(d_cells)
+            },
+        )
+    }
+}
+
+fn assign_limbs(
+    region: &mut Region<'_, Fp>,
+    columns: &[Column<Advice>],
+    value: &Value<BigUint>,
+) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+    let limbs = value.as_ref().map(|value| to_limbs(value, columns.len()));
+    assign_limbs_from(region, columns, &limbs)
+}
+
+fn assign_limbs_from(
+    region: &mut Region<'_, Fp>,
+    columns: &[Column<Advice>],
+    limbs: &Value<Vec<BigUint>>,
+) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            let limb = limbs.as_ref().map(|limbs| big_to_fe(&limbs[i]));
+            region.assign_advice(|| "limb", *column, 0, || limb)
+        })
+        .collect()
+}
+
+fn from_limbs(limbs: &[BigUint]) -> BigUint {
+    limbs
+        .iter()
+        .rev()
+        .fold(BigUint::zero(), |acc, limb| (acc << LIMB_BITS) + limb)
+}
+
+fn fe_to_big(value: &Fp) -> BigUint {
+    BigUint::from_bytes_le(value.to_repr().as_ref())
+}
+
+fn int_to_fe(value: &BigInt) -> Fp {
+    let magnitude = big_to_fe::<Fp>(value.magnitude());
+    if value.sign() == Sign::Minus {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+/// The carries of `p * q = n` column by column. They only fit the range
+/// check when the product is right.
+fn product_carries(p: &[BigUint], q: &[BigUint], n: &[BigUint]) -> Vec<BigInt> {
+    let mut carry = BigInt::zero();
+    (0..2 * NUM_LIMBS - 1)
+        .map(|k| {
+            let mut column = carry.clone() - BigInt::from(n[k].clone());
+            for i in k.saturating_sub(NUM_LIMBS - 1)..=k.min(NUM_LIMBS - 1) {
+                column += BigInt::from(&p[i] * &q[k - i]);
+            }
+            carry = column >> LIMB_BITS;
+            carry.clone()
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct BigFactorCircuit {
+    p: Value<BigUint>,
+    q: Value<BigUint>,
+}
+
+impl Circuit<Fp> for BigFactorCircuit {
+    type Config = BigFactorConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let constant = meta.fixed_column();
+        meta.enable_constant(constant);
+        BigFactorChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = BigFactorChip::construct(config.clone());
+        let range = RangeCheckChip::construct(config.range);
+        range.load_table(layouter.namespace(|| "range table"))?;
+
+        let [p, q, n, carries] =
+            chip.assign_product(layouter.namespace(|| "product"), &self.p, &self.q)?;
+        let p_low = chip.assert_at_least_two(layouter.namespace(|| "p > 1"), &p)?;
+        let q_low = chip.assert_at_least_two(layouter.namespace(|| "q > 1"), &q)?;
+
+        let limbs: Vec<_> = [p, q, n, p_low, q_low].concat();
+        range.range_check(layouter.namespace(|| "limbs"), &limbs, LIMB_BITS)?;
+        range.range_check(layouter.namespace(|| "carries"), &carries, CARRY_BITS)
+    }
+}
+
+const K: u32 = 17;
+
+fn test_factor() {
+    let verify = |p: Fp, q: Fp, n: Fp| {
+        let circuit = FactorCircuit {
+            p: Value::known(p),
+            q: Value::known(q),
+        };
+        MockProver::run(K, &circuit, vec![vec![n]])
+            .unwrap()
+            .verify()
+    };
+
+    // Two primes, and the smallest factors.
+    let (p, q) = (Fp::from(1_000_003), Fp::from(998_244_353));
+    let n = p * q;
+    assert_eq!(verify(p, q, n), Ok(()));
+    assert_eq!(verify(q, p, n), Ok(()));
+    assert_eq!(verify(Fp::from(2), Fp::from(2), Fp::from(4)), Ok(()));
+
+    // The trivial factorisations.
+    assert!(verify(Fp::one(), n, n).is_err());
+    assert!(verify(n, Fp::one(), n).is_err());
+
+    // Factors that only multiply to N in the field: -1 * -N, or any p
+    // with q = N / p.
+    assert!(verify(-Fp::one(), -n, n).is_err());
+    let p = Fp::from(7);
+    assert!(verify(p, n * p.invert().unwrap(), n).is_err());
+
+    // Not a factorisation at all.
+    assert!(verify(Fp::from(1_000_003), Fp::from(998_244_353), n + Fp::one()).is_err());
+}
+
+fn big_instance(n: &BigUint) -> Vec<Vec<Fp>> {
+    vec![to_limbs(n, 2 * NUM_LIMBS).iter().map(big_to_fe).collect()]
+}
+
+fn test_big_factor() {
+    let verify = |p: &BigUint, q: &BigUint, n: &BigUint| {
+        let circuit = BigFactorCircuit {
+            p: Value::known(p.clone()),
+            q: Value::known(q.clone()),
+        };
+        MockProver::run(K, &circuit, big_instance(n))
+            .unwrap()
+            .verify()
+    };
+    let native_modulus = fe_to_big(&-Fp::one()) + 1u32;
+
+    // A 381-bit prime times a 300-bit number, far past the native modulus.
+    let p = modulus().clone();
+    let q = (BigUint::one() << 300u32) + 12345u32;
+    let n = &p * &q;
+    assert!(n > native_modulus);
+    assert_eq!(verify(&p, &q, &n), Ok(()));
+    assert_eq!(verify(&q, &p, &n), Ok(()));
+
+    // N with a zero limb and carries through every column.
+    let p = (BigUint::one() << (NUM_LIMBS * LIMB_BITS)) - 1u32;
+    let n = &p * &p;
+    assert_eq!(verify(&p, &p, &n), Ok(()));
+    let two = BigUint::from(2u32);
+    assert_eq!(verify(&two, &two, &BigUint::from(4u32)), Ok(()));
+
+    // The trivial factorisation, for an N that fits in a factor.
+    let n = modulus().clone();
+    assert!(verify(&BigUint::one(), &n, &n).is_err());
+    assert!(verify(&n, &BigUint::one(), &n).is_err());
+
+    // A wrong product, and a product off by 2^LIMB_BITS in one column.
+    let (p, q) = (modulus().clone(), BigUint::from(3u32));
+    assert!(verify(&p, &q, &(&p * &q + 1u32)).is_err());
+    assert!(verify(&p, &q, &(&p * &q + (BigUint::one() << LIMB_BITS))).is_err());
+}
+
+fn main() {
+    test_factor();
+    test_big_factor();
+}
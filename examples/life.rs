@@ -0,0 +1,418 @@
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::Fp,
+    plonk::{
+        Advice, Circuit, Column, ConstraintSystem, Constraints, Error, Expression, Instance,
+        Selector, TableColumn,
+    },
+    poly::Rotation,
+};
+
+/// Side of the board. Cells past the edges are dead.
+const N: usize = 8;
+
+type Board = [[bool; N]; N];
+
+/// Conway's rule: a live cell survives with two or three live neighbours,
+/// a dead one comes alive with exactly three.
+fn rule(alive: bool, count: usize) -> bool {
+    count == 3 || (alive && count == 2)
+}
+
+fn neighbours(board: &Board, row: usize, col: usize) -> usize {
+    let mut count = 0;
+    for (r, cells) in board
+        .iter()
+        .enumerate()
+        .take(row + 2)
+        .skip(row.saturating_sub(1))
+    {
+        for (c, alive) in cells
+            .iter()
+            .enumerate()
+            .take(col + 2)
+            .skip(col.saturating_sub(1))
+        {
+            if (r, c) != (row, col) && *alive {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+fn step(board: &Board) -> Board {
+    let mut next = [[false; N]; N];
+    for (row, cells) in next.iter_mut().enumerate() {
+        for (col, cell) in cells.iter_mut().enumerate() {
+            *cell = rule(board[row][col], neighbours(board, row, col));
+        }
+    }
+    next
+}
+
+/// `board` followed by its next `generations` generations.
+fn evolve(board: Board, generations: usize) -> Vec<Board> {
+    let mut boards = vec![board];
+    for _ in 0..generations {
+        boards.push(step(boards.last().unwrap()));
+    }
+    boards
+}
+
+/// Parses rows of `#` for live cells and `.` for dead ones.
+fn parse(rows: [&str; N]) -> Board {
+    rows.map(|row| {
+        let cells: Vec<bool> = row.chars().map(|c| c == '#').collect();
+        cells.try_into().unwrap()
+    })
+}
+
+#[derive(Clone, Debug)]
+struct LifeConfig {
+    instance: Column<Instance>,
+    cells: [Column<Advice>; N],
+    counts: [Column<Advice>; N],
+    s_step: Selector,
+    s_border: Selector,
+    table_alive: TableColumn,
+    table_count: TableColumn,
+    table_next: TableColumn,
+}
+
+/// Lays generations out one board row per circuit row, one column per
+/// board column, with a dead row above and below every board:
+///
+/// ```text
+///  0      dead
+///  1..=N  generation 0
+///  N+1    dead
+///  N+2..  generation 1
+///  ...
+/// ```
+///
+/// A cell's neighbours are then the cells at `Rotation(-1..=1)` in the
+/// adjacent columns, and the same cell one generation later is at
+/// `Rotation(N + 1)`. Each cell row of every generation but the last has:
+///
+/// - "neighbours": `count` is the sum of the eight neighbours, and
+/// - a lookup of `(cell, count, next)` in the table of the rule,
+///
+/// which also makes every cell boolean. "dead border" pins the rows in
+/// between to zero.
+struct LifeChip {
+    config: LifeConfig,
+}
+
+impl LifeChip {
+    fn construct(config: LifeConfig) -> Self {
+        LifeChip { config }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> LifeConfig {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let cells = [(); N].map(|_| meta.advice_column());
+        let counts = [(); N].map(|_| meta.advice_column());
+        for column in cells {
+            meta.enable_equality(column);
+        }
+
+        let s_step = meta.complex_selector();
+        let s_border = meta.selector();
+        let table_alive = meta.lookup_table_column();
+        let table_count = meta.lookup_table_column();
+        let table_next = meta.lookup_table_column();
+
+        meta.create_gate("neighbours", |meta| {
+            let s_step = meta.query_selector(s_step);
+            let constraints: Vec<_> = (0..N)
+                .map(|col| {
+                    let mut sum = Expression::Constant(Fp::zero());
+                    let adjacent = cells.iter().enumerate().take(col + 2);
+                    for (c, column) in adjacent.skip(col.saturating_sub(1)) {
+                        for r in -1..=1 {
+                            if (r, c) != (0, col) {
+                                sum = sum + meta.query_advice(*column, Rotation(r));
+                            }
+                        }
+                    }
+                    meta.query_advice(counts[col], Rotation::cur()) - sum
+                })
+                .collect();
+            Constraints::with_selector(s_step, constraints)
+        });
+
+        meta.create_gate("dead border", |meta| {
+            let s_border = meta.query_selector(s_border);
+            let cells: Vec<_> = cells
+                .iter()
+                .map(|cell| meta.query_advice(*cell, Rotation::cur()))
+                .collect();
+            Constraints::with_selector(s_border, cells)
+        });
+
+        for col in 0..N {
+            meta.lookup(|meta| {
+                let s_step = meta.query_selector(s_step);
+                let cell = meta.query_advice(cells[col], Rotation::cur());
+                let count = meta.query_advice(counts[col], Rotation::cur());
+                let next = meta.query_advice(cells[col], Rotation(N as i32 + 1));
+                vec![
+                    (s_step.clone() * cell, table_alive),
+                    (s_step.clone() * count, table_count),
+                    (s_step * next, table_next),
+                ]
+            });
+        }
+
+        LifeConfig {
+            instance,
+            cells,
+            counts,
+            s_step,
+            s_border,
+            table_alive,
+            table_count,
+            table_next,
+        }
+    }
+
+    /// Fills `(alive, count, rule(alive, count))`. Disabled rows look up
+    /// `(0, 0, 0)`, a dead cell with no neighbours.
+    fn load_table(&self, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+        let config = &self.config;
+        layouter.assign_table(
+            || "rule",
+            |mut table| {
+                for (offset, (alive, count)) in [false, true]
+                    .into_iter()
+                    .flat_map(|alive| (0..=8).map(move |count| (alive, count)))
+                    .enumerate()
+                {
+                    let columns = [config.table_alive, config.table_count, config.table_next];
+                    let values = [alive as u64, count as u64, rule(alive, count) as u64];
+                    for (column, value) in columns.into_iter().zip(values) {
+                        table.assign_cell(
+                            || "rule",
+                            column,
+                            offset,
+                            || Value::known(Fp::from(value)),
+                        )?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Assigns `boards`, the first copied from instance rows `0..N * N`,
+    /// row-major, and returns the cells of the last.
+    fn assign_generations(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        boards: &[Value<Board>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let config = &self.config;
+        let generations = boards.len() - 1;
+
+        layouter.assign_region(
+            || "generations",
+            |mut region| {
+                let mut last = Vec::with_capacity(N * N);
+                for (generation, board) in boards.iter().enumerate() {
+                    let top = generation * (N + 1);
+                    config.s_border.enable(&mut region, top)?;
+                    for column in config.cells {
+                        region.assign_advice(
+                            || "dead",
+                            column,
+                            top,
+                            || Value::known(Fp::zero()),
+                        )?;
+                    }
+
+                    for row in 0..N {
+                        let offset = top + 1 + row;
+                        for col in 0..N {
+                            let cell = if generation == 0 {
+                                region.assign_advice_from_instance(
+                                    || "initial",
+                                    config.instance,
+                                    row * N + col,
+                                    config.cells[col],
+                                    offset,
+                                )?
+                            } else {
+                                let alive = board.map(|board| Fp::from(board[row][col]));
+                                region.assign_advice(
+                                    || "cell",
+                                    config.cells[col],
+                                    offset,
+                                    || alive,
+                                )?
+                            };
+                            if generation == generations {
+                                last.push(cell);
+                                continue;
+                            }
+                            let count =
+                                board.map(|board| Fp::from(neighbours(&board, row, col) as u64));
+                            region.assign_advice(
+                                || "count",
+                                config.counts[col],
+                                offset,
+                                || count,
+                            )?;
+                        }
+                        if generation < generations {
+                            config.s_step.enable(&mut region, offset)?;
+                        }
+                    }
+                }
+
+                let bottom = (generations + 1) * (N + 1);
+                config.s_border.enable(&mut region, bottom)?;
+                for column in config.cells {
+                    region.assign_advice(|| "dead", column, bottom, || Value::known(Fp::zero()))?;
+                }
+                Ok(last)
+            },
+        )
+    }
+}
+
+/// Proves that the public board in instance rows `0..N * N` turns into the
+/// one in rows `N * N..2 * N * N` after `boards.len() - 1` generations,
+/// with the boards in between private.
+struct LifeCircuit {
+    boards: Vec<Value<Board>>,
+}
+
+impl LifeCircuit {
+    fn new(boards: &[Board]) -> Self {
+        LifeCircuit {
+            boards: boards.iter().map(|board| Value::known(*board)).collect(),
+        }
+    }
+}
+
+impl Circuit<Fp> for LifeCircuit {
+    type Config = LifeConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        LifeCircuit {
+            boards: vec![Value::unknown(); self.boards.len()],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        LifeChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = LifeChip::construct(config.clone());
+        chip.load_table(layouter.namespace(|| "rule"))?;
+        let last = chip.assign_generations(layouter.namespace(|| "generations"), &self.boards)?;
+        for (i, cell) in last.iter().enumerate() {
+            layouter.constrain_instance(cell.cell(), config.instance, N * N + i)?;
+        }
+        Ok(())
+    }
+}
+
+fn instance(first: &Board, last: &Board) -> Vec<Vec<Fp>> {
+    let cells = first.iter().chain(last).flatten();
+    vec![cells.map(|alive| Fp::from(*alive)).collect()]
+}
+
+fn verify(
+    boards: &[Board],
+    first: &Board,
+    last: &Board,
+) -> Result<(), Vec<halo2_proofs::dev::VerifyFailure>> {
+    MockProver::run(8, &LifeCircuit::new(boards), instance(first, last))
+        .unwrap()
+        .verify()
+}
+
+const GLIDER: [&str; N] = [
+    ".#......", "..#.....", "###.....", "........", "........", "........", "........", "........",
+];
+
+const BLINKER: [&str; N] = [
+    "........", "........", "........", "..###...", "........", "........", "........", "........",
+];
+
+fn test_life() {
+    // A glider moves one cell down and right every four generations.
+    let glider = parse(GLIDER);
+    let boards = evolve(glider, 4);
+    let moved = parse([
+        "........", "..#.....", "...#....", ".###....", "........", "........", "........",
+        "........",
+    ]);
+    assert_eq!(boards[4], moved);
+    assert_eq!(verify(&boards, &glider, &moved), Ok(()));
+
+    // A blinker has period two, and a block sitting on the edge is still.
+    let blinker = parse(BLINKER);
+    let boards = evolve(blinker, 4);
+    assert_eq!(boards[2], blinker);
+    assert_eq!(verify(&boards, &blinker, &blinker), Ok(()));
+    let mut block = [[false; N]; N];
+    for (row, col) in [(6, 6), (6, 7), (7, 6), (7, 7)] {
+        block[row][col] = true;
+    }
+    assert_eq!(verify(&evolve(block, 4), &block, &block), Ok(()));
+
+    // A wrong final board.
+    let boards = evolve(glider, 4);
+    assert!(verify(&boards, &glider, &glider).is_err());
+    let mut shifted = moved;
+    shifted[7][7] = true;
+    assert!(verify(&boards, &glider, &shifted).is_err());
+
+    // A private generation with a cell flipped.
+    let mut forged = evolve(glider, 4);
+    forged[2][7][0] = true;
+    assert!(verify(&forged, &glider, &moved).is_err());
+
+    // Cells must be 0 or 1.
+    let mut first = instance(&blinker, &blinker);
+    first[0][0] = Fp::from(2);
+    let circuit = LifeCircuit::new(&evolve(blinker, 4));
+    assert!(MockProver::run(8, &circuit, first)
+        .unwrap()
+        .verify()
+        .is_err());
+}
+
+fn main() {
+    test_life();
+
+    #[cfg(feature = "dev-graph")]
+    plot_life_circuit();
+}
+
+#[cfg(feature = "dev-graph")]
+fn plot_life_circuit() {
+    use plotters::prelude::*;
+
+    let circuit = LifeCircuit::new(&evolve(parse(GLIDER), 4));
+    let root = BitMapBackend::new("life-layout.png", (1024, 1024)).into_drawing_area();
+    root.fill(&WHITE).unwrap();
+    let root = root
+        .titled("Game of Life Circuit Layout", ("sans-serif", 60))
+        .unwrap();
+    halo2_proofs::dev::CircuitLayout::default()
+        .show_labels(false)
+        .render(8, &circuit, &root)
+        .unwrap();
+}
@@ -0,0 +1,128 @@
+use halo2_learning::merkle::{MerkleChip, MerkleConfig, MerklePath, MerkleTree};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::Fp,
+    plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
+};
+
+#[derive(Clone, Debug)]
+struct MembershipConfig {
+    merkle: MerkleConfig<Fp>,
+    instance: Column<Instance>,
+}
+
+/// Proves that a private leaf sits in the tree with the public root.
+struct MembershipCircuit<const DEPTH: usize> {
+    leaf: Value<Fp>,
+    siblings: [Value<Fp>; DEPTH],
+    directions: [Value<bool>; DEPTH],
+}
+
+impl<const DEPTH: usize> MembershipCircuit<DEPTH> {
+    fn new(leaf: Fp, path: &MerklePath<Fp>) -> Self {
+        let siblings: [Fp; DEPTH] = path.siblings.clone().try_into().unwrap();
+        let directions: [bool; DEPTH] = path.directions.clone().try_into().unwrap();
+        MembershipCircuit {
+            leaf: Value::known(leaf),
+            siblings: siblings.map(Value::known),
+            directions: directions.map(Value::known),
+        }
+    }
+}
+
+impl<const DEPTH: usize> Circuit<Fp> for MembershipCircuit<DEPTH> {
+    type Config = MembershipConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        MembershipCircuit {
+            leaf: Value::unknown(),
+            siblings: [Value::unknown(); DEPTH],
+            directions: [Value::unknown(); DEPTH],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let merkle = MerkleChip::configure(meta);
+
+        MembershipConfig { merkle, instance }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = MerkleChip::construct(config.merkle);
+        let leaf = chip.load_private(layouter.namespace(|| "leaf"), self.leaf)?;
+        let root = chip.compute_root(
+            layouter.namespace(|| "root"),
+            &leaf,
+            &self.siblings,
+            &self.directions,
+        )?;
+        layouter.constrain_instance(root.cell(), config.instance, 0)
+    }
+}
+
+fn test_membership() {
+    let k = 9;
+    let leaves: Vec<Fp> = (0..11u64).map(|i| Fp::from(1000 + i)).collect();
+    let tree = MerkleTree::new(4, leaves.clone());
+    let root = tree.root();
+
+    for index in [0, 6, 10] {
+        let path = tree.path(index);
+        assert_eq!(path.root(leaves[index]), root);
+
+        let circuit = MembershipCircuit::<4>::new(leaves[index], &path);
+        let prover = MockProver::run(k, &circuit, vec![vec![root]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    let path = tree.path(6);
+
+    // A leaf that is not in the tree.
+    let circuit = MembershipCircuit::<4>::new(Fp::from(999), &path);
+    let prover = MockProver::run(k, &circuit, vec![vec![root]]).unwrap();
+    assert!(prover.verify().is_err());
+
+    // A wrong sibling.
+    let mut wrong_sibling = path.clone();
+    wrong_sibling.siblings[2] += Fp::one();
+    let circuit = MembershipCircuit::<4>::new(leaves[6], &wrong_sibling);
+    let prover = MockProver::run(k, &circuit, vec![vec![root]]).unwrap();
+    assert!(prover.verify().is_err());
+
+    // A flipped direction bit.
+    let mut flipped = path.clone();
+    flipped.directions[1] = !flipped.directions[1];
+    let circuit = MembershipCircuit::<4>::new(leaves[6], &flipped);
+    let prover = MockProver::run(k, &circuit, vec![vec![root]]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+fn test_deep_tree() {
+    let k = 12;
+    let leaves: Vec<Fp> = (0..5u64).map(|i| Fp::from(i * i + 7)).collect();
+    let tree = MerkleTree::new(20, leaves.clone());
+
+    let path = tree.path(3);
+    let circuit = MembershipCircuit::<20>::new(leaves[3], &path);
+    let prover = MockProver::run(k, &circuit, vec![vec![tree.root()]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // An empty slot is a zero leaf.
+    let path = tree.path(1 << 19);
+    let circuit = MembershipCircuit::<20>::new(Fp::zero(), &path);
+    let prover = MockProver::run(k, &circuit, vec![vec![tree.root()]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+}
+
+fn main() {
+    test_membership();
+    test_deep_tree();
+}
@@ -0,0 +1,106 @@
+use halo2_learning::mimc::{self, MimcChip, MimcConfig, ROWS_PER_HASH};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::Fp,
+    plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
+};
+
+#[derive(Clone, Debug)]
+struct ChainConfig {
+    mimc: MimcConfig,
+    instance: Column<Instance>,
+}
+
+/// Proves knowledge of a private seed with `H^N(seed)` equal to the public
+/// output.
+#[derive(Default)]
+struct ChainCircuit<const N: usize> {
+    seed: Value<Fp>,
+}
+
+impl<const N: usize> Circuit<Fp> for ChainCircuit<N> {
+    type Config = ChainConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let mimc = MimcChip::configure(meta);
+
+        ChainConfig { mimc, instance }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = MimcChip::construct(config.mimc);
+        let seed = chip.load_private(layouter.namespace(|| "seed"), self.seed)?;
+        let out = chip.hash_chain(layouter.namespace(|| "chain"), &seed, N)?;
+        layouter.constrain_instance(out.cell(), config.instance, 0)
+    }
+}
+
+fn chain(seed: Fp, n: usize) -> Fp {
+    (0..n).fold(seed, |node, _| mimc::hash(node))
+}
+
+fn test_single() {
+    let k = 7;
+    let seed = Fp::from(42);
+    assert_ne!(mimc::hash(seed), mimc::hash(seed + Fp::one()));
+
+    let circuit = ChainCircuit::<1> {
+        seed: Value::known(seed),
+    };
+    let prover = MockProver::run(k, &circuit, vec![vec![mimc::hash(seed)]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // The permutation alone, without the feed-forward.
+    let prover = MockProver::run(k, &circuit, vec![vec![mimc::hash(seed) - seed]]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+fn test_chain() {
+    const N: usize = 10;
+    let k = 10;
+    assert!(N * ROWS_PER_HASH < 1 << k);
+
+    let seed = Fp::from(0x5eed);
+    let out = chain(seed, N);
+
+    let circuit = ChainCircuit::<N> {
+        seed: Value::known(seed),
+    };
+    let prover = MockProver::run(k, &circuit, vec![vec![out]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // One hash short.
+    let prover = MockProver::run(k, &circuit, vec![vec![chain(seed, N - 1)]]).unwrap();
+    assert!(prover.verify().is_err());
+
+    // Another seed.
+    let circuit = ChainCircuit::<N> {
+        seed: Value::known(seed + Fp::one()),
+    };
+    let prover = MockProver::run(k, &circuit, vec![vec![out]]).unwrap();
+    assert!(prover.verify().is_err());
+
+    // The intermediate H(seed) is a valid seed for N - 1 steps only.
+    let circuit = ChainCircuit::<{ N - 1 }> {
+        seed: Value::known(mimc::hash(seed)),
+    };
+    let prover = MockProver::run(k, &circuit, vec![vec![out]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+}
+
+fn main() {
+    test_single();
+    test_chain();
+}
@@ -0,0 +1,770 @@
+use std::collections::{HashMap, HashSet};
+
+use halo2_learning::range::{RangeCheckChip, RangeCheckConfig, LOOKUP_BITS, NUM_CHUNKS};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Region, SimpleFloorPlanner, Value},
+    dev::{CircuitGates, MockProver},
+    pasta::Fp,
+    plonk::{
+        Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Constraints, Error,
+        Expression, Fixed, FloorPlanner, Instance, Selector,
+    },
+    poly::Rotation,
+};
+
+const INPUTS: usize = 4;
+const HIDDEN: usize = 8;
+const OUTPUTS: usize = 3;
+/// Widest dense layer, i.e. the number of input columns.
+const WIDTH: usize = HIDDEN;
+
+/// Fixed-point values carry `FRAC_BITS` fractional bits, products twice as
+/// many until they are rescaled.
+const FRAC_BITS: usize = 16;
+const SCALE: i128 = 1 << FRAC_BITS;
+/// Inputs are non-negative measurements below `2^(INPUT_BITS - FRAC_BITS)`.
+const INPUT_BITS: usize = 32;
+/// Bound on the hidden activations, and on the negated pre-activations the
+/// ReLU zeroes out.
+const VALUE_BITS: usize = 48;
+/// Bound on the gaps between the winning logit and the others.
+const DIFF_BITS: usize = 80;
+/// Set by the `2^LOOKUP_BITS` rows of the range table.
+const K: u32 = 17;
+
+/// A 4-8-3 classifier for iris-like measurements in cm (sepal length, sepal
+/// width, petal length, petal width), trained offline on synthetic clusters
+/// around the means of setosa, versicolor and virginica.
+const W1: [[f64; INPUTS]; HIDDEN] = [
+    [-0.03, 0.22, 0.23, -0.49],
+    [-0.49, -0.24, -0.01, -0.35],
+    [-0.71, 0.84, -0.39, -0.06],
+    [-0.23, -0.72, 0.1, -1.63],
+    [-0.19, -0.37, -1.52, 0.46],
+    [0.64, 0.32, 1.46, 2.86],
+    [-0.02, -0.71, -0.88, 0.41],
+    [-0.23, 0.45, -1.76, -1.53],
+];
+const B1: [f64; HIDDEN] = [-0.95, 3.7, 3.12, 7.4, 7.39, -14.94, 5.12, 8.55];
+const W2: [[f64; HIDDEN]; OUTPUTS] = [
+    [-0.47, -0.45, 0.46, -1.06, 1.01, -0.98, -0.13, 1.35],
+    [0.1, -0.43, 0.29, 2.04, -0.71, -1.17, -0.52, -1.67],
+    [0.32, 0.21, 0.2, -0.34, -1.11, 1.71, -0.46, -0.78],
+];
+const B2: [f64; OUTPUTS] = [-0.76, 1.36, -0.6];
+
+/// The float reference the circuit approximates.
+fn forward_float(x: [f64; INPUTS]) -> [f64; OUTPUTS] {
+    let hidden: [f64; HIDDEN] = std::array::from_fn(|j| {
+        let acc: f64 = W1[j].iter().zip(x).map(|(w, x)| w * x).sum();
+        (acc + B1[j]).max(0.0)
+    });
+    std::array::from_fn(|k| {
+        let acc: f64 = W2[k].iter().zip(hidden).map(|(w, h)| w * h).sum();
+        acc + B2[k]
+    })
+}
+
+/// The first index of the largest value.
+fn argmax<T: PartialOrd + Copy>(values: &[T]) -> usize {
+    (0..values.len()).fold(0, |best, i| if values[i] > values[best] { i } else { best })
+}
+
+/// `x * 2^(FRAC_BITS * frac)`, rounded to the nearest integer.
+fn quantize(x: f64, frac: i32) -> i128 {
+    (x * (SCALE as f64).powi(frac)).round() as i128
+}
+
+fn to_field(x: i128) -> Fp {
+    let magnitude = x.unsigned_abs();
+    let fe = Fp::from_raw([magnitude as u64, (magnitude >> 64) as u64, 0, 0]);
+    if x < 0 {
+        -fe
+    } else {
+        fe
+    }
+}
+
+/// The fixed-point evaluation, step by step as the circuit lays it out.
+/// Weights have `FRAC_BITS` fractional bits and biases twice as many, to
+/// match the products they are added to.
+#[derive(Clone, Debug)]
+struct Trace {
+    input: [i128; INPUTS],
+    /// First layer outputs, with `2 * FRAC_BITS` fractional bits.
+    acc: [i128; HIDDEN],
+    /// `acc` rescaled, rounding down.
+    pre: [i128; HIDDEN],
+    hidden: [i128; HIDDEN],
+    /// With `2 * FRAC_BITS` fractional bits.
+    logits: [i128; OUTPUTS],
+}
+
+impl Trace {
+    fn new(input: [u64; INPUTS]) -> Self {
+        let input = input.map(i128::from);
+        let acc = std::array::from_fn(|j| {
+            let products: i128 = W1[j]
+                .iter()
+                .zip(input)
+                .map(|(w, x)| quantize(*w, 1) * x)
+                .sum();
+            products + quantize(B1[j], 2)
+        });
+        let pre = acc.map(|acc: i128| acc.div_euclid(SCALE));
+        let hidden = pre.map(|pre| pre.max(0));
+        let mut trace = Trace {
+            input,
+            acc,
+            pre,
+            hidden,
+            logits: [0; OUTPUTS],
+        };
+        trace.update_logits();
+        trace
+    }
+
+    fn update_logits(&mut self) {
+        self.logits = std::array::from_fn(|k| {
+            let products: i128 = W2[k]
+                .iter()
+                .zip(self.hidden)
+                .map(|(w, h)| quantize(*w, 1) * h)
+                .sum();
+            products + quantize(B2[k], 2)
+        });
+    }
+
+    fn class(&self) -> usize {
+        argmax(&self.logits)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct MlpConfig {
+    instance: Column<Instance>,
+    x: [Column<Advice>; WIDTH],
+    out: Column<Advice>,
+    quotient: Column<Advice>,
+    remainder: Column<Advice>,
+    hidden: Column<Advice>,
+    negated: Column<Advice>,
+    weights: [Column<Fixed>; WIDTH],
+    bias: Column<Fixed>,
+    constant: Column<Fixed>,
+    s_dense: Selector,
+    s_relu: Selector,
+    s_argmax: Selector,
+    range: RangeCheckConfig,
+}
+
+/// Dense layers with the weights in fixed columns, one row per neuron:
+///
+/// x_0 .. x_7 out quotient remainder hidden negated
+///
+/// with `out = sum(w_i * x_i) + b` over the fixed `w_i` and `b` of the row.
+/// Narrower layers leave the extra weights at zero. On the rows of a hidden
+/// layer, `out` is rescaled and passed through the ReLU:
+///
+/// - `out = quotient * 2^FRAC_BITS + remainder`, the remainder range-checked
+///   to `FRAC_BITS`, so `quotient` rounds down,
+/// - `hidden * (hidden - quotient) = 0` and `negated = hidden - quotient`,
+///   both range-checked to `VALUE_BITS`: either `hidden = quotient >= 0`, or
+///   `hidden = 0` and `quotient <= 0`.
+///
+/// The argmax takes one row per logit, reusing the input columns as
+///
+/// logit chosen seen count max gap
+///
+/// with `chosen` boolean and `seen` summing it, from 0 in the first row to
+/// exactly 1 below the last. `before = 1 - seen - chosen` is 1 on the rows
+/// above the chosen one, whose number `count` accumulates, so the class is
+/// the final `count`. `max` is the same on every row and equal to the
+/// chosen logit, and `gap = max - logit - before` is range-checked to
+/// `DIFF_BITS`: the chosen logit is greater than those above it and at
+/// least those below, so it is a largest one and no earlier logit is as
+/// large, which makes it the first.
+struct MlpChip {
+    config: MlpConfig,
+}
+
+impl MlpChip {
+    fn construct(config: MlpConfig) -> Self {
+        MlpChip { config }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> MlpConfig {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let x = [(); WIDTH].map(|_| meta.advice_column());
+        let [out, quotient, remainder, hidden, negated] = [(); 5].map(|_| meta.advice_column());
+        for column in x.into_iter().chain([out, remainder, hidden, negated]) {
+            meta.enable_equality(column);
+        }
+        let weights = [(); WIDTH].map(|_| meta.fixed_column());
+        let bias = meta.fixed_column();
+        let constant = meta.fixed_column();
+        meta.enable_constant(constant);
+        let s_dense = meta.selector();
+        let s_relu = meta.selector();
+        let s_argmax = meta.selector();
+
+        meta.create_gate("dense", |meta| {
+            let s_dense = meta.query_selector(s_dense);
+            let out = meta.query_advice(out, Rotation::cur());
+            let bias = meta.query_fixed(bias, Rotation::cur());
+            let sum = x.iter().zip(weights).fold(bias, |sum, (x, w)| {
+                sum + meta.query_fixed(w, Rotation::cur()) * meta.query_advice(*x, Rotation::cur())
+            });
+            Constraints::with_selector(s_dense, vec![out - sum])
+        });
+
+        meta.create_gate("relu", |meta| {
+            let s_relu = meta.query_selector(s_relu);
+            let out = meta.query_advice(out, Rotation::cur());
+            let quotient = meta.query_advice(quotient, Rotation::cur());
+            let remainder = meta.query_advice(remainder, Rotation::cur());
+            let hidden = meta.query_advice(hidden, Rotation::cur());
+            let negated = meta.query_advice(negated, Rotation::cur());
+            let scale = Expression::Constant(Fp::from(SCALE as u64));
+            Constraints::with_selector(
+                s_relu,
+                vec![
+                    ("rescale", out - quotient.clone() * scale - remainder),
+                    ("relu", hidden.clone() * (hidden.clone() - quotient.clone())),
+                    ("negated", negated - (hidden - quotient)),
+                ],
+            )
+        });
+
+        meta.create_gate("argmax", |meta| {
+            let s_argmax = meta.query_selector(s_argmax);
+            let [logit, chosen, gap] =
+                [x[0], x[1], x[5]].map(|column| meta.query_advice(column, Rotation::cur()));
+            let [seen, count, max] = [x[2], x[3], x[4]].map(|column| {
+                [Rotation::cur(), Rotation::next()]
+                    .map(|rotation| meta.query_advice(column, rotation))
+            });
+            let one = Expression::Constant(Fp::one());
+            let before = one.clone() - seen[0].clone() - chosen.clone();
+            Constraints::with_selector(
+                s_argmax,
+                vec![
+                    ("chosen", chosen.clone() * (one - chosen.clone())),
+                    ("seen", seen[1].clone() - seen[0].clone() - chosen.clone()),
+                    (
+                        "count",
+                        count[1].clone() - count[0].clone() - before.clone(),
+                    ),
+                    ("same max", max[1].clone() - max[0].clone()),
+                    ("max", chosen * (max[0].clone() - logit.clone())),
+                    ("gap", gap - (max[0].clone() - logit - before)),
+                ],
+            )
+        });
+
+        let range = RangeCheckChip::configure(meta);
+
+        MlpConfig {
+            instance,
+            x,
+            out,
+            quotient,
+            remainder,
+            hidden,
+            negated,
+            weights,
+            bias,
+            constant,
+            s_dense,
+            s_relu,
+            s_argmax,
+            range,
+        }
+    }
+
+    fn load_inputs(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        input: &Value<[i128; INPUTS]>,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        layouter.assign_region(
+            || "inputs",
+            |mut region| {
+                (0..INPUTS)
+                    .map(|i| {
+                        let x = input.map(|input| to_field(input[i]));
+                        region.assign_advice(|| "input", self.config.x[i], 0, || x)
+                    })
+                    .collect()
+            },
+        )
+    }
+
+    /// Assigns the row of a neuron with the given weights and bias, and
+    /// returns its `out` cell.
+    fn assign_neuron(
+        &self,
+        region: &mut Region<'_, Fp>,
+        row: usize,
+        inputs: &[AssignedCell<Fp, Fp>],
+        weights: &[i128],
+        bias: i128,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let config = &self.config;
+        config.s_dense.enable(region, row)?;
+        for i in 0..WIDTH {
+            let weight = weights.get(i).copied().unwrap_or(0);
+            region.assign_fixed(
+                || "weight",
+                config.weights[i],
+                row,
+                || Value::known(to_field(weight)),
+            )?;
+            match inputs.get(i) {
+                Some(input) => {
+                    input.copy_advice(|| "x", region, config.x[i], row)?;
+                }
+                None => {
+                    region.assign_advice(
+                        || "unused x",
+                        config.x[i],
+                        row,
+                        || Value::known(Fp::zero()),
+                    )?;
+                }
+            }
+        }
+        region.assign_fixed(|| "bias", config.bias, row, || Value::known(to_field(bias)))?;
+
+        let out = inputs
+            .iter()
+            .zip(weights)
+            .fold(Value::known(to_field(bias)), |sum, (input, weight)| {
+                sum + input.value().map(|x| *x * to_field(*weight))
+            });
+        region.assign_advice(|| "out", config.out, row, || out)
+    }
+
+    /// The first layer, rescaled and through the ReLU. Returns the
+    /// remainders, the activations and the negated pre-activations, which
+    /// the caller range-checks.
+    fn assign_hidden(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        inputs: &[AssignedCell<Fp, Fp>],
+        trace: &Value<Trace>,
+    ) -> Result<[Vec<AssignedCell<Fp, Fp>>; 3], Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "hidden layer",
+            |mut region| {
+                let mut cells = [(); 3].map(|_| Vec::with_capacity(HIDDEN));
+                for j in 0..HIDDEN {
+                    let weights = W1[j].map(|w| quantize(w, 1));
+                    self.assign_neuron(&mut region, j, inputs, &weights, quantize(B1[j], 2))?;
+
+                    config.s_relu.enable(&mut region, j)?;
+                    let pre = trace.as_ref().map(|trace| trace.pre[j]);
+                    let acc = trace.as_ref().map(|trace| trace.acc[j]);
+                    let hidden = trace.as_ref().map(|trace| trace.hidden[j]);
+                    region.assign_advice(
+                        || "quotient",
+                        config.quotient,
+                        j,
+                        || pre.map(to_field),
+                    )?;
+                    let assigned = [
+                        (
+                            config.remainder,
+                            acc.zip(pre).map(|(acc, pre)| acc - pre * SCALE),
+                        ),
+                        (config.hidden, hidden),
+                        (
+                            config.negated,
+                            hidden.zip(pre).map(|(hidden, pre)| hidden - pre),
+                        ),
+                    ];
+                    for (cells, (column, value)) in cells.iter_mut().zip(assigned) {
+                        let value = value.map(to_field);
+                        cells.push(region.assign_advice(|| "relu", column, j, || value)?);
+                    }
+                }
+                Ok(cells)
+            },
+        )
+    }
+
+    fn assign_logits(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        hidden: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        layouter.assign_region(
+            || "output layer",
+            |mut region| {
+                (0..OUTPUTS)
+                    .map(|k| {
+                        let weights = W2[k].map(|w| quantize(w, 1));
+                        self.assign_neuron(&mut region, k, hidden, &weights, quantize(B2[k], 2))
+                    })
+                    .collect()
+            },
+        )
+    }
+
+    /// Constrains the class in instance row 0 to be the argmax of `logits`,
+    /// and returns the gaps for the caller to range-check.
+    fn assign_argmax(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        logits: &[AssignedCell<Fp, Fp>],
+        class: Value<usize>,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let config = &self.config;
+        let [logit_col, chosen_col, seen_col, count_col, max_col, gap_col] =
+            [0, 1, 2, 3, 4, 5].map(|i| config.x[i]);
+        let (gaps, count) = layouter.assign_region(
+            || "argmax",
+            |mut region| {
+                let values: Value<Vec<Fp>> =
+                    logits.iter().map(|logit| logit.value().copied()).collect();
+                let max = values.zip(class).map(|(values, class)| values[class]);
+                let mut seen =
+                    region.assign_advice_from_constant(|| "seen", seen_col, 0, Fp::zero())?;
+                let mut count =
+                    region.assign_advice_from_constant(|| "count", count_col, 0, Fp::zero())?;
+                let mut gaps = Vec::with_capacity(logits.len());
+                for (k, logit) in logits.iter().enumerate() {
+                    config.s_argmax.enable(&mut region, k)?;
+                    logit.copy_advice(|| "logit", &mut region, logit_col, k)?;
+                    let chosen = class.map(|class| Fp::from(class == k));
+                    let before = class.map(|class| Fp::from(k < class));
+                    region.assign_advice(|| "chosen", chosen_col, k, || chosen)?;
+                    region.assign_advice(|| "max", max_col, k, || max)?;
+                    let gap = max - logit.value().copied() - before;
+                    gaps.push(region.assign_advice(|| "gap", gap_col, k, || gap)?);
+
+                    let next = seen.value().copied() + chosen;
+                    seen = region.assign_advice(|| "seen", seen_col, k + 1, || next)?;
+                    let next = count.value().copied() + before;
+                    count = region.assign_advice(|| "count", count_col, k + 1, || next)?;
+                }
+                region.assign_advice(|| "max", max_col, logits.len(), || max)?;
+                region.constrain_constant(seen.cell(), Fp::one())?;
+                Ok((gaps, count))
+            },
+        )?;
+        layouter.constrain_instance(count.cell(), config.instance, 0)?;
+        Ok(gaps)
+    }
+}
+
+/// Proves that the network classifies a private input as the class in
+/// instance row 0. The weights are part of the circuit, so the verifying
+/// key commits to the model.
+struct MlpCircuit {
+    trace: Value<Trace>,
+}
+
+impl MlpCircuit {
+    fn new(trace: Trace) -> Self {
+        MlpCircuit {
+            trace: Value::known(trace),
+        }
+    }
+}
+
+impl Circuit<Fp> for MlpCircuit {
+    type Config = MlpConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        MlpCircuit {
+            trace: Value::unknown(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        MlpChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = MlpChip::construct(config.clone());
+        let range = RangeCheckChip::construct(config.range.clone());
+        range.load_table(layouter.namespace(|| "range table"))?;
+
+        let input = self.trace.as_ref().map(|trace| trace.input);
+        let inputs = chip.load_inputs(layouter.namespace(|| "inputs"), &input)?;
+        let [remainders, hidden, negated] =
+            chip.assign_hidden(layouter.namespace(|| "hidden"), &inputs, &self.trace)?;
+        let logits = chip.assign_logits(layouter.namespace(|| "logits"), &hidden)?;
+        let class = self.trace.as_ref().map(Trace::class);
+        let gaps = chip.assign_argmax(layouter.namespace(|| "argmax"), &logits, class)?;
+
+        range.range_check(layouter.namespace(|| "inputs"), &inputs, INPUT_BITS)?;
+        range.range_check(layouter.namespace(|| "remainders"), &remainders, FRAC_BITS)?;
+        let activations: Vec<_> = hidden.into_iter().chain(negated).collect();
+        range.range_check(
+            layouter.namespace(|| "activations"),
+            &activations,
+            VALUE_BITS,
+        )?;
+        range.range_check(layouter.namespace(|| "gaps"), &gaps, DIFF_BITS)
+    }
+}
+
+fn verify(trace: Trace, class: usize) -> Result<(), Vec<halo2_proofs::dev::VerifyFailure>> {
+    MockProver::run(
+        K,
+        &MlpCircuit::new(trace),
+        vec![vec![Fp::from(class as u64)]],
+    )
+    .unwrap()
+    .verify()
+}
+
+/// Fixed-point input from measurements in cm.
+fn input(x: [f64; INPUTS]) -> [u64; INPUTS] {
+    x.map(|x| quantize(x, 1) as u64)
+}
+
+/// Samples of each species, with the expected class.
+const SAMPLES: [([f64; INPUTS], usize); 9] = [
+    ([5.0, 3.4, 1.5, 0.25], 0),
+    ([4.6, 3.1, 1.5, 0.2], 0),
+    ([5.4, 3.9, 1.3, 0.4], 0),
+    ([5.9, 2.8, 4.3, 1.3], 1),
+    ([5.6, 3.0, 4.1, 1.3], 1),
+    ([6.1, 2.8, 4.7, 1.2], 1),
+    ([6.6, 3.0, 5.6, 2.0], 2),
+    ([7.2, 3.6, 6.1, 2.5], 2),
+    ([6.3, 2.9, 5.6, 1.8], 2),
+];
+
+fn test_reference() {
+    // Ties go to the first largest value, as in the circuit.
+    assert_eq!(argmax(&[1, 3, 3]), 1);
+
+    for (x, class) in SAMPLES {
+        let float = forward_float(x);
+        assert_eq!(argmax(&float), class);
+
+        // The fixed-point logits stay within 2^-8 of the float ones: the
+        // error of the quantized weights and of the rescaling.
+        let trace = Trace::new(input(x));
+        assert_eq!(trace.class(), class);
+        for (fixed, float) in trace.logits.iter().zip(float) {
+            let fixed = *fixed as f64 / (SCALE * SCALE) as f64;
+            assert!(
+                (fixed - float).abs() < 1.0 / 256.0,
+                "{} vs {}",
+                fixed,
+                float
+            );
+        }
+    }
+}
+
+fn test_mlp() {
+    for (x, class) in SAMPLES {
+        assert_eq!(verify(Trace::new(input(x)), class), Ok(()));
+    }
+
+    // Any other class.
+    let trace = Trace::new(input(SAMPLES[3].0));
+    assert!(verify(trace.clone(), 0).is_err());
+    assert!(verify(trace.clone(), 2).is_err());
+    assert!(verify(trace, OUTPUTS).is_err());
+
+    // Skipping the ReLU on a negative pre-activation, the logits recomputed
+    // to match.
+    let mut leaky = Trace::new(input(SAMPLES[6].0));
+    let j = leaky.pre.iter().position(|pre| *pre < 0).unwrap();
+    leaky.hidden[j] = leaky.pre[j];
+    leaky.update_logits();
+    let class = leaky.class();
+    assert!(verify(leaky, class).is_err());
+
+    // Zeroing a positive one instead.
+    let mut dead = Trace::new(input(SAMPLES[6].0));
+    let j = dead.pre.iter().position(|pre| *pre > 0).unwrap();
+    dead.hidden[j] = 0;
+    dead.update_logits();
+    let class = dead.class();
+    assert!(verify(dead, class).is_err());
+
+    // Rounding the wrong way.
+    let mut rounded = Trace::new(input(SAMPLES[0].0));
+    rounded.pre = rounded.pre.map(|pre| pre + 1);
+    rounded.hidden = rounded.pre.map(|pre| pre.max(0));
+    rounded.update_logits();
+    let class = rounded.class();
+    assert!(verify(rounded, class).is_err());
+}
+
+/// Measures a layout: the rows the floor planner gives to regions, and
+/// those taken by lookup tables, whose columns are the ones it pads with
+/// `fill_from_row`.
+#[derive(Default)]
+struct RowCount {
+    rows: usize,
+    fixed: HashMap<Column<Fixed>, usize>,
+    tables: HashSet<Column<Fixed>>,
+}
+
+impl RowCount {
+    fn regions(&self) -> usize {
+        self.fixed
+            .iter()
+            .filter(|(column, _)| !self.tables.contains(column))
+            .map(|(_, rows)| *rows)
+            .fold(self.rows, usize::max)
+    }
+
+    fn tables(&self) -> usize {
+        self.tables
+            .iter()
+            .map(|column| self.fixed[column])
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Assignment<Fp> for RowCount {
+    fn enter_region<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn exit_region(&mut self) {}
+
+    fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, row: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.rows = self.rows.max(row + 1);
+        Ok(())
+    }
+
+    fn query_instance(&self, _: Column<Instance>, _: usize) -> Result<Option<Fp>, Error> {
+        Ok(None)
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Advice>,
+        row: usize,
+        _: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Result<VR, Error>,
+        VR: Into<Assigned<Fp>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.rows = self.rows.max(row + 1);
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        column: Column<Fixed>,
+        row: usize,
+        _: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Result<VR, Error>,
+        VR: Into<Assigned<Fp>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let rows = self.fixed.entry(column).or_default();
+        *rows = (*rows).max(row + 1);
+        Ok(())
+    }
+
+    fn copy(&mut self, _: Column<Any>, _: usize, _: Column<Any>, _: usize) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        column: Column<Fixed>,
+        _: usize,
+        _: Option<Assigned<Fp>>,
+    ) -> Result<(), Error> {
+        self.tables.insert(column);
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {}
+}
+
+/// Prints the size of the circuit. Nearly all of `2^K` goes to the 16-bit
+/// range table; the network itself takes about a hundred and fifty rows, so
+/// it could grow a lot before needing a larger `k`.
+fn cost_report() {
+    let mut cs = ConstraintSystem::<Fp>::default();
+    let config = MlpCircuit::configure(&mut cs);
+    let usable = (1 << K) - (cs.blinding_factors() + 1);
+
+    let circuit = MlpCircuit {
+        trace: Value::unknown(),
+    };
+    let mut rows = RowCount::default();
+    let constants = vec![config.constant];
+    SimpleFloorPlanner::synthesize(&mut rows, &circuit, config, constants).unwrap();
+
+    println!("MLP {}-{}-{} at k = {}", INPUTS, HIDDEN, OUTPUTS, K);
+    println!(
+        "columns: {} advice, {} fixed, 1 instance, 3 selectors, plus {} advice, 1 table and 1 selector for range checks",
+        WIDTH + 5,
+        WIDTH + 2,
+        1 + NUM_CHUNKS,
+    );
+    println!("lookups: {} of {} bits", NUM_CHUNKS, LOOKUP_BITS);
+    println!("degree: {}", cs.degree());
+    println!(
+        "rows: {} for regions, {} for the range table, of {} usable",
+        rows.regions(),
+        rows.tables(),
+        usable,
+    );
+    println!(
+        "fixed-point multiplications: {}",
+        INPUTS * HIDDEN + HIDDEN * OUTPUTS
+    );
+    // The table has a column of its own and only has to fit. The region rows
+    // include the constants column, which the range checks fill with a zero
+    // per unused chunk and which is the tallest: what is left below it is
+    // room for more neurons and range checks.
+    println!("rows left for the network: {}", usable - rows.regions());
+
+    let gates = CircuitGates::collect::<Fp, MlpCircuit>().to_string();
+    for line in gates.lines().filter(|line| line.starts_with("Total")) {
+        println!("{}", line);
+    }
+}
+
+fn main() {
+    test_reference();
+    test_mlp();
+    cost_report();
+}
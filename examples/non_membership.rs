@@ -0,0 +1,305 @@
+use halo2_learning::{
+    bits::{BitsChip, BitsConfig},
+    merkle::{MerkleChip, MerkleConfig, MerkleTree},
+    sorted::{SortedChip, SortedConfig},
+};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::Fp,
+    plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
+};
+
+/// Width of the list entries and of the values checked against them.
+const BITS: usize = 64;
+const DEPTH: usize = 3;
+
+#[derive(Clone, Debug)]
+struct SetConfig {
+    merkle: MerkleConfig<Fp>,
+    bits: BitsConfig,
+    sorted: SortedConfig,
+    instance: Column<Instance>,
+}
+
+fn configure(meta: &mut ConstraintSystem<Fp>) -> SetConfig {
+    let instance = meta.instance_column();
+    meta.enable_equality(instance);
+    let bits = BitsChip::configure(meta);
+    let sorted = SortedChip::configure(meta);
+    // Also enables the constant column the other chips rely on.
+    let merkle = MerkleChip::configure(meta);
+
+    SetConfig {
+        merkle,
+        bits,
+        sorted,
+        instance,
+    }
+}
+
+/// Proves that the tree with the public root holds a strictly increasing
+/// list in its first `N` leaves and zeros after it. Run once by whoever
+/// publishes the list, so that a root is known to commit to a sorted one.
+struct SortedListCircuit<const N: usize> {
+    list: [Value<Fp>; N],
+}
+
+impl<const N: usize> Circuit<Fp> for SortedListCircuit<N> {
+    type Config = SetConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        SortedListCircuit {
+            list: [Value::unknown(); N],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let merkle = MerkleChip::construct(config.merkle);
+        let sorted = SortedChip::construct(config.sorted);
+        sorted.load_table(layouter.namespace(|| "range table"))?;
+
+        let list = self
+            .list
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                merkle.load_private(layouter.namespace(|| format!("entry {}", i)), *value)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        sorted.assert_increasing(layouter.namespace(|| "sorted"), &list, BITS)?;
+
+        let mut level = list;
+        while level.len() < 1 << DEPTH {
+            level.push(merkle.load_constant(layouter.namespace(|| "empty leaf"), Fp::zero())?);
+        }
+        let poseidon = merkle.poseidon();
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| {
+                    poseidon.hash(
+                        layouter.namespace(|| "hash pair"),
+                        &[pair[0].clone(), pair[1].clone()],
+                    )
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        layouter.constrain_instance(level[0].cell(), config.instance, 0)
+    }
+}
+
+/// Proves that the public `x` is not in the sorted list committed to by the
+/// public root: it lies strictly between the leaves at some private index
+/// and the next one. Lists should start and end with the sentinels `0` and
+/// `2^BITS - 1`, so that every other value has such a pair.
+///
+/// Instance rows: root, x.
+struct NonMembershipCircuit {
+    low: Value<Fp>,
+    high: Value<Fp>,
+    low_index: Value<u64>,
+    high_index: Value<u64>,
+    low_siblings: [Value<Fp>; DEPTH],
+    high_siblings: [Value<Fp>; DEPTH],
+}
+
+impl NonMembershipCircuit {
+    fn new(tree: &MerkleTree<Fp>, list: &[Fp], low_index: usize, high_index: usize) -> Self {
+        let siblings = |index: usize| -> [Value<Fp>; DEPTH] {
+            let siblings: [Fp; DEPTH] = tree.path(index).siblings.try_into().unwrap();
+            siblings.map(Value::known)
+        };
+        NonMembershipCircuit {
+            low: Value::known(list[low_index]),
+            high: Value::known(list[high_index]),
+            low_index: Value::known(low_index as u64),
+            high_index: Value::known(high_index as u64),
+            low_siblings: siblings(low_index),
+            high_siblings: siblings(high_index),
+        }
+    }
+}
+
+impl Circuit<Fp> for NonMembershipCircuit {
+    type Config = SetConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        NonMembershipCircuit {
+            low: Value::unknown(),
+            high: Value::unknown(),
+            low_index: Value::unknown(),
+            high_index: Value::unknown(),
+            low_siblings: [Value::unknown(); DEPTH],
+            high_siblings: [Value::unknown(); DEPTH],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let merkle = MerkleChip::construct(config.merkle);
+        let bits = BitsChip::construct(config.bits);
+        let sorted = SortedChip::construct(config.sorted);
+        sorted.load_table(layouter.namespace(|| "range table"))?;
+
+        let x = merkle.load_instance(layouter.namespace(|| "x"), config.instance, 1)?;
+        let low = merkle.load_private(layouter.namespace(|| "low"), self.low)?;
+        let high = merkle.load_private(layouter.namespace(|| "high"), self.high)?;
+
+        // Adjacent leaves: the high index follows the low one, and both fit
+        // in DEPTH bits, so the low one is not the last leaf.
+        let low_index = merkle.load_private(
+            layouter.namespace(|| "low index"),
+            self.low_index.map(Fp::from),
+        )?;
+        let high_index = merkle.load_private(
+            layouter.namespace(|| "high index"),
+            self.high_index.map(Fp::from),
+        )?;
+        sorted.assert_successor(layouter.namespace(|| "adjacent"), &low_index, &high_index)?;
+        let low_bits = bits.decompose(layouter.namespace(|| "low bits"), &low_index, DEPTH)?;
+        let high_bits = bits.decompose(layouter.namespace(|| "high bits"), &high_index, DEPTH)?;
+
+        let low_root = merkle.compute_root_at_index(
+            layouter.namespace(|| "low root"),
+            &low,
+            &self.low_siblings,
+            &low_bits,
+        )?;
+        layouter.constrain_instance(low_root.cell(), config.instance, 0)?;
+        let high_root = merkle.compute_root_at_index(
+            layouter.namespace(|| "high root"),
+            &high,
+            &self.high_siblings,
+            &high_bits,
+        )?;
+        layouter.constrain_instance(high_root.cell(), config.instance, 0)?;
+
+        sorted.assert_increasing(layouter.namespace(|| "between"), &[low, x, high], BITS)
+    }
+}
+
+const K: u32 = 17;
+
+fn denylist() -> Vec<Fp> {
+    [0, 7, 19, 20, 42, 1000, 1 << 40, u64::MAX]
+        .map(Fp::from)
+        .to_vec()
+}
+
+fn verify_sorted<const N: usize>(
+    list: [Fp; N],
+    root: Fp,
+) -> Result<(), Vec<halo2_proofs::dev::VerifyFailure>> {
+    let circuit = SortedListCircuit {
+        list: list.map(Value::known),
+    };
+    MockProver::run(K, &circuit, vec![vec![root]])
+        .unwrap()
+        .verify()
+}
+
+fn test_sorted_list() {
+    let list: [Fp; 8] = denylist().try_into().unwrap();
+    let root = MerkleTree::new(DEPTH, list.to_vec()).root();
+    assert_eq!(verify_sorted(list, root), Ok(()));
+
+    // A shorter list, zero-padded in the tree.
+    let short = [7, 19, 1000].map(Fp::from);
+    let short_root = MerkleTree::new(DEPTH, short.to_vec()).root();
+    assert_eq!(verify_sorted(short, short_root), Ok(()));
+
+    // The root of another list.
+    assert!(verify_sorted(short, root).is_err());
+
+    let tree_of = |list: [Fp; 3]| MerkleTree::new(DEPTH, list.to_vec()).root();
+
+    // Repeated and out-of-order entries.
+    let repeated = [7, 19, 19].map(Fp::from);
+    assert!(verify_sorted(repeated, tree_of(repeated)).is_err());
+    let unordered = [7, 1000, 19].map(Fp::from);
+    assert!(verify_sorted(unordered, tree_of(unordered)).is_err());
+
+    // -1 is one below 3 only modulo p; the range check on the entries
+    // rules out the wrap-around.
+    let wrapped = [-Fp::one(), Fp::from(3), Fp::from(5)];
+    assert!(verify_sorted(wrapped, tree_of(wrapped)).is_err());
+}
+
+fn verify_excluded(
+    circuit: &NonMembershipCircuit,
+    root: Fp,
+    x: Fp,
+) -> Result<(), Vec<halo2_proofs::dev::VerifyFailure>> {
+    MockProver::run(K, circuit, vec![vec![root, x]])
+        .unwrap()
+        .verify()
+}
+
+fn test_non_membership() {
+    let list = denylist();
+    let tree = MerkleTree::new(DEPTH, list.clone());
+    let root = tree.root();
+    let excluded = |x: u64, low: usize, high: usize| {
+        let circuit = NonMembershipCircuit::new(&tree, &list, low, high);
+        verify_excluded(&circuit, root, Fp::from(x))
+    };
+
+    // Values in every kind of gap, including next to the sentinels.
+    assert_eq!(excluded(1, 0, 1), Ok(()));
+    assert_eq!(excluded(8, 1, 2), Ok(()));
+    assert_eq!(excluded(500, 4, 5), Ok(()));
+    assert_eq!(excluded(u64::MAX - 1, 6, 7), Ok(()));
+
+    // Listed values have no pair around them.
+    assert!(excluded(19, 1, 2).is_err());
+    assert!(excluded(19, 2, 3).is_err());
+    assert!(excluded(7, 0, 1).is_err());
+
+    // No gap between 19 and 20.
+    assert!(excluded(20, 2, 3).is_err());
+
+    // Skipping over 19 with leaves two apart.
+    assert!(excluded(19, 1, 3).is_err());
+    let mut skipping = NonMembershipCircuit::new(&tree, &list, 1, 3);
+    skipping.high_index = Value::known(2);
+    assert!(verify_excluded(&skipping, root, Fp::from(19)).is_err());
+
+    // The zero padding after a short list is no upper neighbour.
+    let mut padded = list[..6].to_vec();
+    let padded_tree = MerkleTree::new(DEPTH, padded.clone());
+    padded.resize(1 << DEPTH, Fp::zero());
+    let past_end = NonMembershipCircuit::new(&padded_tree, &padded, 5, 6);
+    assert!(verify_excluded(&past_end, padded_tree.root(), Fp::from(1 << 41)).is_err());
+
+    // A different root.
+    let other = MerkleTree::new(DEPTH, list[1..].to_vec()).root();
+    assert!(verify_excluded(
+        &NonMembershipCircuit::new(&tree, &list, 4, 5),
+        other,
+        Fp::from(500)
+    )
+    .is_err());
+}
+
+fn main() {
+    test_sorted_list();
+    test_non_membership();
+}
@@ -0,0 +1,295 @@
+use halo2_learning::{
+    pedersen::{self, PedersenChip, PedersenConfig},
+    range::{RangeCheckChip, RangeCheckConfig},
+};
+use halo2_proofs::{
+    arithmetic::CurveAffine,
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::{pallas, Fp, Fq},
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Instance, Selector},
+    poly::Rotation,
+};
+
+/// Bits the range circuit allows between the committed value and its bounds.
+const RANGE_BITS: usize = 64;
+
+#[derive(Clone, Debug)]
+struct CommitmentConfig {
+    instance: Column<Instance>,
+    advice: [Column<Advice>; 3],
+    s_sub: Selector,
+    pedersen: PedersenConfig,
+    range: RangeCheckConfig,
+}
+
+/// Loads values and takes differences next to `PedersenChip`:
+///
+/// a b c
+///
+/// with `c = a - b` where `s_sub` is enabled.
+struct CommitmentChip {
+    config: CommitmentConfig,
+}
+
+impl CommitmentChip {
+    fn construct(config: CommitmentConfig) -> Self {
+        CommitmentChip { config }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> CommitmentConfig {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let advice = [(); 3].map(|_| meta.advice_column());
+        for column in advice {
+            meta.enable_equality(column);
+        }
+        let constant = meta.fixed_column();
+        meta.enable_constant(constant);
+        let s_sub = meta.selector();
+
+        meta.create_gate("sub", |meta| {
+            let [a, b, c] = advice.map(|column| meta.query_advice(column, Rotation::cur()));
+            let s_sub = meta.query_selector(s_sub);
+            vec![s_sub * (a - b - c)]
+        });
+
+        let pedersen = PedersenChip::configure(meta);
+        let range = RangeCheckChip::configure(meta);
+
+        CommitmentConfig {
+            instance,
+            advice,
+            s_sub,
+            pedersen,
+            range,
+        }
+    }
+
+    fn load_private(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        value: Value<Fp>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        layouter.assign_region(
+            || "load private",
+            |mut region| region.assign_advice(|| "value", self.config.advice[0], 0, || value),
+        )
+    }
+
+    fn load_instance(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        row: usize,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "load instance",
+            |mut region| {
+                region.assign_advice_from_instance(
+                    || "value",
+                    config.instance,
+                    row,
+                    config.advice[0],
+                    0,
+                )
+            },
+        )
+    }
+
+    fn sub(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        a: &AssignedCell<Fp, Fp>,
+        b: &AssignedCell<Fp, Fp>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "sub",
+            |mut region| {
+                config.s_sub.enable(&mut region, 0)?;
+                a.copy_advice(|| "a", &mut region, config.advice[0], 0)?;
+                b.copy_advice(|| "b", &mut region, config.advice[1], 0)?;
+                let c = a.value().copied() - b.value();
+                region.assign_advice(|| "a - b", config.advice[2], 0, || c)
+            },
+        )
+    }
+
+    /// Opens the commitment in instance rows 0 and 1 to a private value,
+    /// which is returned.
+    fn open(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        value: Value<Fp>,
+        blind: Value<Fq>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let pedersen = PedersenChip::construct(self.config.pedersen.clone());
+        let value = self.load_private(layouter.namespace(|| "value"), value)?;
+        let commitment = pedersen.commit(layouter.namespace(|| "commit"), &value, blind)?;
+        let commitment = commitment.inner();
+        layouter.constrain_instance(commitment.x().cell(), self.config.instance, 0)?;
+        layouter.constrain_instance(commitment.y().cell(), self.config.instance, 1)?;
+        Ok(value)
+    }
+}
+
+/// Proves knowledge of an opening of the public commitment.
+/// Instance: `[C.x, C.y]`.
+#[derive(Default)]
+struct OpeningCircuit {
+    value: Value<Fp>,
+    blind: Value<Fq>,
+}
+
+impl Circuit<Fp> for OpeningCircuit {
+    type Config = CommitmentConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        CommitmentChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = CommitmentChip::construct(config.clone());
+        PedersenChip::construct(config.pedersen).load_table(layouter.namespace(|| "ecc table"))?;
+        chip.open(layouter.namespace(|| "open"), self.value, self.blind)?;
+        Ok(())
+    }
+}
+
+/// Proves that the value in the public commitment lies in `[lo, hi]`, for
+/// public bounds below `2^64`. Instance: `[C.x, C.y, lo, hi]`.
+#[derive(Default)]
+struct RangeCircuit {
+    value: Value<Fp>,
+    blind: Value<Fq>,
+}
+
+impl Circuit<Fp> for RangeCircuit {
+    type Config = CommitmentConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        CommitmentChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = CommitmentChip::construct(config.clone());
+        let range = RangeCheckChip::construct(config.range);
+        range.load_table(layouter.namespace(|| "range table"))?;
+        PedersenChip::construct(config.pedersen).load_table(layouter.namespace(|| "ecc table"))?;
+
+        let value = chip.open(layouter.namespace(|| "open"), self.value, self.blind)?;
+        let lo = chip.load_instance(layouter.namespace(|| "lo"), 2)?;
+        let hi = chip.load_instance(layouter.namespace(|| "hi"), 3)?;
+
+        // Both differences wrap around to huge values if a bound is violated.
+        let above_lo = chip.sub(layouter.namespace(|| "value - lo"), &value, &lo)?;
+        let below_hi = chip.sub(layouter.namespace(|| "hi - value"), &hi, &value)?;
+        range.range_check(
+            layouter.namespace(|| "differences"),
+            &[above_lo, below_hi],
+            RANGE_BITS,
+        )
+    }
+}
+
+fn commitment_instance(commitment: &pallas::Affine) -> Vec<Fp> {
+    let coordinates = commitment.coordinates().unwrap();
+    vec![*coordinates.x(), *coordinates.y()]
+}
+
+fn test_opening() {
+    let k = 12;
+    let value = Fp::from(5_000);
+    let blind = Fq::from(0x0123_4567_89ab_cdef) * Fq::from(0xfedc_ba98_7654_3210);
+    let commitment = pedersen::commit(value, blind);
+
+    let circuit = OpeningCircuit {
+        value: Value::known(value),
+        blind: Value::known(blind),
+    };
+    let prover = MockProver::run(k, &circuit, vec![commitment_instance(&commitment)]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // Hiding: the same value under another blinding factor.
+    let other = pedersen::commit(value, blind + Fq::one());
+    assert_ne!(other, commitment);
+    let prover = MockProver::run(k, &circuit, vec![commitment_instance(&other)]).unwrap();
+    assert!(prover.verify().is_err());
+
+    // Binding: another value under the same blinding factor.
+    let circuit = OpeningCircuit {
+        value: Value::known(value + Fp::one()),
+        blind: Value::known(blind),
+    };
+    let prover = MockProver::run(k, &circuit, vec![commitment_instance(&commitment)]).unwrap();
+    assert!(prover.verify().is_err());
+
+    // Zero is a valid value.
+    let zero = pedersen::commit(Fp::zero(), blind);
+    let circuit = OpeningCircuit {
+        value: Value::known(Fp::zero()),
+        blind: Value::known(blind),
+    };
+    let prover = MockProver::run(k, &circuit, vec![commitment_instance(&zero)]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // So is the largest base field element: every one is below the scalar
+    // field order, so the value is read as it is.
+    let minus_one = pedersen::commit(-Fp::one(), blind);
+    let circuit = OpeningCircuit {
+        value: Value::known(-Fp::one()),
+        blind: Value::known(blind),
+    };
+    let prover = MockProver::run(k, &circuit, vec![commitment_instance(&minus_one)]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+}
+
+fn test_range() {
+    let k = 17;
+    let value = Fp::from(5_000);
+    let blind = Fq::from(0x5eed_b11d);
+    let commitment = pedersen::commit(value, blind);
+
+    let circuit = RangeCircuit {
+        value: Value::known(value),
+        blind: Value::known(blind),
+    };
+    let run = |lo: u64, hi: u64| {
+        let mut instance = commitment_instance(&commitment);
+        instance.extend([Fp::from(lo), Fp::from(hi)]);
+        MockProver::run(k, &circuit, vec![instance])
+            .unwrap()
+            .verify()
+    };
+
+    // Committed now, proven in range later.
+    assert_eq!(run(1_000, 10_000), Ok(()));
+    assert_eq!(run(5_000, 5_000), Ok(()));
+    assert_eq!(run(0, u64::MAX), Ok(()));
+    assert!(run(5_001, 10_000).is_err());
+    assert!(run(0, 4_999).is_err());
+}
+
+fn main() {
+    test_opening();
+    test_range();
+}
@@ -0,0 +1,93 @@
+use halo2_learning::poseidon::{self, PoseidonChip, PoseidonConfig};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::Fp,
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Instance},
+};
+
+#[derive(Clone, Debug)]
+struct HashConfig {
+    advice: Column<Advice>,
+    instance: Column<Instance>,
+    poseidon: PoseidonConfig<Fp>,
+}
+
+/// Proves knowledge of a private `L`-element message hashing to the public
+/// value.
+struct HashCircuit<const L: usize> {
+    message: [Value<Fp>; L],
+}
+
+impl<const L: usize> Circuit<Fp> for HashCircuit<L> {
+    type Config = HashConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        HashCircuit {
+            message: [Value::unknown(); L],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let advice = meta.advice_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(advice);
+        meta.enable_equality(instance);
+        let poseidon = PoseidonChip::configure(meta);
+
+        HashConfig {
+            advice,
+            instance,
+            poseidon,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let message = layouter.assign_region(
+            || "load message",
+            |mut region| {
+                self.message
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| region.assign_advice(|| "word", config.advice, i, || *value))
+                    .collect::<Result<Vec<_>, _>>()
+            },
+        )?;
+        let message: [AssignedCell<Fp, Fp>; L] = message.try_into().unwrap();
+
+        let chip = PoseidonChip::construct(config.poseidon);
+        let digest = chip.hash(layouter.namespace(|| "poseidon"), &message)?;
+        layouter.constrain_instance(digest.cell(), config.instance, 0)
+    }
+}
+
+fn test_hash<const L: usize>(message: [Fp; L]) {
+    let k = 8;
+    let digest = poseidon::hash(message);
+    let circuit = HashCircuit {
+        message: message.map(Value::known),
+    };
+
+    let prover = MockProver::run(k, &circuit, vec![vec![digest]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let prover = MockProver::run(k, &circuit, vec![vec![digest + Fp::one()]]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+fn main() {
+    test_hash([Fp::from(42)]);
+    test_hash([Fp::from(1), Fp::from(2)]);
+    test_hash([1, 2, 3, 4, 5].map(Fp::from));
+
+    // The length is part of the domain: zero padding changes the hash.
+    assert_ne!(
+        poseidon::hash([Fp::from(1), Fp::from(2)]),
+        poseidon::hash([Fp::from(1), Fp::from(2), Fp::zero()])
+    );
+}
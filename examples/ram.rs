@@ -0,0 +1,205 @@
+use halo2_learning::ram::{MemoryAccess, RamChip, RamConfig};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::Fp,
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Constraints, Error, Expression, Selector},
+    poly::Rotation,
+};
+
+/// A memory access of the trace: `(addr, is_write, value)`.
+type Access = (u64, bool, u64);
+
+#[derive(Clone, Debug)]
+struct TraceConfig {
+    advice: [Column<Advice>; 4],
+    selector: Selector,
+    ram: RamConfig<Fp>,
+}
+
+/// A bare step circuit, one memory access per row, as in `FiboChip`:
+///
+/// addr time is_write value
+///
+/// with `time_next = time + 1` starting from zero. A real step circuit would
+/// constrain the addresses and values by its instructions; this one leaves
+/// them to `RamChip`.
+struct TraceChip {
+    config: TraceConfig,
+}
+
+impl TraceChip {
+    fn construct(config: TraceConfig) -> Self {
+        TraceChip { config }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> TraceConfig {
+        let advice = [(); 4].map(|_| meta.advice_column());
+        for column in advice {
+            meta.enable_equality(column);
+        }
+        let selector = meta.selector();
+
+        meta.create_gate("step", |meta| {
+            let selector = meta.query_selector(selector);
+            let time = meta.query_advice(advice[1], Rotation::cur());
+            let time_next = meta.query_advice(advice[1], Rotation::next());
+            Constraints::with_selector(
+                selector,
+                vec![time_next - time - Expression::Constant(Fp::one())],
+            )
+        });
+
+        let ram = RamChip::configure(meta);
+        TraceConfig {
+            advice,
+            selector,
+            ram,
+        }
+    }
+
+    fn assign(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        trace: &[Value<Access>],
+    ) -> Result<Vec<MemoryAccess<Fp>>, Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "trace",
+            |mut region| {
+                let mut accesses = Vec::with_capacity(trace.len());
+                for (row, access) in trace.iter().enumerate() {
+                    if row + 1 < trace.len() {
+                        config.selector.enable(&mut region, row)?;
+                    }
+                    let [addr_column, time_column, write_column, value_column] = config.advice;
+                    let addr = region.assign_advice(
+                        || "addr",
+                        addr_column,
+                        row,
+                        || access.map(|(addr, _, _)| Fp::from(addr)),
+                    )?;
+                    let time = if row == 0 {
+                        region.assign_advice_from_constant(
+                            || "time",
+                            time_column,
+                            row,
+                            Fp::zero(),
+                        )?
+                    } else {
+                        region.assign_advice(
+                            || "time",
+                            time_column,
+                            row,
+                            || Value::known(Fp::from(row as u64)),
+                        )?
+                    };
+                    let is_write = region.assign_advice(
+                        || "is_write",
+                        write_column,
+                        row,
+                        || access.map(|(_, is_write, _)| Fp::from(is_write as u64)),
+                    )?;
+                    let value = region.assign_advice(
+                        || "value",
+                        value_column,
+                        row,
+                        || access.map(|(_, _, value)| Fp::from(value)),
+                    )?;
+                    accesses.push(MemoryAccess {
+                        addr,
+                        time,
+                        is_write,
+                        value,
+                    });
+                }
+                Ok(accesses)
+            },
+        )
+    }
+}
+
+/// Proves that a trace of memory accesses is consistent. The number of
+/// accesses is fixed at keygen.
+#[derive(Default)]
+struct RamCircuit {
+    trace: Vec<Value<Access>>,
+}
+
+impl Circuit<Fp> for RamCircuit {
+    type Config = TraceConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        RamCircuit {
+            trace: vec![Value::unknown(); self.trace.len()],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        TraceChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = TraceChip::construct(config.clone());
+        let ram = RamChip::construct(config.ram);
+        ram.load_table(layouter.namespace(|| "range table"))?;
+        let accesses = chip.assign(layouter.namespace(|| "trace"), &self.trace)?;
+        ram.assert_consistent(layouter.namespace(|| "ram"), &accesses)
+    }
+}
+
+fn verify(trace: &[Access]) -> Result<(), Vec<halo2_proofs::dev::VerifyFailure>> {
+    let circuit = RamCircuit {
+        trace: trace.iter().map(|access| Value::known(*access)).collect(),
+    };
+    MockProver::run(17, &circuit, vec![]).unwrap().verify()
+}
+
+fn test_ram() {
+    const W: bool = true;
+    const R: bool = false;
+    let trace = [
+        (3, W, 7),
+        (1, W, 5),
+        (3, R, 7),
+        (2, R, 0),
+        (3, W, 8),
+        (3, R, 8),
+        (1, R, 5),
+        (1, R, 5),
+        (1_000_000, W, 1),
+        (1_000_000, R, 1),
+    ];
+    assert_eq!(verify(&trace), Ok(()));
+    assert_eq!(verify(&trace[..1]), Ok(()));
+
+    let forge = |step: usize, access: Access| {
+        let mut forged = trace;
+        forged[step] = access;
+        verify(&forged)
+    };
+    // A stale read, after the address was overwritten.
+    assert!(forge(5, (3, R, 7)).is_err());
+    // A read of an address never written.
+    assert!(forge(3, (2, R, 4)).is_err());
+    // A read that returns the value of a later write.
+    assert!(forge(2, (3, R, 8)).is_err());
+    // A read of the previous address.
+    assert!(forge(3, (2, R, 5)).is_err());
+    // Overwriting, then reading the new value.
+    let mut overwritten = trace;
+    overwritten[6] = (1, W, 6);
+    overwritten[7] = (1, R, 6);
+    assert_eq!(verify(&overwritten), Ok(()));
+    // The next read still returning the old one.
+    assert!(forge(6, (1, W, 6)).is_err());
+}
+
+fn main() {
+    test_ram();
+}
@@ -0,0 +1,655 @@
+use std::collections::{BTreeSet, HashMap};
+
+use halo2_learning::poseidon::{self, PoseidonChip, PoseidonConfig};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::Fp,
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Instance, Selector, TableColumn},
+    poly::Rotation,
+};
+
+type ByteSet = [bool; 256];
+
+/// The supported subset: literals, `\` escapes, `.` for any byte,
+/// classes like `[a-z0-9._]` or `[^@]`, groups, `|`, and the `*`, `+`, `?`
+/// quantifiers. Byte 0 is never matched; it pads strings in the circuit.
+#[derive(Clone, Debug)]
+enum Regex {
+    Bytes(Box<ByteSet>),
+    Concat(Vec<Regex>),
+    Alt(Vec<Regex>),
+    Star(Box<Regex>),
+    Plus(Box<Regex>),
+    Optional(Box<Regex>),
+}
+
+#[derive(Debug, PartialEq)]
+enum RegexError {
+    UnexpectedEnd,
+    Unexpected { position: usize, found: char },
+}
+
+struct Parser<'a> {
+    pattern: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn parse(pattern: &str) -> Result<Regex, RegexError> {
+        let mut parser = Parser {
+            pattern: pattern.as_bytes(),
+            position: 0,
+        };
+        let regex = parser.alt()?;
+        match parser.peek() {
+            Some(found) => Err(parser.unexpected(found)),
+            None => Ok(regex),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.pattern.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Result<u8, RegexError> {
+        let byte = self.peek().ok_or(RegexError::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.peek() == Some(byte);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn unexpected(&self, found: u8) -> RegexError {
+        RegexError::Unexpected {
+            position: self.position,
+            found: found as char,
+        }
+    }
+
+    fn alt(&mut self) -> Result<Regex, RegexError> {
+        let mut branches = vec![self.concat()?];
+        while self.eat(b'|') {
+            branches.push(self.concat()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().unwrap()
+        } else {
+            Regex::Alt(branches)
+        })
+    }
+
+    fn concat(&mut self) -> Result<Regex, RegexError> {
+        let mut items = vec![];
+        while !matches!(self.peek(), None | Some(b'|') | Some(b')')) {
+            items.push(self.repeat()?);
+        }
+        Ok(Regex::Concat(items))
+    }
+
+    fn repeat(&mut self) -> Result<Regex, RegexError> {
+        let mut regex = self.atom()?;
+        loop {
+            regex = match self.peek() {
+                Some(b'*') => Regex::Star(Box::new(regex)),
+                Some(b'+') => Regex::Plus(Box::new(regex)),
+                Some(b'?') => Regex::Optional(Box::new(regex)),
+                _ => return Ok(regex),
+            };
+            self.position += 1;
+        }
+    }
+
+    fn atom(&mut self) -> Result<Regex, RegexError> {
+        let mut set = [false; 256];
+        match self.peek().ok_or(RegexError::UnexpectedEnd)? {
+            b'(' => {
+                self.position += 1;
+                let regex = self.alt()?;
+                if !self.eat(b')') {
+                    return Err(self
+                        .peek()
+                        .map_or(RegexError::UnexpectedEnd, |found| self.unexpected(found)));
+                }
+                return Ok(regex);
+            }
+            b'[' => {
+                self.position += 1;
+                return self.class();
+            }
+            b'.' => set[1..].fill(true),
+            b'\\' => {
+                self.position += 1;
+                set[self.peek().ok_or(RegexError::UnexpectedEnd)? as usize] = true;
+            }
+            found @ (b'*' | b'+' | b'?' | b')' | b']') => return Err(self.unexpected(found)),
+            byte => set[byte as usize] = true,
+        }
+        self.position += 1;
+        set[0] = false;
+        Ok(Regex::Bytes(Box::new(set)))
+    }
+
+    /// The inside of `[...]`, after the opening bracket.
+    fn class(&mut self) -> Result<Regex, RegexError> {
+        let negated = self.eat(b'^');
+        let mut set = [false; 256];
+        while !self.eat(b']') {
+            let low = self.class_byte()?;
+            let high = if self.peek() == Some(b'-')
+                && self.pattern.get(self.position + 1) != Some(&b']')
+            {
+                self.position += 1;
+                self.class_byte()?
+            } else {
+                low
+            };
+            for byte in low..=high {
+                set[byte as usize] = true;
+            }
+        }
+        if negated {
+            set = set.map(|member| !member);
+        }
+        set[0] = false;
+        Ok(Regex::Bytes(Box::new(set)))
+    }
+
+    fn class_byte(&mut self) -> Result<u8, RegexError> {
+        match self.next()? {
+            b'\\' => self.next(),
+            byte => Ok(byte),
+        }
+    }
+}
+
+/// A Thompson NFA: byte-set and epsilon edges between numbered states.
+#[derive(Default)]
+struct Nfa {
+    edges: Vec<Vec<(ByteSet, usize)>>,
+    epsilon: Vec<Vec<usize>>,
+}
+
+impl Nfa {
+    fn state(&mut self) -> usize {
+        self.edges.push(vec![]);
+        self.epsilon.push(vec![]);
+        self.edges.len() - 1
+    }
+
+    /// Adds `regex` and returns its start and accepting states.
+    fn build(&mut self, regex: &Regex) -> (usize, usize) {
+        let (start, end) = (self.state(), self.state());
+        match regex {
+            Regex::Bytes(set) => self.edges[start].push((**set, end)),
+            Regex::Concat(items) => {
+                let mut last = start;
+                for item in items {
+                    let (item_start, item_end) = self.build(item);
+                    self.epsilon[last].push(item_start);
+                    last = item_end;
+                }
+                self.epsilon[last].push(end);
+            }
+            Regex::Alt(branches) => {
+                for branch in branches {
+                    let (branch_start, branch_end) = self.build(branch);
+                    self.epsilon[start].push(branch_start);
+                    self.epsilon[branch_end].push(end);
+                }
+            }
+            Regex::Star(inner) | Regex::Plus(inner) | Regex::Optional(inner) => {
+                let (inner_start, inner_end) = self.build(inner);
+                self.epsilon[start].push(inner_start);
+                self.epsilon[inner_end].push(end);
+                if !matches!(regex, Regex::Plus(_)) {
+                    self.epsilon[start].push(end);
+                }
+                if !matches!(regex, Regex::Optional(_)) {
+                    self.epsilon[inner_end].push(inner_start);
+                }
+            }
+        }
+        (start, end)
+    }
+
+    fn closure(&self, states: impl IntoIterator<Item = usize>) -> BTreeSet<usize> {
+        let mut closure = BTreeSet::new();
+        let mut stack: Vec<usize> = states.into_iter().collect();
+        while let Some(state) = stack.pop() {
+            if closure.insert(state) {
+                stack.extend(&self.epsilon[state]);
+            }
+        }
+        closure
+    }
+}
+
+/// Rejects everything from here on.
+const DEAD: usize = 0;
+/// Reached on the first padding byte after an accepted string, and kept
+/// through the rest of the padding.
+const END: usize = 1;
+const START: usize = 2;
+
+/// A DFA over bytes, extended with `DEAD` and `END` so that a padded string
+/// ends in `END` exactly when it is an accepted string followed by zeros.
+#[derive(Clone, Debug)]
+struct Dfa {
+    transitions: Vec<[usize; 256]>,
+}
+
+impl Dfa {
+    /// Compiles `pattern` by subset construction. The DFA matches whole
+    /// strings.
+    fn compile(pattern: &str) -> Result<Dfa, RegexError> {
+        let regex = Parser::parse(pattern)?;
+        let mut nfa = Nfa::default();
+        let (start, accept) = nfa.build(&regex);
+
+        let mut transitions = vec![[DEAD; 256], [DEAD; 256]];
+        transitions[END][0] = END;
+        let mut subsets = vec![nfa.closure([start])];
+        let mut ids = HashMap::from([(subsets[0].clone(), START)]);
+
+        let mut i = 0;
+        while let Some(subset) = subsets.get(i).cloned() {
+            let mut row = [DEAD; 256];
+            if subset.contains(&accept) {
+                row[0] = END;
+            }
+            for (byte, next) in row.iter_mut().enumerate().skip(1) {
+                let moved = subset.iter().flat_map(|state| {
+                    nfa.edges[*state]
+                        .iter()
+                        .filter(move |(set, _)| set[byte])
+                        .map(|(_, target)| *target)
+                });
+                let target = nfa.closure(moved);
+                if target.is_empty() {
+                    continue;
+                }
+                *next = *ids.entry(target.clone()).or_insert_with(|| {
+                    subsets.push(target);
+                    START + subsets.len() - 1
+                });
+            }
+            transitions.push(row);
+            i += 1;
+        }
+
+        Ok(Dfa { transitions })
+    }
+
+    fn num_states(&self) -> usize {
+        self.transitions.len()
+    }
+
+    /// The states visited on `bytes` from `START`, one more than the bytes.
+    fn states(&self, bytes: &[u8]) -> Vec<usize> {
+        let mut states = vec![START];
+        for byte in bytes {
+            states.push(self.transitions[*states.last().unwrap()][*byte as usize]);
+        }
+        states
+    }
+
+    fn accepts(&self, input: &[u8]) -> bool {
+        let states = self.states(input);
+        self.transitions[*states.last().unwrap()][0] == END
+    }
+}
+
+/// `input` padded with zeros to `max_len`, or `None` if it does not fit.
+fn pad(input: &[u8], max_len: usize) -> Option<Vec<u8>> {
+    let mut bytes = input.to_vec();
+    if bytes.len() > max_len {
+        return None;
+    }
+    bytes.resize(max_len, 0);
+    Some(bytes)
+}
+
+/// `Poseidon(salt, bytes...)` through `hash_slice`.
+fn commitment(salt: Fp, bytes: &[u8]) -> Fp {
+    let message: Vec<Fp> = std::iter::once(salt)
+        .chain(bytes.iter().map(|byte| Fp::from(*byte as u64)))
+        .collect();
+    poseidon::hash_slice(&message)
+}
+
+#[derive(Clone, Debug)]
+struct RegexConfig {
+    instance: Column<Instance>,
+    state: Column<Advice>,
+    byte: Column<Advice>,
+    s_step: Selector,
+    table_state: TableColumn,
+    table_byte: TableColumn,
+    table_next: TableColumn,
+    poseidon: PoseidonConfig<Fp>,
+}
+
+/// Runs the DFA, one byte per row:
+///
+/// state byte
+///
+/// with `(state, byte, state_next)` looked up in the transition table,
+/// which also bounds the bytes. The first state is `START`; after the
+/// padded string one more zero byte must lead to `END`. Disabled rows look
+/// up `(DEAD, 0, DEAD)`, a real transition.
+struct RegexChip {
+    config: RegexConfig,
+}
+
+impl RegexChip {
+    fn construct(config: RegexConfig) -> Self {
+        RegexChip { config }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> RegexConfig {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let state = meta.advice_column();
+        let byte = meta.advice_column();
+        meta.enable_equality(state);
+        meta.enable_equality(byte);
+
+        let s_step = meta.complex_selector();
+        let table_state = meta.lookup_table_column();
+        let table_byte = meta.lookup_table_column();
+        let table_next = meta.lookup_table_column();
+
+        meta.lookup(|meta| {
+            let s_step = meta.query_selector(s_step);
+            let state_cur = meta.query_advice(state, Rotation::cur());
+            let byte = meta.query_advice(byte, Rotation::cur());
+            let state_next = meta.query_advice(state, Rotation::next());
+            vec![
+                (s_step.clone() * state_cur, table_state),
+                (s_step.clone() * byte, table_byte),
+                (s_step * state_next, table_next),
+            ]
+        });
+
+        // Also enables the constant column for START, END and the last byte.
+        let poseidon = PoseidonChip::configure(meta);
+
+        RegexConfig {
+            instance,
+            state,
+            byte,
+            s_step,
+            table_state,
+            table_byte,
+            table_next,
+            poseidon,
+        }
+    }
+
+    /// Loads every transition of `dfa`. The table is fixed at keygen.
+    fn load_table(&self, mut layouter: impl Layouter<Fp>, dfa: &Dfa) -> Result<(), Error> {
+        let config = &self.config;
+        layouter.assign_table(
+            || "transitions",
+            |mut table| {
+                for (state, row) in dfa.transitions.iter().enumerate() {
+                    for (byte, next) in row.iter().enumerate() {
+                        let offset = state * 256 + byte;
+                        let columns = [config.table_state, config.table_byte, config.table_next];
+                        for (column, value) in columns.into_iter().zip([state, byte, *next]) {
+                            table.assign_cell(
+                                || "transition",
+                                column,
+                                offset,
+                                || Value::known(Fp::from(value as u64)),
+                            )?;
+                        }
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+
+    fn load_private(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        value: Value<Fp>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        layouter.assign_region(
+            || "load private",
+            |mut region| region.assign_advice(|| "value", self.config.byte, 0, || value),
+        )
+    }
+
+    /// Runs `dfa` over the `max_len` padded bytes, and returns their cells.
+    fn assign_run(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        dfa: &Dfa,
+        bytes: &Value<Vec<u8>>,
+        max_len: usize,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let config = &self.config;
+        let states = bytes.as_ref().map(|bytes| dfa.states(bytes));
+
+        layouter.assign_region(
+            || "dfa run",
+            |mut region| {
+                region.assign_advice_from_constant(
+                    || "start",
+                    config.state,
+                    0,
+                    Fp::from(START as u64),
+                )?;
+
+                let mut cells = Vec::with_capacity(max_len);
+                for row in 0..max_len {
+                    config.s_step.enable(&mut region, row)?;
+                    let byte = bytes.as_ref().map(|bytes| Fp::from(bytes[row] as u64));
+                    cells.push(region.assign_advice(|| "byte", config.byte, row, || byte)?);
+                    let state = states
+                        .as_ref()
+                        .map(|states| Fp::from(states[row + 1] as u64));
+                    region.assign_advice(|| "state", config.state, row + 1, || state)?;
+                }
+
+                config.s_step.enable(&mut region, max_len)?;
+                region.assign_advice_from_constant(
+                    || "padding",
+                    config.byte,
+                    max_len,
+                    Fp::zero(),
+                )?;
+                region.assign_advice_from_constant(
+                    || "end",
+                    config.state,
+                    max_len + 1,
+                    Fp::from(END as u64),
+                )?;
+                Ok(cells)
+            },
+        )
+    }
+}
+
+/// Proves that the string in the public commitment `Poseidon(salt, bytes)`
+/// matches `dfa`, for strings of up to `MAX_LEN` bytes zero-padded to
+/// `MAX_LEN`.
+struct RegexCircuit<const MAX_LEN: usize> {
+    dfa: Dfa,
+    bytes: Value<Vec<u8>>,
+    salt: Value<Fp>,
+}
+
+impl<const MAX_LEN: usize> RegexCircuit<MAX_LEN> {
+    fn new(dfa: &Dfa, input: &[u8], salt: Fp) -> Self {
+        let bytes = pad(input, MAX_LEN).expect("input too long");
+        RegexCircuit {
+            dfa: dfa.clone(),
+            bytes: Value::known(bytes),
+            salt: Value::known(salt),
+        }
+    }
+}
+
+impl<const MAX_LEN: usize> Circuit<Fp> for RegexCircuit<MAX_LEN> {
+    type Config = RegexConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        RegexCircuit {
+            dfa: self.dfa.clone(),
+            bytes: Value::unknown(),
+            salt: Value::unknown(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        RegexChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = RegexChip::construct(config.clone());
+        chip.load_table(layouter.namespace(|| "transitions"), &self.dfa)?;
+        let bytes = chip.assign_run(
+            layouter.namespace(|| "run"),
+            &self.dfa,
+            &self.bytes,
+            MAX_LEN,
+        )?;
+
+        let salt = chip.load_private(layouter.namespace(|| "salt"), self.salt)?;
+        let message: Vec<_> = std::iter::once(salt).chain(bytes).collect();
+        let poseidon = PoseidonChip::construct(config.poseidon);
+        let commitment = poseidon.hash_slice(layouter.namespace(|| "commitment"), &message)?;
+        layouter.constrain_instance(commitment.cell(), config.instance, 0)
+    }
+}
+
+const EMAIL: &str = r"[a-z0-9._%+\-]+@[a-z0-9\-]+(\.[a-z0-9\-]+)*\.[a-z][a-z]+";
+
+fn test_compiler() {
+    let dfa = Dfa::compile(EMAIL).unwrap();
+    for email in ["alice@example.com", "a.b+c@mail.co.uk", "x_1@y-2.io"] {
+        assert!(dfa.accepts(email.as_bytes()), "{}", email);
+    }
+    for email in [
+        "alice",
+        "alice@",
+        "@example.com",
+        "alice@example",
+        "Alice@example.com",
+        "alice@@example.com",
+        "alice@example.c",
+    ] {
+        assert!(!dfa.accepts(email.as_bytes()), "{}", email);
+    }
+
+    let dfa = Dfa::compile("colou?r|(ab)*c|[^0-9]").unwrap();
+    for input in ["color", "colour", "c", "ababc", "x", "?"] {
+        assert!(dfa.accepts(input.as_bytes()), "{}", input);
+    }
+    for input in ["", "colouur", "abac", "aabc", "7", "xy"] {
+        assert!(!dfa.accepts(input.as_bytes()), "{}", input);
+    }
+
+    // Trailing NUL bytes are padding. Anywhere else they send the DFA to
+    // DEAD, or to END which rejects any further byte.
+    assert!(dfa.accepts(b"c\0\0"));
+    assert!(!dfa.accepts(b"c\0c"));
+    assert!(!dfa.accepts(b"colo\0r"));
+
+    assert_eq!(Dfa::compile("a(b").unwrap_err(), RegexError::UnexpectedEnd);
+    assert_eq!(
+        Dfa::compile("a)").unwrap_err(),
+        RegexError::Unexpected {
+            position: 1,
+            found: ')'
+        }
+    );
+    assert_eq!(
+        Dfa::compile("*a").unwrap_err(),
+        RegexError::Unexpected {
+            position: 0,
+            found: '*'
+        }
+    );
+}
+
+fn test_regex() {
+    const MAX_LEN: usize = 32;
+    let k = 13;
+    let dfa = Dfa::compile(EMAIL).unwrap();
+    assert!(dfa.num_states() * 256 < 1 << k);
+    let salt = Fp::from(0xe4a11);
+    let run = |circuit: &RegexCircuit<MAX_LEN>, commitment: Fp| {
+        MockProver::run(k, circuit, vec![vec![commitment]])
+            .unwrap()
+            .verify()
+    };
+    let prove = |input: &[u8]| {
+        let circuit = RegexCircuit::<MAX_LEN>::new(&dfa, input, salt);
+        run(&circuit, commitment(salt, &pad(input, MAX_LEN).unwrap()))
+    };
+
+    // Accepted, up to exactly MAX_LEN bytes.
+    for email in [
+        "alice@example.com",
+        "a.b+c@mail.co.uk",
+        "averylongname@subdomain.test.org",
+    ] {
+        assert!(email.len() <= MAX_LEN);
+        assert_eq!(prove(email.as_bytes()), Ok(()), "{}", email);
+    }
+
+    // Rejected.
+    for email in [
+        "",
+        "alice",
+        "alice@example",
+        "Alice@example.com",
+        "alice@@example.com",
+    ] {
+        assert!(prove(email.as_bytes()).is_err(), "{}", email);
+    }
+
+    // A NUL inside the string is not padding.
+    assert!(prove(b"alice@example.com\0x").is_err());
+    assert!(prove(b"alice\0@example.com").is_err());
+
+    // The proof does not open a commitment to another string or salt.
+    let circuit = RegexCircuit::<MAX_LEN>::new(&dfa, b"alice@example.com", salt);
+    let other = commitment(salt, &pad(b"bob@example.com", MAX_LEN).unwrap());
+    assert!(run(&circuit, other).is_err());
+    let salted = commitment(
+        salt + Fp::one(),
+        &pad(b"alice@example.com", MAX_LEN).unwrap(),
+    );
+    assert!(run(&circuit, salted).is_err());
+
+    // Too long: one byte over does not fit, and the accepted prefix of the
+    // right length proves nothing about the whole committed string.
+    let long = b"averylongname@subdomain.test.orgs";
+    assert_eq!(long.len(), MAX_LEN + 1);
+    assert!(dfa.accepts(long));
+    assert_eq!(pad(long, MAX_LEN), None);
+    let truncated = RegexCircuit::<MAX_LEN>::new(&dfa, &long[..MAX_LEN], salt);
+    assert!(run(&truncated, commitment(salt, long)).is_err());
+}
+
+fn main() {
+    test_compiler();
+    test_regex();
+}
@@ -0,0 +1,471 @@
+use halo2_learning::{
+    poseidon::{self, PoseidonChip, PoseidonConfig},
+    range::{RangeCheckChip, RangeCheckConfig},
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::Fp,
+    plonk::{
+        Advice, Circuit, Column, ConstraintSystem, Constraints, Error, Expression, Instance,
+        Selector,
+    },
+    poly::Rotation,
+};
+
+/// Width every sum is range-checked to. The total must fit, and so must
+/// every partial sum below it.
+const SUM_BITS: usize = 64;
+const DEPTH: usize = 4;
+const K: u32 = 17;
+
+/// A node of a Merkle sum tree: the hash commits to the sum below it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct SumNode {
+    hash: Fp,
+    sum: Fp,
+}
+
+impl SumNode {
+    /// The empty slot, which no leaf hashes to.
+    const EMPTY: SumNode = SumNode {
+        hash: Fp::zero(),
+        sum: Fp::zero(),
+    };
+
+    fn leaf(id_hash: Fp, balance: Fp) -> Self {
+        SumNode {
+            hash: poseidon::hash([id_hash, balance]),
+            sum: balance,
+        }
+    }
+
+    fn parent(left: &SumNode, right: &SumNode) -> Self {
+        SumNode {
+            hash: poseidon::hash([left.hash, left.sum, right.hash, right.sum]),
+            sum: left.sum + right.sum,
+        }
+    }
+}
+
+/// A full Merkle sum tree over `2^depth` slots, the unused ones empty.
+/// Sums are taken in the field: nothing here stops a negative balance, which
+/// is what the circuit's range checks are for.
+struct SumTree {
+    levels: Vec<Vec<SumNode>>,
+}
+
+impl SumTree {
+    fn new(depth: usize, mut leaves: Vec<SumNode>) -> Self {
+        assert!(leaves.len() <= 1 << depth, "too many leaves");
+        leaves.resize(1 << depth, SumNode::EMPTY);
+
+        let mut levels = vec![leaves];
+        for i in 0..depth {
+            let next = levels[i]
+                .chunks(2)
+                .map(|pair| SumNode::parent(&pair[0], &pair[1]))
+                .collect();
+            levels.push(next);
+        }
+        SumTree { levels }
+    }
+
+    fn root(&self) -> SumNode {
+        self.levels[self.levels.len() - 1][0]
+    }
+
+    /// The siblings and directions from the leaf at `index` up to the root.
+    fn path(&self, index: usize) -> (Vec<SumNode>, Vec<bool>) {
+        (0..self.levels.len() - 1)
+            .map(|i| {
+                let node = index >> i;
+                (self.levels[i][node ^ 1], node & 1 == 1)
+            })
+            .unzip()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct AssignedNode {
+    hash: AssignedCell<Fp, Fp>,
+    sum: AssignedCell<Fp, Fp>,
+}
+
+#[derive(Clone, Debug)]
+struct SumTreeConfig {
+    instance: Column<Instance>,
+    advice: [Column<Advice>; 10],
+    s_level: Selector,
+    poseidon: PoseidonConfig<Fp>,
+    range: RangeCheckConfig,
+}
+
+/// Recomputes the root of a Merkle sum tree from a leaf and its path, one
+/// row per level:
+///
+/// node_hash node_sum sibling_hash sibling_sum direction
+/// left_hash left_sum right_hash right_sum parent_sum
+///
+/// with `direction` boolean, the left and right pairs ordered as in
+/// `MerkleChip`, and `parent_sum = node_sum + sibling_sum`. The parent hash
+/// is `Poseidon(left_hash, left_sum, right_hash, right_sum)`.
+///
+/// Every sum on the path is range-checked to `SUM_BITS`, so no addition on
+/// it can wrap around the modulus. That covers one path only: a negative
+/// leaf elsewhere hides inside a sibling sum that is still in range. The
+/// guarantee that liabilities are neither offset by a negative balance nor
+/// overflowed back to a small total therefore holds only if every user
+/// verifies their own proof, in particular the owner of each leaf next to
+/// a negative one, whose path range-checks its sum directly.
+struct SumTreeChip {
+    config: SumTreeConfig,
+}
+
+impl SumTreeChip {
+    fn construct(config: SumTreeConfig) -> Self {
+        SumTreeChip { config }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> SumTreeConfig {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let advice = [(); 10].map(|_| meta.advice_column());
+        for column in advice {
+            meta.enable_equality(column);
+        }
+        let s_level = meta.selector();
+
+        meta.create_gate("sum tree level", |meta| {
+            let s_level = meta.query_selector(s_level);
+            let [node_hash, node_sum, sibling_hash, sibling_sum, direction, left_hash, left_sum, right_hash, right_sum, parent_sum] =
+                advice.map(|column| meta.query_advice(column, Rotation::cur()));
+            let one = Expression::Constant(Fp::one());
+            let d = direction;
+
+            Constraints::with_selector(
+                s_level,
+                vec![
+                    ("direction", d.clone() * (one - d.clone())),
+                    (
+                        "left hash",
+                        left_hash - node_hash.clone()
+                            - d.clone() * (sibling_hash.clone() - node_hash.clone()),
+                    ),
+                    (
+                        "right hash",
+                        right_hash - sibling_hash.clone()
+                            - d.clone() * (node_hash - sibling_hash),
+                    ),
+                    (
+                        "left sum",
+                        left_sum - node_sum.clone()
+                            - d.clone() * (sibling_sum.clone() - node_sum.clone()),
+                    ),
+                    (
+                        "right sum",
+                        right_sum - sibling_sum.clone() - d * (node_sum.clone() - sibling_sum.clone()),
+                    ),
+                    ("parent sum", parent_sum - node_sum - sibling_sum),
+                ],
+            )
+        });
+
+        // Also enables the constant column the range check needs.
+        let poseidon = PoseidonChip::configure(meta);
+        let range = RangeCheckChip::configure(meta);
+
+        SumTreeConfig {
+            instance,
+            advice,
+            s_level,
+            poseidon,
+            range,
+        }
+    }
+
+    fn load_instance(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        row: usize,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        layouter.assign_region(
+            || "load instance",
+            |mut region| {
+                region.assign_advice_from_instance(
+                    || "value",
+                    self.config.instance,
+                    row,
+                    self.config.advice[0],
+                    0,
+                )
+            },
+        )
+    }
+
+    fn leaf(
+        &self,
+        layouter: impl Layouter<Fp>,
+        id_hash: AssignedCell<Fp, Fp>,
+        balance: AssignedCell<Fp, Fp>,
+    ) -> Result<AssignedNode, Error> {
+        let poseidon = PoseidonChip::construct(self.config.poseidon.clone());
+        let hash = poseidon.hash(layouter, &[id_hash, balance.clone()])?;
+        Ok(AssignedNode { hash, sum: balance })
+    }
+
+    /// Hashes `leaf` up to the root along a private path, range-checking
+    /// the leaf sum, every sibling sum and every parent sum.
+    fn compute_root(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        leaf: &AssignedNode,
+        siblings: &[Value<SumNode>],
+        directions: &[Value<bool>],
+    ) -> Result<AssignedNode, Error> {
+        assert_eq!(siblings.len(), directions.len());
+        let config = &self.config;
+        let poseidon = PoseidonChip::construct(config.poseidon.clone());
+
+        let mut sums = vec![leaf.sum.clone()];
+        let mut node = leaf.clone();
+        for (i, (sibling, direction)) in siblings.iter().zip(directions).enumerate() {
+            let mut layouter = layouter.namespace(|| format!("level {}", i));
+            let (pair, sibling_sum, parent_sum) = layouter.assign_region(
+                || "sum tree level",
+                |mut region| {
+                    config.s_level.enable(&mut region, 0)?;
+                    let [node_hash, node_sum, sibling_hash, sibling_sum, direction_col, left_hash, left_sum, right_hash, right_sum, parent_sum] =
+                        config.advice;
+                    let hash = node.hash.copy_advice(|| "node hash", &mut region, node_hash, 0)?;
+                    let sum = node.sum.copy_advice(|| "node sum", &mut region, node_sum, 0)?;
+                    let sib_hash = sibling.map(|s| s.hash);
+                    let sib_sum = sibling.map(|s| s.sum);
+                    region.assign_advice(|| "sibling hash", sibling_hash, 0, || sib_hash)?;
+                    let sibling_sum_cell =
+                        region.assign_advice(|| "sibling sum", sibling_sum, 0, || sib_sum)?;
+                    region.assign_advice(
+                        || "direction",
+                        direction_col,
+                        0,
+                        || direction.map(Fp::from),
+                    )?;
+
+                    let ordered = |own: Value<Fp>, other: Value<Fp>| {
+                        own.zip(other)
+                            .zip(*direction)
+                            .map(|((own, other), right)| {
+                                if right {
+                                    (other, own)
+                                } else {
+                                    (own, other)
+                                }
+                            })
+                            .unzip()
+                    };
+                    let (lh, rh) = ordered(hash.value().copied(), sib_hash);
+                    let (ls, rs) = ordered(sum.value().copied(), sib_sum);
+                    let pair = [
+                        region.assign_advice(|| "left hash", left_hash, 0, || lh)?,
+                        region.assign_advice(|| "left sum", left_sum, 0, || ls)?,
+                        region.assign_advice(|| "right hash", right_hash, 0, || rh)?,
+                        region.assign_advice(|| "right sum", right_sum, 0, || rs)?,
+                    ];
+                    let parent = region.assign_advice(
+                        || "parent sum",
+                        parent_sum,
+                        0,
+                        || sum.value().copied() + sib_sum,
+                    )?;
+                    Ok((pair, sibling_sum_cell, parent))
+                },
+            )?;
+            let hash = poseidon.hash(layouter.namespace(|| "hash pair"), &pair)?;
+            sums.push(sibling_sum);
+            sums.push(parent_sum.clone());
+            node = AssignedNode {
+                hash,
+                sum: parent_sum,
+            };
+        }
+
+        let range = RangeCheckChip::construct(config.range.clone());
+        range.range_check(layouter.namespace(|| "sums"), &sums, SUM_BITS)?;
+        Ok(node)
+    }
+}
+
+/// Proves that the public leaf `(id_hash, balance)` is included in the
+/// Merkle sum tree with the public root hash and total. The instance column
+/// holds `[root hash, total, id_hash, balance]`: the user checks the last
+/// two against their own account, anyone the first two against the
+/// published root.
+struct ReservesCircuit {
+    siblings: [Value<SumNode>; DEPTH],
+    directions: [Value<bool>; DEPTH],
+}
+
+impl ReservesCircuit {
+    fn new(tree: &SumTree, index: usize) -> Self {
+        let (siblings, directions) = tree.path(index);
+        let siblings: [SumNode; DEPTH] = siblings.try_into().unwrap();
+        let directions: [bool; DEPTH] = directions.try_into().unwrap();
+        ReservesCircuit {
+            siblings: siblings.map(Value::known),
+            directions: directions.map(Value::known),
+        }
+    }
+}
+
+impl Circuit<Fp> for ReservesCircuit {
+    type Config = SumTreeConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        ReservesCircuit {
+            siblings: [Value::unknown(); DEPTH],
+            directions: [Value::unknown(); DEPTH],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        SumTreeChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = SumTreeChip::construct(config.clone());
+        let range = RangeCheckChip::construct(config.range.clone());
+        range.load_table(layouter.namespace(|| "range table"))?;
+
+        let id_hash = chip.load_instance(layouter.namespace(|| "id hash"), 2)?;
+        let balance = chip.load_instance(layouter.namespace(|| "balance"), 3)?;
+        let leaf = chip.leaf(layouter.namespace(|| "leaf"), id_hash, balance)?;
+        let root = chip.compute_root(
+            layouter.namespace(|| "root"),
+            &leaf,
+            &self.siblings,
+            &self.directions,
+        )?;
+        layouter.constrain_instance(root.hash.cell(), config.instance, 0)?;
+        layouter.constrain_instance(root.sum.cell(), config.instance, 1)
+    }
+}
+
+fn verify(
+    circuit: &ReservesCircuit,
+    root: SumNode,
+    id_hash: Fp,
+    balance: Fp,
+) -> Result<(), Vec<halo2_proofs::dev::VerifyFailure>> {
+    let instance = vec![root.hash, root.sum, id_hash, balance];
+    MockProver::run(K, circuit, vec![instance])
+        .unwrap()
+        .verify()
+}
+
+fn test_reserves() {
+    // Account ids are hashed off-circuit, so the tree reveals none.
+    let accounts: Vec<(Fp, u64)> = [
+        ("alice", 1_500),
+        ("bob", 0),
+        ("carol", 42),
+        ("dave", 1 << 40),
+        ("erin", 7),
+        ("frank", 99_999),
+        ("grace", 3),
+        ("heidi", 250_000),
+        ("ivan", 1),
+        ("judy", 31_337),
+        ("mallory", 12),
+    ]
+    .iter()
+    .map(|(name, balance)| {
+        let bytes: Vec<Fp> = name.bytes().map(|b| Fp::from(b as u64)).collect();
+        (poseidon::hash_slice(&bytes), *balance)
+    })
+    .collect();
+    let leaves = accounts
+        .iter()
+        .map(|(id_hash, balance)| SumNode::leaf(*id_hash, Fp::from(*balance)))
+        .collect();
+    let tree = SumTree::new(DEPTH, leaves);
+    let root = tree.root();
+    let total: u64 = accounts.iter().map(|(_, balance)| balance).sum();
+    assert_eq!(root.sum, Fp::from(total));
+
+    for index in [0, 1, 3, 10] {
+        let (id_hash, balance) = accounts[index];
+        let circuit = ReservesCircuit::new(&tree, index);
+        assert_eq!(verify(&circuit, root, id_hash, Fp::from(balance)), Ok(()));
+    }
+
+    let (id_hash, balance) = accounts[3];
+    let balance = Fp::from(balance);
+    let circuit = ReservesCircuit::new(&tree, 3);
+
+    // A different total, balance, account or root.
+    let understated = SumNode {
+        sum: root.sum - Fp::one(),
+        ..root
+    };
+    assert!(verify(&circuit, understated, id_hash, balance).is_err());
+    assert!(verify(&circuit, root, id_hash, balance + Fp::one()).is_err());
+    assert!(verify(&circuit, root, accounts[4].0, balance).is_err());
+    let other_root = SumNode {
+        hash: root.hash + Fp::one(),
+        ..root
+    };
+    assert!(verify(&circuit, other_root, id_hash, balance).is_err());
+
+    // A wrong sibling sum, or a flipped direction.
+    let mut wrong_sum = ReservesCircuit::new(&tree, 3);
+    wrong_sum.siblings[1] = wrong_sum.siblings[1].map(|s| SumNode {
+        sum: s.sum + Fp::one(),
+        ..s
+    });
+    assert!(verify(&wrong_sum, root, id_hash, balance).is_err());
+    let mut flipped = ReservesCircuit::new(&tree, 3);
+    flipped.directions[2] = flipped.directions[2].map(|d| !d);
+    assert!(verify(&flipped, root, id_hash, balance).is_err());
+
+    // A negative balance hides liabilities: the tree is consistent and its
+    // total small, but the sum of the fake account is out of range.
+    let fake = SumNode::leaf(Fp::from(666), -Fp::from(1 << 40));
+    let mut leaves: Vec<SumNode> = tree.levels[0].clone();
+    leaves[11] = fake;
+    let forged = SumTree::new(DEPTH, leaves);
+    assert_eq!(forged.root().sum, Fp::from(total - (1 << 40)));
+    let circuit = ReservesCircuit::new(&forged, 3);
+    assert!(verify(&circuit, forged.root(), id_hash, balance).is_err());
+
+    // A small negative balance only shows up on the paths through it: dave's
+    // proof checks the sum of leaves 8 to 15, still positive, while mallory's
+    // checks leaf 11 itself.
+    let fake = SumNode::leaf(Fp::from(666), -Fp::from(10_000));
+    let mut leaves: Vec<SumNode> = tree.levels[0].clone();
+    leaves[11] = fake;
+    let forged = SumTree::new(DEPTH, leaves);
+    let circuit = ReservesCircuit::new(&forged, 3);
+    assert_eq!(verify(&circuit, forged.root(), id_hash, balance), Ok(()));
+    let (id_hash, balance) = accounts[10];
+    let circuit = ReservesCircuit::new(&forged, 10);
+    assert!(verify(&circuit, forged.root(), id_hash, Fp::from(balance)).is_err());
+
+    // Balances that each fit but whose sum does not.
+    let big = [(Fp::from(1), 1 << 63), (Fp::from(2), 1 << 63)];
+    let leaves = big
+        .iter()
+        .map(|(id_hash, balance)| SumNode::leaf(*id_hash, Fp::from(*balance)))
+        .collect();
+    let overflowing = SumTree::new(DEPTH, leaves);
+    let circuit = ReservesCircuit::new(&overflowing, 0);
+    assert!(verify(&circuit, overflowing.root(), big[0].0, Fp::from(big[0].1)).is_err());
+}
+
+fn main() {
+    test_reserves();
+}
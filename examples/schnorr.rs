@@ -0,0 +1,247 @@
+use halo2_learning::{
+    bits::{le_bits, BitsChip, BitsConfig},
+    ecc::{coordinates, AssignedPoint, EccChip, EccConfig},
+    poseidon::{PoseidonChip, PoseidonConfig},
+    schnorr::{self, Signature, CHALLENGE_BITS, TEST_VECTORS},
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::{
+        group::{ff::PrimeField, prime::PrimeCurveAffine, GroupEncoding},
+        pallas, Fp, Fq,
+    },
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Instance},
+};
+
+#[derive(Clone, Debug)]
+struct SchnorrConfig {
+    instance: Column<Instance>,
+    advice: Column<Advice>,
+    ecc: EccConfig,
+    bits: BitsConfig,
+    poseidon: PoseidonConfig<Fp>,
+}
+
+struct SchnorrChip {
+    config: SchnorrConfig,
+}
+
+impl SchnorrChip {
+    fn construct(config: SchnorrConfig) -> Self {
+        SchnorrChip { config }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> SchnorrConfig {
+        let instance = meta.instance_column();
+        let advice = meta.advice_column();
+        meta.enable_equality(instance);
+        meta.enable_equality(advice);
+        let ecc = EccChip::configure(meta);
+        let bits = BitsChip::configure(meta);
+        // Also enables the constant column the other chips need.
+        let poseidon = PoseidonChip::configure(meta);
+
+        SchnorrConfig {
+            instance,
+            advice,
+            ecc,
+            bits,
+            poseidon,
+        }
+    }
+
+    fn load_message<const L: usize>(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        row: usize,
+    ) -> Result<[AssignedCell<Fp, Fp>; L], Error> {
+        let config = &self.config;
+        let cells = layouter.assign_region(
+            || "load message",
+            |mut region| {
+                (0..L)
+                    .map(|i| {
+                        region.assign_advice_from_instance(
+                            || "message",
+                            config.instance,
+                            row + i,
+                            config.advice,
+                            i,
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            },
+        )?;
+        Ok(cells.try_into().unwrap())
+    }
+
+    /// Constrains `s * G = R + e * pk` with `e` the challenge of `R`, `pk`
+    /// and `Poseidon(msg)`.
+    fn verify<const L: usize>(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        pubkey: &AssignedPoint,
+        msg: &[AssignedCell<Fp, Fp>; L],
+        signature: Value<Signature>,
+    ) -> Result<(), Error> {
+        let ecc = EccChip::construct(self.config.ecc.clone());
+        let bits = BitsChip::construct(self.config.bits.clone());
+        let poseidon = PoseidonChip::construct(self.config.poseidon.clone());
+
+        let r = ecc.load_private(layouter.namespace(|| "R"), signature.map(|sig| sig.r))?;
+        ecc.assert_on_curve(layouter.namespace(|| "R on curve"), &r)?;
+        ecc.assert_on_curve(layouter.namespace(|| "pk on curve"), pubkey)?;
+
+        let m = poseidon.hash(layouter.namespace(|| "message hash"), msg)?;
+        let e = poseidon.hash(
+            layouter.namespace(|| "challenge"),
+            &[
+                r.x.clone(),
+                r.y.clone(),
+                pubkey.x.clone(),
+                pubkey.y.clone(),
+                m,
+            ],
+        )?;
+        let e_bits = bits.decompose(layouter.namespace(|| "e bits"), &e, CHALLENGE_BITS)?;
+        let s_bits = bits.assign_bits(
+            layouter.namespace(|| "s bits"),
+            signature.map(|sig| le_bits(&sig.s)),
+            Fq::NUM_BITS as usize,
+        )?;
+
+        let lhs = ecc.fixed_base_mul(
+            layouter.namespace(|| "s * G"),
+            &s_bits,
+            &pallas::Affine::generator(),
+        )?;
+        let e_pk = ecc.variable_base_mul(layouter.namespace(|| "e * pk"), &e_bits, pubkey)?;
+        let rhs = ecc.add(layouter.namespace(|| "R + e * pk"), &r, &e_pk)?;
+        ecc.assert_equal(layouter.namespace(|| "s * G = R + e * pk"), &lhs, &rhs)
+    }
+}
+
+/// Verifies a private signature on a public message under a public key.
+/// Instance: `[pk.x, pk.y, msg_0, .., msg_{L-1}]`.
+#[derive(Default)]
+struct SchnorrCircuit<const L: usize> {
+    signature: Value<Signature>,
+}
+
+impl<const L: usize> Circuit<Fp> for SchnorrCircuit<L> {
+    type Config = SchnorrConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        SchnorrChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = SchnorrChip::construct(config.clone());
+        let ecc = EccChip::construct(config.ecc);
+        let pubkey = ecc.load_instance(layouter.namespace(|| "pk"), config.instance, 0)?;
+        let msg = chip.load_message::<L>(layouter.namespace(|| "msg"), 2)?;
+        chip.verify(
+            layouter.namespace(|| "verify"),
+            &pubkey,
+            &msg,
+            self.signature,
+        )
+    }
+}
+
+fn instance<const L: usize>(pubkey: &pallas::Affine, msg: [Fp; L]) -> Vec<Vec<Fp>> {
+    let (x, y) = coordinates(pubkey);
+    let mut instance = vec![x, y];
+    instance.extend(msg);
+    vec![instance]
+}
+
+fn test_vectors() {
+    for vector in &TEST_VECTORS {
+        let (sk, pubkey) = schnorr::keygen(vector.ikm);
+        assert_eq!(schnorr::to_hex(&pubkey.to_bytes()), vector.pubkey);
+
+        let msg = vector.msg.map(Fp::from);
+        let signature = schnorr::sign(&sk, msg);
+        assert!(schnorr::verify(&pubkey, msg, &signature));
+        println!(
+            "ikm {:?}: R = {}, s = {}",
+            String::from_utf8_lossy(vector.ikm),
+            schnorr::to_hex(&signature.r.to_bytes()),
+            schnorr::to_hex(&signature.s.to_repr()),
+        );
+    }
+}
+
+fn test_verify() {
+    let k = 12;
+    let (sk, pubkey) = schnorr::keygen(TEST_VECTORS[0].ikm);
+    let msg = TEST_VECTORS[0].msg.map(Fp::from);
+    let signature = schnorr::sign(&sk, msg);
+
+    let circuit = SchnorrCircuit::<2> {
+        signature: Value::known(signature),
+    };
+    let prover = MockProver::run(k, &circuit, instance(&pubkey, msg)).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // Another message.
+    let other_msg = [msg[0], msg[1] + Fp::one()];
+    assert!(!schnorr::verify(&pubkey, other_msg, &signature));
+    let prover = MockProver::run(k, &circuit, instance(&pubkey, other_msg)).unwrap();
+    assert!(prover.verify().is_err());
+
+    // Another public key.
+    let (_, other_pubkey) = schnorr::keygen(TEST_VECTORS[1].ikm);
+    let prover = MockProver::run(k, &circuit, instance(&other_pubkey, msg)).unwrap();
+    assert!(prover.verify().is_err());
+
+    // A tampered s.
+    let tampered = Signature {
+        r: signature.r,
+        s: signature.s + Fq::one(),
+    };
+    assert!(!schnorr::verify(&pubkey, msg, &tampered));
+    let circuit = SchnorrCircuit::<2> {
+        signature: Value::known(tampered),
+    };
+    let prover = MockProver::run(k, &circuit, instance(&pubkey, msg)).unwrap();
+    assert!(prover.verify().is_err());
+
+    // The same message signed by another key.
+    let (other_sk, _) = schnorr::keygen(TEST_VECTORS[1].ikm);
+    let circuit = SchnorrCircuit::<2> {
+        signature: Value::known(schnorr::sign(&other_sk, msg)),
+    };
+    let prover = MockProver::run(k, &circuit, instance(&pubkey, msg)).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+fn test_long_message() {
+    let k = 12;
+    let (sk, pubkey) = schnorr::keygen(TEST_VECTORS[2].ikm);
+    let msg: [Fp; 5] = [1, 1, 2, 3, 5].map(Fp::from);
+    let signature = schnorr::sign(&sk, msg);
+
+    let circuit = SchnorrCircuit::<5> {
+        signature: Value::known(signature),
+    };
+    let prover = MockProver::run(k, &circuit, instance(&pubkey, msg)).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+}
+
+fn main() {
+    test_vectors();
+    test_verify();
+    test_long_message();
+}
@@ -0,0 +1,110 @@
+use halo2_learning::shuffle::{ShuffleChip, ShuffleConfig};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::Fp,
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error},
+};
+
+#[derive(Clone, Debug)]
+struct ListsConfig {
+    advice: Column<Advice>,
+    shuffle: ShuffleConfig<Fp>,
+}
+
+/// Proves that two private lists, of a length fixed at keygen, hold the
+/// same values with the same multiplicities.
+#[derive(Default)]
+struct ShuffleCircuit {
+    a: Vec<Value<u64>>,
+    b: Vec<Value<u64>>,
+}
+
+impl ShuffleCircuit {
+    fn new(a: &[u64], b: &[u64]) -> Self {
+        let known = |list: &[u64]| list.iter().map(|x| Value::known(*x)).collect();
+        ShuffleCircuit {
+            a: known(a),
+            b: known(b),
+        }
+    }
+}
+
+fn load_list(
+    mut layouter: impl Layouter<Fp>,
+    column: Column<Advice>,
+    list: &[Value<u64>],
+) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+    layouter.assign_region(
+        || "list",
+        |mut region| {
+            list.iter()
+                .enumerate()
+                .map(|(row, x)| region.assign_advice(|| "x", column, row, || x.map(Fp::from)))
+                .collect()
+        },
+    )
+}
+
+impl Circuit<Fp> for ShuffleCircuit {
+    type Config = ListsConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        ShuffleCircuit {
+            a: vec![Value::unknown(); self.a.len()],
+            b: vec![Value::unknown(); self.b.len()],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let advice = meta.advice_column();
+        meta.enable_equality(advice);
+        let shuffle = ShuffleChip::configure(meta);
+        ListsConfig { advice, shuffle }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let a = load_list(layouter.namespace(|| "a"), config.advice, &self.a)?;
+        let b = load_list(layouter.namespace(|| "b"), config.advice, &self.b)?;
+        let chip = ShuffleChip::construct(config.shuffle);
+        chip.assert_shuffle(layouter.namespace(|| "shuffle"), &a, &b)
+    }
+}
+
+fn verify(a: &[u64], b: &[u64]) -> Result<(), Vec<halo2_proofs::dev::VerifyFailure>> {
+    MockProver::run(11, &ShuffleCircuit::new(a, b), vec![])
+        .unwrap()
+        .verify()
+}
+
+fn test_shuffle() {
+    let a = [3, 1, 4, 1, 5, 9, 2, 6, 5, 3];
+
+    // Equal multisets.
+    assert_eq!(verify(&a, &a), Ok(()));
+    assert_eq!(verify(&a, &[1, 1, 2, 3, 3, 4, 5, 5, 6, 9]), Ok(()));
+    assert_eq!(verify(&a, &[9, 6, 5, 5, 4, 3, 3, 2, 1, 1]), Ok(()));
+    assert_eq!(verify(&[], &[]), Ok(()));
+
+    // A single value swapped for another.
+    assert!(verify(&a, &[3, 1, 4, 1, 5, 9, 2, 6, 5, 4]).is_err());
+    assert!(verify(&a, &[1, 1, 2, 3, 3, 4, 5, 5, 6, 0]).is_err());
+
+    // Duplicated entries must match in number.
+    assert!(verify(&a, &[3, 1, 4, 1, 5, 9, 2, 6, 5, 5]).is_err());
+    assert!(verify(&[1, 1, 2], &[1, 2, 2]).is_err());
+    assert!(verify(&[7, 7, 7], &[7, 7, 8]).is_err());
+    assert_eq!(verify(&[7, 7, 7], &[7, 7, 7]), Ok(()));
+
+    // Same sum and product as 1..9, but not a permutation of it.
+    assert!(verify(&[1, 2, 3, 4, 5, 6, 7, 8, 9], &[1, 2, 4, 4, 4, 5, 7, 9, 9]).is_err());
+}
+
+fn main() {
+    test_shuffle();
+}
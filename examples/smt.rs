@@ -0,0 +1,252 @@
+use halo2_learning::smt::{SmtChip, SmtConfig, SparseMerkleTree, DEPTH};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::{group::ff::Field, Fp},
+    plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
+};
+
+#[derive(Clone, Debug)]
+struct SmtCircuitConfig {
+    smt: SmtConfig<Fp>,
+    instance: Column<Instance>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Claim {
+    /// Public `[root, key, value]`.
+    Inclusion,
+    /// Public `[root, key]`.
+    Exclusion,
+}
+
+/// Proves that the tree with the public root maps the public key to the
+/// public value, or holds nothing at that key.
+struct LookupCircuit {
+    claim: Claim,
+    siblings: Vec<Value<Fp>>,
+}
+
+impl LookupCircuit {
+    fn new(claim: Claim, tree: &SparseMerkleTree<Fp>, key: Fp) -> Self {
+        LookupCircuit {
+            claim,
+            siblings: tree.siblings(key).into_iter().map(Value::known).collect(),
+        }
+    }
+}
+
+impl Circuit<Fp> for LookupCircuit {
+    type Config = SmtCircuitConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        LookupCircuit {
+            claim: self.claim,
+            siblings: vec![Value::unknown(); DEPTH],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let smt = SmtChip::configure(meta);
+
+        SmtCircuitConfig { smt, instance }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = SmtChip::construct(config.smt);
+        let key = chip.load_instance(layouter.namespace(|| "key"), config.instance, 1)?;
+        let root = match self.claim {
+            Claim::Inclusion => {
+                let value =
+                    chip.load_instance(layouter.namespace(|| "value"), config.instance, 2)?;
+                chip.inclusion_root(
+                    layouter.namespace(|| "inclusion"),
+                    &key,
+                    &value,
+                    &self.siblings,
+                )?
+            }
+            Claim::Exclusion => {
+                chip.exclusion_root(layouter.namespace(|| "exclusion"), &key, &self.siblings)?
+            }
+        };
+        layouter.constrain_instance(root.cell(), config.instance, 0)
+    }
+}
+
+/// Proves that setting the public key to the public value turns the tree
+/// with `old_root` into the one with `new_root`. Public
+/// `[old_root, new_root, key, new_value]`.
+struct UpdateCircuit {
+    old_leaf: Value<Fp>,
+    siblings: Vec<Value<Fp>>,
+}
+
+impl UpdateCircuit {
+    fn new(tree: &SparseMerkleTree<Fp>, key: Fp) -> Self {
+        UpdateCircuit {
+            old_leaf: Value::known(tree.leaf(key)),
+            siblings: tree.siblings(key).into_iter().map(Value::known).collect(),
+        }
+    }
+}
+
+impl Circuit<Fp> for UpdateCircuit {
+    type Config = SmtCircuitConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        UpdateCircuit {
+            old_leaf: Value::unknown(),
+            siblings: vec![Value::unknown(); DEPTH],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        LookupCircuit::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = SmtChip::construct(config.smt);
+        let key = chip.load_instance(layouter.namespace(|| "key"), config.instance, 2)?;
+        let new_value =
+            chip.load_instance(layouter.namespace(|| "new value"), config.instance, 3)?;
+        let [old_root, new_root] = chip.update(
+            layouter.namespace(|| "update"),
+            &key,
+            self.old_leaf,
+            &new_value,
+            &self.siblings,
+        )?;
+        layouter.constrain_instance(old_root.cell(), config.instance, 0)?;
+        layouter.constrain_instance(new_root.cell(), config.instance, 1)
+    }
+}
+
+fn sample_tree() -> (SparseMerkleTree<Fp>, Vec<(Fp, Fp)>) {
+    // Neighbouring keys share all but their lowest bit, the last one uses
+    // the top bit.
+    let entries = vec![
+        (Fp::from(4), Fp::from(40)),
+        (Fp::from(5), Fp::from(50)),
+        (Fp::from(0x1234_5678), Fp::zero()),
+        (Fp::from(2).pow_vartime([253]) + Fp::from(9), Fp::from(90)),
+    ];
+    let mut tree = SparseMerkleTree::new();
+    for (key, value) in &entries {
+        tree.insert(*key, *value);
+    }
+    (tree, entries)
+}
+
+fn test_lookup() {
+    let k = 15;
+    let (tree, entries) = sample_tree();
+    let root = tree.root();
+
+    for (key, value) in &entries {
+        let circuit = LookupCircuit::new(Claim::Inclusion, &tree, *key);
+        let prover = MockProver::run(k, &circuit, vec![vec![root, *key, *value]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    // A present key with the wrong value.
+    let (key, value) = entries[0];
+    let circuit = LookupCircuit::new(Claim::Inclusion, &tree, key);
+    let prover = MockProver::run(k, &circuit, vec![vec![root, key, value + Fp::one()]]).unwrap();
+    assert!(prover.verify().is_err());
+
+    // Absent keys, including a neighbour of a present one.
+    for key in [Fp::from(6), Fp::from(7), Fp::from(2).pow_vartime([200])] {
+        let circuit = LookupCircuit::new(Claim::Exclusion, &tree, key);
+        let prover = MockProver::run(k, &circuit, vec![vec![root, key]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let circuit = LookupCircuit::new(Claim::Inclusion, &tree, key);
+        let prover = MockProver::run(k, &circuit, vec![vec![root, key, Fp::zero()]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    // A present key, even one mapped to zero, cannot be shown absent.
+    for (key, _) in &entries {
+        let circuit = LookupCircuit::new(Claim::Exclusion, &tree, *key);
+        let prover = MockProver::run(k, &circuit, vec![vec![root, *key]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    // -1 has the same low 254 bits as -1 - 2^254, but is out of range.
+    let alias = -Fp::one() - Fp::from(2).pow_vartime([254]);
+    let circuit = LookupCircuit::new(Claim::Exclusion, &tree, alias);
+    let prover = MockProver::run(k, &circuit, vec![vec![root, alias]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    let prover = MockProver::run(k, &circuit, vec![vec![root, -Fp::one()]]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+fn test_update() {
+    let k = 16;
+    let (mut tree, entries) = sample_tree();
+
+    // Insert a fresh key, then overwrite an existing one.
+    for (key, value) in [(Fp::from(6), Fp::from(60)), (entries[1].0, Fp::from(51))] {
+        let old_root = tree.root();
+        let circuit = UpdateCircuit::new(&tree, key);
+        tree.insert(key, value);
+        let new_root = tree.root();
+
+        let prover =
+            MockProver::run(k, &circuit, vec![vec![old_root, new_root, key, value]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // A different new value.
+        let prover = MockProver::run(
+            k,
+            &circuit,
+            vec![vec![old_root, new_root, key, value + Fp::one()]],
+        )
+        .unwrap();
+        assert!(prover.verify().is_err());
+
+        // A different key with the same path witnesses.
+        let prover = MockProver::run(
+            k,
+            &circuit,
+            vec![vec![old_root, new_root, key + Fp::one(), value]],
+        )
+        .unwrap();
+        assert!(prover.verify().is_err());
+
+        // Roots swapped: the new tree does not become the old one.
+        let prover =
+            MockProver::run(k, &circuit, vec![vec![new_root, old_root, key, value]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    // Removing a key back to an empty leaf is not an update.
+    let old_root = tree.root();
+    let circuit = UpdateCircuit::new(&tree, Fp::from(6));
+    tree.remove(Fp::from(6));
+    let prover = MockProver::run(
+        k,
+        &circuit,
+        vec![vec![old_root, tree.root(), Fp::from(6), Fp::zero()]],
+    )
+    .unwrap();
+    assert!(prover.verify().is_err());
+}
+
+fn main() {
+    test_lookup();
+    test_update();
+}
@@ -0,0 +1,749 @@
+use halo2_learning::poseidon::{self, PoseidonChip, PoseidonConfig};
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::Fp,
+    plonk::{
+        Advice, Circuit, Column, ConstraintSystem, Constraints, Error, Expression, Instance,
+        Selector, TableColumn,
+    },
+    poly::Rotation,
+};
+
+/// Stack slots carried on every row.
+const STACK_DEPTH: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Instruction {
+    Push(Fp),
+    Add,
+    Mul,
+    Dup,
+    Swap,
+    /// Pops the top and jumps to the target if it was zero.
+    Jz(usize),
+    /// Stays on the same instruction forever, which pads the trace.
+    Halt,
+}
+
+/// Opcode order, which is also the order of the flag columns.
+const OPCODES: usize = 7;
+
+impl Instruction {
+    /// Opcodes start at 1, so that the all-zero row of the program table
+    /// matches no instruction.
+    fn opcode(&self) -> u64 {
+        match self {
+            Instruction::Push(_) => 1,
+            Instruction::Add => 2,
+            Instruction::Mul => 3,
+            Instruction::Dup => 4,
+            Instruction::Swap => 5,
+            Instruction::Jz(_) => 6,
+            Instruction::Halt => 7,
+        }
+    }
+
+    fn arg(&self) -> Fp {
+        match self {
+            Instruction::Push(value) => *value,
+            Instruction::Jz(target) => Fp::from(*target as u64),
+            _ => Fp::zero(),
+        }
+    }
+
+    /// Stack items read.
+    fn reads(&self) -> usize {
+        match self {
+            Instruction::Push(_) => 0,
+            Instruction::Dup | Instruction::Jz(_) | Instruction::Halt => 1,
+            Instruction::Add | Instruction::Mul | Instruction::Swap => 2,
+        }
+    }
+
+    /// Whether the stack grows by one.
+    fn grows(&self) -> bool {
+        matches!(self, Instruction::Push(_) | Instruction::Dup)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum VmError {
+    StackUnderflow {
+        pc: usize,
+    },
+    StackOverflow {
+        pc: usize,
+    },
+    PcOutOfRange {
+        pc: usize,
+    },
+    /// The program had not halted after the given number of steps.
+    StepLimit,
+}
+
+/// The state before executing `instruction`. The top of `stack` is last.
+#[derive(Clone, Debug)]
+struct Step {
+    pc: usize,
+    instruction: Instruction,
+    stack: Vec<Fp>,
+}
+
+/// Runs `program` on a stack holding `inputs`, top last, and returns
+/// exactly `steps` rows: the execution, then copies of the halting row.
+fn run(program: &[Instruction], inputs: &[Fp], steps: usize) -> Result<Vec<Step>, VmError> {
+    run_from(program, 0, inputs.to_vec(), steps)
+}
+
+/// Like `run`, from an arbitrary state.
+fn run_from(
+    program: &[Instruction],
+    mut pc: usize,
+    mut stack: Vec<Fp>,
+    steps: usize,
+) -> Result<Vec<Step>, VmError> {
+    let mut trace = Vec::with_capacity(steps);
+    if stack.len() > STACK_DEPTH {
+        return Err(VmError::StackOverflow { pc });
+    }
+
+    while trace.len() < steps {
+        let instruction = *program.get(pc).ok_or(VmError::PcOutOfRange { pc })?;
+        if stack.len() < instruction.reads() {
+            return Err(VmError::StackUnderflow { pc });
+        }
+        if instruction.grows() && stack.len() == STACK_DEPTH {
+            return Err(VmError::StackOverflow { pc });
+        }
+        trace.push(Step {
+            pc,
+            instruction,
+            stack: stack.clone(),
+        });
+
+        pc = match instruction {
+            Instruction::Push(value) => {
+                stack.push(value);
+                pc + 1
+            }
+            Instruction::Add | Instruction::Mul => {
+                let a = stack.pop().unwrap();
+                let b = stack.pop().unwrap();
+                stack.push(if instruction == Instruction::Add {
+                    a + b
+                } else {
+                    a * b
+                });
+                pc + 1
+            }
+            Instruction::Dup => {
+                stack.push(*stack.last().unwrap());
+                pc + 1
+            }
+            Instruction::Swap => {
+                let len = stack.len();
+                stack.swap(len - 1, len - 2);
+                pc + 1
+            }
+            Instruction::Jz(target) => {
+                if stack.pop().unwrap().is_zero_vartime() {
+                    target
+                } else {
+                    pc + 1
+                }
+            }
+            Instruction::Halt => pc,
+        };
+    }
+
+    match trace.last() {
+        Some(step) if step.instruction == Instruction::Halt => Ok(trace),
+        _ => Err(VmError::StepLimit),
+    }
+}
+
+/// `h_0 = 0, h_{i+1} = Poseidon([h_i, opcode_i, arg_i])`.
+fn program_hash(program: &[Instruction]) -> Fp {
+    program.iter().fold(Fp::zero(), |h, instruction| {
+        poseidon::hash([h, Fp::from(instruction.opcode()), instruction.arg()])
+    })
+}
+
+#[derive(Clone, Debug)]
+struct StackVmConfig {
+    instance: Column<Instance>,
+    pc: Column<Advice>,
+    arg: Column<Advice>,
+    sp: Column<Advice>,
+    inv: Column<Advice>,
+    stack: [Column<Advice>; STACK_DEPTH],
+    flags: [Column<Advice>; OPCODES],
+    s_fetch: Selector,
+    s_step: Selector,
+    s_last: Selector,
+    table_pc: TableColumn,
+    table_opcode: TableColumn,
+    table_arg: TableColumn,
+    table_depth: TableColumn,
+    poseidon: PoseidonConfig<Fp>,
+}
+
+/// One row per step, as in `FiboChip`, but the row is the whole machine
+/// state:
+///
+/// pc arg sp inv stack[0..8] flags[0..7]
+///
+/// `stack[0]` is the top and `sp` the number of live slots; slots at `sp`
+/// and above are zero. The flags one-hot encode the opcode, and
+/// `(pc, opcode, arg)` is looked up in the program table, which is fixed at
+/// keygen. `sp` is checked against the depth table so that an instruction
+/// never reads below the bottom or pushes past `STACK_DEPTH`.
+///
+/// The transition gate relates each row to the next, every slot either
+/// carried over, shifted by one or replaced by the result of the
+/// instruction, so the stack stays consistent from step to step. `inv` is
+/// the inverse of the top, if any, for the zero test of `JZ`.
+struct StackVmChip {
+    config: StackVmConfig,
+}
+
+impl StackVmChip {
+    fn construct(config: StackVmConfig) -> Self {
+        StackVmChip { config }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> StackVmConfig {
+        let instance = meta.instance_column();
+        let pc = meta.advice_column();
+        let arg = meta.advice_column();
+        let sp = meta.advice_column();
+        let inv = meta.advice_column();
+        let stack = [(); STACK_DEPTH].map(|_| meta.advice_column());
+        let flags = [(); OPCODES].map(|_| meta.advice_column());
+        meta.enable_equality(instance);
+        for column in [pc, arg, sp].into_iter().chain(stack) {
+            meta.enable_equality(column);
+        }
+
+        let s_fetch = meta.complex_selector();
+        let s_step = meta.selector();
+        let s_last = meta.selector();
+        let table_pc = meta.lookup_table_column();
+        let table_opcode = meta.lookup_table_column();
+        let table_arg = meta.lookup_table_column();
+        let table_depth = meta.lookup_table_column();
+
+        let one = || Expression::Constant(Fp::one());
+        let constant = |value: u64| Expression::Constant(Fp::from(value));
+
+        meta.create_gate("decode", |meta| {
+            let s_fetch = meta.query_selector(s_fetch);
+            let flags = flags.map(|flag| meta.query_advice(flag, Rotation::cur()));
+            let [_, _, _, _, _, is_jz, _] = flags.clone();
+            let top = meta.query_advice(stack[0], Rotation::cur());
+            let inv = meta.query_advice(inv, Rotation::cur());
+
+            let mut constraints: Vec<_> = flags
+                .iter()
+                .map(|flag| flag.clone() * (one() - flag.clone()))
+                .collect();
+            let sum = flags
+                .iter()
+                .fold(Expression::Constant(Fp::zero()), |acc, flag| {
+                    acc + flag.clone()
+                });
+            constraints.push(sum - one());
+            // If the top is nonzero, `inv` has to be its inverse.
+            constraints.push(is_jz * top.clone() * (one() - top * inv));
+            Constraints::with_selector(s_fetch, constraints)
+        });
+
+        meta.lookup(|meta| {
+            let s_fetch = meta.query_selector(s_fetch);
+            let pc = meta.query_advice(pc, Rotation::cur());
+            let arg = meta.query_advice(arg, Rotation::cur());
+            let opcode = flags.iter().enumerate().fold(
+                Expression::Constant(Fp::zero()),
+                |acc, (i, flag)| {
+                    acc + constant(i as u64 + 1) * meta.query_advice(*flag, Rotation::cur())
+                },
+            );
+            vec![
+                (s_fetch.clone() * pc, table_pc),
+                (s_fetch.clone() * opcode, table_opcode),
+                (s_fetch * arg, table_arg),
+            ]
+        });
+
+        meta.lookup(|meta| {
+            let s_fetch = meta.query_selector(s_fetch);
+            let sp = meta.query_advice(sp, Rotation::cur());
+            let [_, is_add, is_mul, is_dup, is_swap, is_jz, is_halt] =
+                flags.map(|flag| meta.query_advice(flag, Rotation::cur()));
+            let reads = constant(2) * (is_add + is_mul + is_swap) + is_dup + is_jz + is_halt;
+            vec![(s_fetch * (sp - reads), table_depth)]
+        });
+
+        meta.lookup(|meta| {
+            let s_fetch = meta.query_selector(s_fetch);
+            let sp = meta.query_advice(sp, Rotation::cur());
+            let is_push = meta.query_advice(flags[0], Rotation::cur());
+            let is_dup = meta.query_advice(flags[3], Rotation::cur());
+            vec![(s_fetch * (sp + is_push + is_dup), table_depth)]
+        });
+
+        meta.create_gate("transition", |meta| {
+            let s_step = meta.query_selector(s_step);
+            let [is_push, is_add, is_mul, is_dup, is_swap, is_jz, is_halt] =
+                flags.map(|flag| meta.query_advice(flag, Rotation::cur()));
+            let pc_cur = meta.query_advice(pc, Rotation::cur());
+            let pc_next = meta.query_advice(pc, Rotation::next());
+            let arg = meta.query_advice(arg, Rotation::cur());
+            let sp_cur = meta.query_advice(sp, Rotation::cur());
+            let sp_next = meta.query_advice(sp, Rotation::next());
+            let inv = meta.query_advice(inv, Rotation::cur());
+            let cur = stack.map(|slot| meta.query_advice(slot, Rotation::cur()));
+            let next = stack.map(|slot| meta.query_advice(slot, Rotation::next()));
+            let zero = || Expression::Constant(Fp::zero());
+            // Slot `j` of the current row, zero past the bottom.
+            let slot = |j: usize| cur.get(j).cloned().unwrap_or_else(zero);
+
+            let is_zero = one() - cur[0].clone() * inv;
+            let advance = is_push.clone()
+                + is_add.clone()
+                + is_mul.clone()
+                + is_dup.clone()
+                + is_swap.clone();
+            let expected_pc = advance * (pc_cur.clone() + one())
+                + is_jz.clone()
+                    * (is_zero.clone() * arg.clone()
+                        + (one() - is_zero) * (pc_cur.clone() + one()))
+                + is_halt.clone() * pc_cur;
+
+            let expected_sp = sp_cur + is_push.clone() + is_dup.clone()
+                - is_add.clone()
+                - is_mul.clone()
+                - is_jz.clone();
+
+            let mut constraints = vec![pc_next - expected_pc, sp_next - expected_sp];
+            for (j, next) in next.into_iter().enumerate() {
+                let expected = match j {
+                    0 => {
+                        is_push.clone() * arg.clone()
+                            + is_add.clone() * (slot(0) + slot(1))
+                            + is_mul.clone() * slot(0) * slot(1)
+                            + is_dup.clone() * slot(0)
+                            + is_swap.clone() * slot(1)
+                    }
+                    1 => {
+                        (is_push.clone() + is_dup.clone() + is_swap.clone()) * slot(0)
+                            + (is_add.clone() + is_mul.clone()) * slot(2)
+                    }
+                    _ => {
+                        (is_push.clone() + is_dup.clone()) * slot(j - 1)
+                            + (is_add.clone() + is_mul.clone()) * slot(j + 1)
+                            + is_swap.clone() * slot(j)
+                    }
+                } + is_jz.clone() * slot(j + 1)
+                    + is_halt.clone() * slot(j);
+                constraints.push(next - expected);
+            }
+            Constraints::with_selector(s_step, constraints)
+        });
+
+        meta.create_gate("halted", |meta| {
+            let s_last = meta.query_selector(s_last);
+            let is_halt = meta.query_advice(flags[OPCODES - 1], Rotation::cur());
+            Constraints::with_selector(s_last, vec![one() - is_halt])
+        });
+
+        let poseidon = PoseidonChip::configure(meta);
+
+        StackVmConfig {
+            instance,
+            pc,
+            arg,
+            sp,
+            inv,
+            stack,
+            flags,
+            s_fetch,
+            s_step,
+            s_last,
+            table_pc,
+            table_opcode,
+            table_arg,
+            table_depth,
+            poseidon,
+        }
+    }
+
+    /// Loads the program table, after an all-zero row for disabled lookups,
+    /// and the depth table `0..=STACK_DEPTH`.
+    fn load_tables(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        program: &[Instruction],
+    ) -> Result<(), Error> {
+        let config = &self.config;
+        layouter.assign_table(
+            || "program",
+            |mut table| {
+                let rows = std::iter::once((Fp::zero(), Fp::zero(), Fp::zero())).chain(
+                    program.iter().enumerate().map(|(pc, instruction)| {
+                        (
+                            Fp::from(pc as u64),
+                            Fp::from(instruction.opcode()),
+                            instruction.arg(),
+                        )
+                    }),
+                );
+                for (row, (pc, opcode, arg)) in rows.enumerate() {
+                    table.assign_cell(|| "pc", config.table_pc, row, || Value::known(pc))?;
+                    table.assign_cell(
+                        || "opcode",
+                        config.table_opcode,
+                        row,
+                        || Value::known(opcode),
+                    )?;
+                    table.assign_cell(|| "arg", config.table_arg, row, || Value::known(arg))?;
+                }
+                Ok(())
+            },
+        )?;
+
+        layouter.assign_table(
+            || "depth",
+            |mut table| {
+                for depth in 0..=STACK_DEPTH {
+                    table.assign_cell(
+                        || "depth",
+                        config.table_depth,
+                        depth,
+                        || Value::known(Fp::from(depth as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Hashes the program from constants, as `program_hash` does.
+    fn hash_program(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        program: &[Instruction],
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let config = &self.config;
+        let poseidon = PoseidonChip::construct(config.poseidon.clone());
+        let mut h = layouter.assign_region(
+            || "h_0",
+            |mut region| region.assign_advice_from_constant(|| "h_0", config.arg, 0, Fp::zero()),
+        )?;
+        for (i, instruction) in program.iter().enumerate() {
+            let [opcode, arg] = layouter.assign_region(
+                || format!("instruction {}", i),
+                |mut region| {
+                    let opcode = region.assign_advice_from_constant(
+                        || "opcode",
+                        config.arg,
+                        0,
+                        Fp::from(instruction.opcode()),
+                    )?;
+                    let arg = region.assign_advice_from_constant(
+                        || "arg",
+                        config.arg,
+                        1,
+                        instruction.arg(),
+                    )?;
+                    Ok([opcode, arg])
+                },
+            )?;
+            h = poseidon.hash(
+                layouter.namespace(|| format!("h_{}", i + 1)),
+                &[h, opcode, arg],
+            )?;
+        }
+        Ok(h)
+    }
+
+    /// Assigns `steps` rows of the trace, starting from `pc = 0` and a stack
+    /// of `inputs` private values, and returns the final top of the stack.
+    fn assign_trace(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        trace: &Value<Vec<Step>>,
+        inputs: usize,
+        steps: usize,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "trace",
+            |mut region| {
+                let mut top = None;
+                for row in 0..steps {
+                    config.s_fetch.enable(&mut region, row)?;
+                    if row + 1 < steps {
+                        config.s_step.enable(&mut region, row)?;
+                    } else {
+                        config.s_last.enable(&mut region, row)?;
+                    }
+
+                    let step = trace.as_ref().map(|trace| trace[row].clone());
+                    let pc = region.assign_advice(
+                        || "pc",
+                        config.pc,
+                        row,
+                        || step.as_ref().map(|step| Fp::from(step.pc as u64)),
+                    )?;
+                    region.assign_advice(
+                        || "arg",
+                        config.arg,
+                        row,
+                        || step.as_ref().map(|step| step.instruction.arg()),
+                    )?;
+                    let sp = region.assign_advice(
+                        || "sp",
+                        config.sp,
+                        row,
+                        || step.as_ref().map(|step| Fp::from(step.stack.len() as u64)),
+                    )?;
+                    let slots: Value<Vec<Fp>> = step.as_ref().map(|step| {
+                        let mut slots: Vec<Fp> = step.stack.iter().rev().copied().collect();
+                        slots.resize(STACK_DEPTH, Fp::zero());
+                        slots
+                    });
+                    region.assign_advice(
+                        || "inv",
+                        config.inv,
+                        row,
+                        || {
+                            slots
+                                .as_ref()
+                                .map(|slots| slots[0].invert().unwrap_or(Fp::zero()))
+                        },
+                    )?;
+                    for (i, flag) in config.flags.iter().enumerate() {
+                        region.assign_advice(
+                            || "flag",
+                            *flag,
+                            row,
+                            || {
+                                step.as_ref()
+                                    .map(|step| Fp::from(step.instruction.opcode() == i as u64 + 1))
+                            },
+                        )?;
+                    }
+                    let mut cells = Vec::with_capacity(STACK_DEPTH);
+                    for (j, column) in config.stack.iter().enumerate() {
+                        cells.push(region.assign_advice(
+                            || "slot",
+                            *column,
+                            row,
+                            || slots.as_ref().map(|slots| slots[j]),
+                        )?);
+                    }
+
+                    if row == 0 {
+                        region.constrain_constant(pc.cell(), Fp::zero())?;
+                        region.constrain_constant(sp.cell(), Fp::from(inputs as u64))?;
+                        for cell in &cells[inputs..] {
+                            region.constrain_constant(cell.cell(), Fp::zero())?;
+                        }
+                    }
+                    top = Some(cells.swap_remove(0));
+                }
+                Ok(top.expect("at least one step"))
+            },
+        )
+    }
+}
+
+/// Proves that `program`, run on `inputs` private stack items, halts within
+/// `steps` steps with the public output on top of the stack. The program
+/// and both sizes are fixed at keygen. Instance: `[program hash, output]`.
+struct StackVmCircuit {
+    program: Vec<Instruction>,
+    inputs: usize,
+    steps: usize,
+    trace: Value<Vec<Step>>,
+}
+
+impl Circuit<Fp> for StackVmCircuit {
+    type Config = StackVmConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        StackVmCircuit {
+            program: self.program.clone(),
+            inputs: self.inputs,
+            steps: self.steps,
+            trace: Value::unknown(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        StackVmChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = StackVmChip::construct(config.clone());
+        chip.load_tables(layouter.namespace(|| "tables"), &self.program)?;
+        let hash = chip.hash_program(layouter.namespace(|| "program hash"), &self.program)?;
+        let output = chip.assign_trace(
+            layouter.namespace(|| "trace"),
+            &self.trace,
+            self.inputs,
+            self.steps,
+        )?;
+        layouter.constrain_instance(hash.cell(), config.instance, 0)?;
+        layouter.constrain_instance(output.cell(), config.instance, 1)
+    }
+}
+
+/// `x^3 + x + 5` of the input `x`.
+fn cubic() -> Vec<Instruction> {
+    use Instruction::*;
+    vec![Dup, Dup, Dup, Mul, Mul, Add, Push(Fp::from(5)), Add, Halt]
+}
+
+/// `x * 2^c` for the inputs `[x, c]`, with `c` on top, by a loop that
+/// doubles `x` and decrements `c` until it is zero.
+fn doubling() -> Vec<Instruction> {
+    use Instruction::*;
+    vec![
+        Dup,              // 0: [c, c, x]
+        Jz(10),           // 1: [c, x]
+        Push(-Fp::one()), // 2
+        Add,              // 3: [c - 1, x]
+        Swap,             // 4: [x, c - 1]
+        Dup,              // 5
+        Add,              // 6: [2x, c - 1]
+        Swap,             // 7: [c - 1, 2x]
+        Push(Fp::zero()), // 8
+        Jz(0),            // 9
+        Swap,             // 10: [x * 2^c, 0]
+        Halt,             // 11
+    ]
+}
+
+fn verify(
+    program: &[Instruction],
+    inputs: usize,
+    steps: usize,
+    trace: Vec<Step>,
+    output: Fp,
+) -> Result<(), Vec<halo2_proofs::dev::VerifyFailure>> {
+    let circuit = StackVmCircuit {
+        program: program.to_vec(),
+        inputs,
+        steps,
+        trace: Value::known(trace),
+    };
+    let instance = vec![program_hash(program), output];
+    MockProver::run(11, &circuit, vec![instance])
+        .unwrap()
+        .verify()
+}
+
+fn test_interpreter() {
+    use Instruction::*;
+    let x = Fp::from(3);
+    let trace = run(&cubic(), &[x], 12).unwrap();
+    assert_eq!(trace.len(), 12);
+    assert_eq!(trace[8].stack, vec![Fp::from(35)]);
+    assert!(trace[8..].iter().all(|step| step.instruction == Halt));
+
+    assert_eq!(run(&cubic(), &[x], 8).unwrap_err(), VmError::StepLimit);
+    assert_eq!(
+        run(&[Add, Halt], &[x], 4).unwrap_err(),
+        VmError::StackUnderflow { pc: 0 }
+    );
+    assert_eq!(
+        run(&[Dup; 8], &[x], 8).unwrap_err(),
+        VmError::StackOverflow { pc: 7 }
+    );
+    assert_eq!(
+        run(&[Jz(5)], &[Fp::zero()], 4).unwrap_err(),
+        VmError::PcOutOfRange { pc: 5 }
+    );
+    assert_ne!(program_hash(&cubic()), program_hash(&doubling()));
+}
+
+fn test_cubic() {
+    let steps = 12;
+    let program = cubic();
+    let trace = run(&program, &[Fp::from(3)], steps).unwrap();
+    assert_eq!(
+        verify(&program, 1, steps, trace.clone(), Fp::from(35)),
+        Ok(())
+    );
+
+    // Another output.
+    assert!(verify(&program, 1, steps, trace.clone(), Fp::from(36)).is_err());
+
+    // The hash of another program.
+    let circuit = StackVmCircuit {
+        program: program.clone(),
+        inputs: 1,
+        steps,
+        trace: Value::known(trace.clone()),
+    };
+    let instance = vec![program_hash(&doubling()), Fp::from(35)];
+    let prover = MockProver::run(11, &circuit, vec![instance]).unwrap();
+    assert!(prover.verify().is_err());
+
+    // A step that adds wrongly.
+    let mut forged = trace.clone();
+    for step in &mut forged[6..] {
+        step.stack[0] += Fp::one();
+    }
+    assert!(verify(&program, 1, steps, forged, Fp::from(36)).is_err());
+
+    // An instruction that is not in the program.
+    let mut forged = trace;
+    forged[6].instruction = Instruction::Push(Fp::from(6));
+    for step in &mut forged[7..] {
+        step.stack[0] += Fp::one();
+    }
+    assert!(verify(&program, 1, steps, forged, Fp::from(36)).is_err());
+}
+
+fn test_loop() {
+    let steps = 40;
+    let program = doubling();
+    let inputs = [Fp::from(5), Fp::from(3)];
+    let trace = run(&program, &inputs, steps).unwrap();
+    assert_eq!(
+        verify(&program, 2, steps, trace.clone(), Fp::from(40)),
+        Ok(())
+    );
+
+    // Taking the exit of the loop while the counter is still 2.
+    let exit = &trace[11];
+    assert_eq!(exit.instruction, Instruction::Jz(10));
+    assert_eq!(exit.stack, vec![Fp::from(10), Fp::from(2), Fp::from(2)]);
+    let stack = exit.stack[..2].to_vec();
+    let mut forged = trace[..12].to_vec();
+    forged.extend(run_from(&program, 10, stack, steps - 12).unwrap());
+    assert_eq!(forged[steps - 1].stack, vec![Fp::from(2), Fp::from(10)]);
+    assert!(verify(&program, 2, steps, forged, Fp::from(10)).is_err());
+
+    // Five doublings take more than `steps` steps.
+    let long = run(&program, &[Fp::from(5), Fp::from(5)], 64).unwrap();
+    let unfinished = long[..steps].to_vec();
+    let top = *unfinished[steps - 1].stack.last().unwrap();
+    assert!(verify(&program, 2, steps, unfinished, top).is_err());
+}
+
+fn main() {
+    test_interpreter();
+    test_cubic();
+    test_loop();
+}
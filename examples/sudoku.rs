@@ -0,0 +1,341 @@
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value},
+    dev::{MockProver, VerifyFailure},
+    pasta::Fp,
+    plonk::{
+        Advice, Circuit, Column, ConstraintSystem, Constraints, Error, Fixed, Instance, Selector,
+        TableColumn,
+    },
+    poly::Rotation,
+};
+
+type Grid = [[u64; 9]; 9];
+
+/// The sum of `2^(v - 1)` for `v` in 1..9.
+const GROUP_SUM: u64 = (1 << 9) - 1;
+
+#[derive(Clone, Debug)]
+struct SudokuConfig {
+    instance: Column<Instance>,
+    value: Column<Advice>,
+    power: Column<Advice>,
+    given: Column<Advice>,
+    acc: Column<Advice>,
+    s_cell: Selector,
+    s_sum: Selector,
+    table_value: TableColumn,
+    table_power: TableColumn,
+}
+
+/// Checks a solution cell by cell, then group by group.
+///
+/// Each cell takes one row:
+///
+/// value power given
+///
+/// where `(value, power)` is looked up in `{(v, 2^(v - 1)) : v in 1..9}`
+/// and `given * (value - given) = 0`, so a nonzero given fixes the value.
+///
+/// Each row, column and box then sums its nine powers with a running sum
+/// and must reach `2^9 - 1`. Nine powers of two can only add up to a number
+/// with nine bits set if they are all distinct, so every group is a
+/// permutation of 1..9. A sum or product of the values alone would not do:
+/// `{1, 2, 4, 4, 4, 5, 7, 9, 9}` matches both.
+struct SudokuChip {
+    config: SudokuConfig,
+}
+
+impl SudokuChip {
+    fn construct(config: SudokuConfig) -> Self {
+        SudokuChip { config }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> SudokuConfig {
+        let instance = meta.instance_column();
+        let value = meta.advice_column();
+        let power = meta.advice_column();
+        let given = meta.advice_column();
+        let acc = meta.advice_column();
+        let constant: Column<Fixed> = meta.fixed_column();
+        meta.enable_equality(instance);
+        meta.enable_equality(power);
+        meta.enable_equality(given);
+        meta.enable_equality(acc);
+        meta.enable_constant(constant);
+
+        let s_cell = meta.complex_selector();
+        let s_sum = meta.selector();
+        let table_value = meta.lookup_table_column();
+        let table_power = meta.lookup_table_column();
+
+        meta.create_gate("given", |meta| {
+            let s_cell = meta.query_selector(s_cell);
+            let value = meta.query_advice(value, Rotation::cur());
+            let given = meta.query_advice(given, Rotation::cur());
+            Constraints::with_selector(s_cell, vec![given.clone() * (value - given)])
+        });
+
+        meta.lookup(|meta| {
+            let s_cell = meta.query_selector(s_cell);
+            let value = meta.query_advice(value, Rotation::cur());
+            let power = meta.query_advice(power, Rotation::cur());
+            vec![
+                (s_cell.clone() * value, table_value),
+                (s_cell * power, table_power),
+            ]
+        });
+
+        meta.create_gate("group sum", |meta| {
+            let s_sum = meta.query_selector(s_sum);
+            let power = meta.query_advice(power, Rotation::cur());
+            let acc_cur = meta.query_advice(acc, Rotation::cur());
+            let acc_next = meta.query_advice(acc, Rotation::next());
+            Constraints::with_selector(s_sum, vec![acc_next - acc_cur - power])
+        });
+
+        SudokuConfig {
+            instance,
+            value,
+            power,
+            given,
+            acc,
+            s_cell,
+            s_sum,
+            table_value,
+            table_power,
+        }
+    }
+
+    /// Fills `(v, 2^(v - 1))` for `v` in 1..9, plus the `(0, 0)` that
+    /// disabled rows look up.
+    fn load_table(&self, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+        let config = &self.config;
+        layouter.assign_table(
+            || "digits",
+            |mut table| {
+                for v in 0..=9u64 {
+                    let power = if v == 0 { 0 } else { 1 << (v - 1) };
+                    table.assign_cell(
+                        || "value",
+                        config.table_value,
+                        v as usize,
+                        || Value::known(Fp::from(v)),
+                    )?;
+                    table.assign_cell(
+                        || "power",
+                        config.table_power,
+                        v as usize,
+                        || Value::known(Fp::from(power)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Assigns the solution against the puzzle in instance rows `0..81`,
+    /// row-major, and returns the power cell of every position.
+    fn assign_cells(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        solution: Value<Grid>,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "cells",
+            |mut region| {
+                (0..81)
+                    .map(|i| {
+                        config.s_cell.enable(&mut region, i)?;
+                        let value = solution.map(|grid| grid[i / 9][i % 9]);
+                        region.assign_advice(
+                            || "value",
+                            config.value,
+                            i,
+                            || value.map(Fp::from),
+                        )?;
+                        region.assign_advice_from_instance(
+                            || "given",
+                            config.instance,
+                            i,
+                            config.given,
+                            i,
+                        )?;
+                        // Values above 9 get no valid power and fail the lookup.
+                        // A 0 passes it through the `(0, 0)` row for disabled
+                        // rows; only the sums of its groups, each missing a
+                        // power, reject it.
+                        let power = value.map(|v| match v {
+                            1..=9 => Fp::from(1 << (v - 1)),
+                            _ => Fp::zero(),
+                        });
+                        region.assign_advice(|| "power", config.power, i, || power)
+                    })
+                    .collect()
+            },
+        )
+    }
+
+    /// Constrains the powers of one group to sum to `GROUP_SUM`.
+    fn assert_group(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        powers: &[&AssignedCell<Fp, Fp>],
+    ) -> Result<(), Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "group sum",
+            |mut region| {
+                let mut acc =
+                    region.assign_advice_from_constant(|| "acc", config.acc, 0, Fp::zero())?;
+                for (i, power) in powers.iter().enumerate() {
+                    config.s_sum.enable(&mut region, i)?;
+                    power.copy_advice(|| "power", &mut region, config.power, i)?;
+                    let next = acc.value().copied() + power.value();
+                    acc = region.assign_advice(|| "acc", config.acc, i + 1, || next)?;
+                }
+                region.constrain_constant(acc.cell(), Fp::from(GROUP_SUM))
+            },
+        )
+    }
+}
+
+/// Proves knowledge of a solution to the public puzzle.
+#[derive(Default)]
+struct SudokuCircuit {
+    solution: Value<Grid>,
+}
+
+impl Circuit<Fp> for SudokuCircuit {
+    type Config = SudokuConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        SudokuChip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let chip = SudokuChip::construct(config);
+        chip.load_table(layouter.namespace(|| "table"))?;
+        let powers = chip.assign_cells(layouter.namespace(|| "cells"), self.solution)?;
+        let cell = |row: usize, col: usize| &powers[row * 9 + col];
+
+        for i in 0..9 {
+            let row: Vec<_> = (0..9).map(|j| cell(i, j)).collect();
+            chip.assert_group(layouter.namespace(|| format!("row {}", i)), &row)?;
+            let column: Vec<_> = (0..9).map(|j| cell(j, i)).collect();
+            chip.assert_group(layouter.namespace(|| format!("column {}", i)), &column)?;
+            let (top, left) = (i / 3 * 3, i % 3 * 3);
+            let block: Vec<_> = (0..9).map(|j| cell(top + j / 3, left + j % 3)).collect();
+            chip.assert_group(layouter.namespace(|| format!("box {}", i)), &block)?;
+        }
+        Ok(())
+    }
+}
+
+const PUZZLE: Grid = [
+    [5, 3, 0, 0, 7, 0, 0, 0, 0],
+    [6, 0, 0, 1, 9, 5, 0, 0, 0],
+    [0, 9, 8, 0, 0, 0, 0, 6, 0],
+    [8, 0, 0, 0, 6, 0, 0, 0, 3],
+    [4, 0, 0, 8, 0, 3, 0, 0, 1],
+    [7, 0, 0, 0, 2, 0, 0, 0, 6],
+    [0, 6, 0, 0, 0, 0, 2, 8, 0],
+    [0, 0, 0, 4, 1, 9, 0, 0, 5],
+    [0, 0, 0, 0, 8, 0, 0, 7, 9],
+];
+
+const SOLUTION: Grid = [
+    [5, 3, 4, 6, 7, 8, 9, 1, 2],
+    [6, 7, 2, 1, 9, 5, 3, 4, 8],
+    [1, 9, 8, 3, 4, 2, 5, 6, 7],
+    [8, 5, 9, 7, 6, 1, 4, 2, 3],
+    [4, 2, 6, 8, 5, 3, 7, 9, 1],
+    [7, 1, 3, 9, 2, 4, 8, 5, 6],
+    [9, 6, 1, 5, 3, 7, 2, 8, 4],
+    [2, 8, 7, 4, 1, 9, 6, 3, 5],
+    [3, 4, 5, 2, 8, 6, 1, 7, 9],
+];
+
+fn instance(puzzle: &Grid) -> Vec<Vec<Fp>> {
+    vec![puzzle.iter().flatten().map(|v| Fp::from(*v)).collect()]
+}
+
+fn verify(puzzle: &Grid, solution: Grid) -> Result<(), Vec<VerifyFailure>> {
+    let circuit = SudokuCircuit {
+        solution: Value::known(solution),
+    };
+    MockProver::run(9, &circuit, instance(puzzle))
+        .unwrap()
+        .verify()
+}
+
+fn test_sudoku() {
+    assert_eq!(verify(&PUZZLE, SOLUTION), Ok(()));
+
+    // The solution of an empty puzzle is any valid grid.
+    assert_eq!(verify(&[[0; 9]; 9], SOLUTION), Ok(()));
+
+    // Swapping two blanks in a row keeps the row valid, but not the columns.
+    let mut swapped = SOLUTION;
+    swapped[0].swap(2, 3);
+    assert!(verify(&PUZZLE, swapped).is_err());
+
+    // Relabelling digits keeps every group a permutation, but breaks the
+    // givens.
+    let relabelled = SOLUTION.map(|row| row.map(|v| v % 9 + 1));
+    assert_eq!(verify(&[[0; 9]; 9], relabelled), Ok(()));
+    assert!(verify(&PUZZLE, relabelled).is_err());
+
+    // A row with the sum and product of 1..9 but repeated digits.
+    let mut repeated = SOLUTION;
+    repeated[0] = [1, 2, 4, 4, 4, 5, 7, 9, 9];
+    assert!(verify(&[[0; 9]; 9], repeated).is_err());
+
+    // Digits outside 1..9. A 0 in a blank satisfies the given and the
+    // lookup, and is caught by the group sums alone.
+    let mut zero = SOLUTION;
+    assert_eq!(PUZZLE[0][2], 0);
+    zero[0][2] = 0;
+    let failures = verify(&PUZZLE, zero).unwrap_err();
+    assert!(failures
+        .iter()
+        .all(|failure| matches!(failure, VerifyFailure::Permutation { .. })));
+    assert!(failures
+        .iter()
+        .any(|failure| failure.to_string().contains("group sum")));
+    let mut ten = SOLUTION;
+    ten[0][2] = 10;
+    assert!(verify(&PUZZLE, ten).is_err());
+}
+
+fn main() {
+    test_sudoku();
+
+    #[cfg(feature = "dev-graph")]
+    plot_sudoku_circuit();
+}
+
+#[cfg(feature = "dev-graph")]
+fn plot_sudoku_circuit() {
+    use plotters::prelude::*;
+
+    let circuit = SudokuCircuit::default();
+    let root = BitMapBackend::new("sudoku-layout.png", (1024, 3096)).into_drawing_area();
+    root.fill(&WHITE).unwrap();
+    let root = root
+        .titled("Sudoku Circuit Layout", ("sans-serif", 60))
+        .unwrap();
+    halo2_proofs::dev::CircuitLayout::default()
+        .show_labels(false)
+        .render(9, &circuit, &root)
+        .unwrap();
+}
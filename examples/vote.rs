@@ -0,0 +1,342 @@
+use std::collections::HashSet;
+
+use halo2_learning::{
+    merkle::{MerkleChip, MerkleConfig, MerkleTree},
+    poseidon,
+};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::MockProver,
+    pasta::{group::ff::PrimeField, Fp},
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Instance, Selector, TableColumn},
+    poly::Rotation,
+};
+
+const DEPTH: usize = 4;
+const K: u32 = 10;
+
+/// The ballot options: yes, no, abstain.
+const OPTIONS: [u64; 3] = [0, 1, 2];
+
+/// A voter's private identity. Only the commitment goes into the registry.
+#[derive(Clone, Copy, Debug)]
+struct Identity {
+    secret: Fp,
+    trapdoor: Fp,
+}
+
+impl Identity {
+    fn commitment(&self) -> Fp {
+        poseidon::hash([self.secret, self.trapdoor])
+    }
+
+    /// The same in every ballot of an election, unlinkable across
+    /// elections.
+    fn nullifier(&self, election_id: Fp) -> Fp {
+        poseidon::hash([self.secret, election_id])
+    }
+}
+
+#[derive(Clone, Debug)]
+struct VoteConfig {
+    instance: Column<Instance>,
+    vote: Column<Advice>,
+    s_vote: Selector,
+    options: TableColumn,
+    merkle: MerkleConfig<Fp>,
+}
+
+/// Proves that the voter's identity commitment `Poseidon(secret, trapdoor)`
+/// is a leaf of the registry, and exposes the nullifier
+/// `Poseidon(secret, election_id)` along with the vote, looked up in the
+/// table of `OPTIONS`. The instance column holds
+/// `[registry root, election_id, nullifier, vote]`.
+struct VoteCircuit {
+    identity: Value<Identity>,
+    siblings: [Value<Fp>; DEPTH],
+    directions: [Value<bool>; DEPTH],
+}
+
+impl VoteCircuit {
+    fn new(identity: Identity, registry: &MerkleTree<Fp>, index: usize) -> Self {
+        let path = registry.path(index);
+        let siblings: [Fp; DEPTH] = path.siblings.try_into().unwrap();
+        let directions: [bool; DEPTH] = path.directions.try_into().unwrap();
+        VoteCircuit {
+            identity: Value::known(identity),
+            siblings: siblings.map(Value::known),
+            directions: directions.map(Value::known),
+        }
+    }
+}
+
+impl Circuit<Fp> for VoteCircuit {
+    type Config = VoteConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        VoteCircuit {
+            identity: Value::unknown(),
+            siblings: [Value::unknown(); DEPTH],
+            directions: [Value::unknown(); DEPTH],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let vote = meta.advice_column();
+        meta.enable_equality(vote);
+        let s_vote = meta.complex_selector();
+        let options = meta.lookup_table_column();
+
+        // Disabled rows look up 0, which is an option.
+        meta.lookup(|meta| {
+            let s_vote = meta.query_selector(s_vote);
+            let vote = meta.query_advice(vote, Rotation::cur());
+            vec![(s_vote * vote, options)]
+        });
+
+        let merkle = MerkleChip::configure(meta);
+
+        VoteConfig {
+            instance,
+            vote,
+            s_vote,
+            options,
+            merkle,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        layouter.assign_table(
+            || "options",
+            |mut table| {
+                for (row, option) in OPTIONS.iter().enumerate() {
+                    table.assign_cell(
+                        || "option",
+                        config.options,
+                        row,
+                        || Value::known(Fp::from(*option)),
+                    )?;
+                }
+                Ok(())
+            },
+        )?;
+
+        layouter.assign_region(
+            || "vote",
+            |mut region| {
+                config.s_vote.enable(&mut region, 0)?;
+                region.assign_advice_from_instance(
+                    || "vote",
+                    config.instance,
+                    3,
+                    config.vote,
+                    0,
+                )?;
+                Ok(())
+            },
+        )?;
+
+        let chip = MerkleChip::construct(config.merkle.clone());
+        let poseidon = chip.poseidon();
+        let secret = chip.load_private(
+            layouter.namespace(|| "secret"),
+            self.identity.map(|identity| identity.secret),
+        )?;
+        let trapdoor = chip.load_private(
+            layouter.namespace(|| "trapdoor"),
+            self.identity.map(|identity| identity.trapdoor),
+        )?;
+
+        let commitment = poseidon.hash(
+            layouter.namespace(|| "commitment"),
+            &[secret.clone(), trapdoor],
+        )?;
+        let root = chip.compute_root(
+            layouter.namespace(|| "registry root"),
+            &commitment,
+            &self.siblings,
+            &self.directions,
+        )?;
+        layouter.constrain_instance(root.cell(), config.instance, 0)?;
+
+        let election_id =
+            chip.load_instance(layouter.namespace(|| "election id"), config.instance, 1)?;
+        let nullifier =
+            poseidon.hash(layouter.namespace(|| "nullifier"), &[secret, election_id])?;
+        layouter.constrain_instance(nullifier.cell(), config.instance, 2)
+    }
+}
+
+/// A published ballot: the public inputs and the proof over them, which
+/// `MockProver` stands in for.
+struct Ballot {
+    nullifier: Fp,
+    vote: u64,
+    circuit: VoteCircuit,
+}
+
+/// The registrar's list of identity commitments, in registration order.
+struct Registry {
+    tree: MerkleTree<Fp>,
+    commitments: Vec<Fp>,
+}
+
+impl Registry {
+    fn new(identities: &[Identity]) -> Self {
+        let commitments: Vec<Fp> = identities.iter().map(Identity::commitment).collect();
+        Registry {
+            tree: MerkleTree::new(DEPTH, commitments.clone()),
+            commitments,
+        }
+    }
+
+    /// Casts a ballot as `identity`, who must be registered for the proof
+    /// to verify. An unregistered voter still gets a ballot, proving against
+    /// the path of the first slot.
+    fn ballot(&self, identity: Identity, election_id: Fp, vote: u64) -> Ballot {
+        let index = self
+            .commitments
+            .iter()
+            .position(|c| *c == identity.commitment())
+            .unwrap_or(0);
+        Ballot {
+            nullifier: identity.nullifier(election_id),
+            vote,
+            circuit: VoteCircuit::new(identity, &self.tree, index),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Tally {
+    counts: [u64; OPTIONS.len()],
+    invalid: usize,
+    duplicates: usize,
+}
+
+/// Counts the ballots with a valid proof against the registry root, once
+/// per nullifier: a second ballot from the same voter is a duplicate,
+/// whatever its vote.
+fn tally(root: Fp, election_id: Fp, ballots: &[Ballot]) -> Tally {
+    let mut tally = Tally::default();
+    let mut seen = HashSet::new();
+    for ballot in ballots {
+        let instance = vec![root, election_id, ballot.nullifier, Fp::from(ballot.vote)];
+        let valid = MockProver::run(K, &ballot.circuit, vec![instance])
+            .unwrap()
+            .verify()
+            .is_ok();
+        if !valid {
+            tally.invalid += 1;
+        } else if !seen.insert(ballot.nullifier.to_repr()) {
+            tally.duplicates += 1;
+        } else {
+            tally.counts[ballot.vote as usize] += 1;
+        }
+    }
+    tally
+}
+
+fn test_vote() {
+    let identities: Vec<Identity> = (0..6u64)
+        .map(|i| Identity {
+            secret: Fp::from(0x5ec0 + i),
+            trapdoor: Fp::from(0x7a9 * (i + 1)),
+        })
+        .collect();
+    let registry = Registry::new(&identities);
+    let root = registry.tree.root();
+    let election_id = Fp::from(2024);
+
+    let verify = |ballot: &Ballot, nullifier: Fp, vote: u64| {
+        let instance = vec![root, election_id, nullifier, Fp::from(vote)];
+        MockProver::run(K, &ballot.circuit, vec![instance])
+            .unwrap()
+            .verify()
+    };
+
+    let ballot = registry.ballot(identities[4], election_id, 1);
+    assert_eq!(verify(&ballot, ballot.nullifier, 1), Ok(()));
+
+    // Every option is allowed, anything else is not.
+    for vote in OPTIONS {
+        assert_eq!(verify(&ballot, ballot.nullifier, vote), Ok(()));
+    }
+    assert!(verify(&ballot, ballot.nullifier, 3).is_err());
+    assert!(verify(&ballot, ballot.nullifier, u64::MAX).is_err());
+
+    // The nullifier is bound to the secret and the election.
+    assert!(verify(&ballot, identities[3].nullifier(election_id), 1).is_err());
+    assert!(verify(&ballot, identities[4].nullifier(Fp::from(2025)), 1).is_err());
+    assert_ne!(
+        identities[4].nullifier(election_id),
+        identities[4].nullifier(Fp::from(2025)),
+    );
+
+    // A voter who is not registered, or who lies about their trapdoor.
+    let outsider = Identity {
+        secret: Fp::from(0xbad),
+        trapdoor: Fp::from(0xbad),
+    };
+    let ballot = registry.ballot(outsider, election_id, 0);
+    assert!(verify(&ballot, ballot.nullifier, 0).is_err());
+    let impostor = Identity {
+        trapdoor: Fp::from(1),
+        ..identities[2]
+    };
+    let ballot = VoteCircuit::new(impostor, &registry.tree, 2);
+    let ballot = Ballot {
+        nullifier: impostor.nullifier(election_id),
+        vote: 0,
+        circuit: ballot,
+    };
+    assert!(verify(&ballot, ballot.nullifier, 0).is_err());
+}
+
+fn test_tally() {
+    let identities: Vec<Identity> = (0..5u64)
+        .map(|i| Identity {
+            secret: Fp::from(1000 + i),
+            trapdoor: Fp::from(2000 + i),
+        })
+        .collect();
+    let registry = Registry::new(&identities);
+    let election_id = Fp::from(7);
+    let outsider = Identity {
+        secret: Fp::from(1),
+        trapdoor: Fp::from(2),
+    };
+
+    let ballots = vec![
+        registry.ballot(identities[0], election_id, 0),
+        registry.ballot(identities[1], election_id, 0),
+        registry.ballot(identities[2], election_id, 1),
+        registry.ballot(identities[3], election_id, 2),
+        // Voting twice, even for something else.
+        registry.ballot(identities[1], election_id, 1),
+        // Not registered.
+        registry.ballot(outsider, election_id, 1),
+        // A proof for another election.
+        registry.ballot(identities[4], Fp::from(8), 1),
+    ];
+    assert_eq!(
+        tally(registry.tree.root(), election_id, &ballots),
+        Tally {
+            counts: [2, 1, 1],
+            invalid: 2,
+            duplicates: 1,
+        }
+    );
+}
+
+fn main() {
+    test_vote();
+    test_tally();
+}
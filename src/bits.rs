@@ -0,0 +1,146 @@
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    pasta::group::ff::PrimeField,
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Selector},
+    poly::Rotation,
+};
+
+/// Little-endian bits of the canonical encoding of `value`, which is
+/// little-endian for the Pasta fields.
+pub fn le_bits<F: PrimeField>(value: &F) -> Vec<bool> {
+    value
+        .to_repr()
+        .as_ref()
+        .iter()
+        .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1 == 1))
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+pub struct BitsConfig {
+    bit: Column<Advice>,
+    acc: Column<Advice>,
+    selector: Selector,
+}
+
+/// Boolean decomposition by a running sum, most significant bit first over
+/// `n + 1` rows:
+///
+/// bit       acc
+/// b_{n-1}   0
+/// b_{n-2}   b_{n-1}
+/// ...       ...
+///           value
+///
+/// with every bit boolean and `acc_next = 2 * acc + bit`. For `n` below
+/// `F::NUM_BITS` the decomposition is unique and bounds the value by `2^n`.
+///
+/// The circuit must enable a constant column: the sum starts from a
+/// constant zero.
+#[derive(Clone, Debug)]
+pub struct BitsChip<F: PrimeField> {
+    config: BitsConfig,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: PrimeField> BitsChip<F> {
+    pub fn construct(config: BitsConfig) -> Self {
+        BitsChip {
+            config,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> BitsConfig {
+        let bit = meta.advice_column();
+        let acc = meta.advice_column();
+        meta.enable_equality(bit);
+        meta.enable_equality(acc);
+        let selector = meta.selector();
+
+        meta.create_gate("bit decomposition", |meta| {
+            let selector = meta.query_selector(selector);
+            let bit = meta.query_advice(bit, Rotation::cur());
+            let acc_cur = meta.query_advice(acc, Rotation::cur());
+            let acc_next = meta.query_advice(acc, Rotation::next());
+            let one = Expression::Constant(F::ONE);
+            let two = Expression::Constant(F::from(2));
+
+            Constraints::with_selector(
+                selector,
+                vec![
+                    bit.clone() * (one - bit.clone()),
+                    acc_next - acc_cur * two - bit,
+                ],
+            )
+        });
+
+        BitsConfig { bit, acc, selector }
+    }
+
+    /// Witnesses `num_bits` little-endian bits.
+    pub fn assign_bits(
+        &self,
+        layouter: impl Layouter<F>,
+        bits: Value<Vec<bool>>,
+        num_bits: usize,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        self.assign(layouter, bits, num_bits, None)
+    }
+
+    /// Returns the `num_bits` little-endian bits of `value`, which must be
+    /// below `2^num_bits`.
+    pub fn decompose(
+        &self,
+        layouter: impl Layouter<F>,
+        value: &AssignedCell<F, F>,
+        num_bits: usize,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        // Out-of-range values still get bits; the final copy fails.
+        let bits = value.value().map(le_bits);
+        self.assign(layouter, bits, num_bits, Some(value))
+    }
+
+    fn assign(
+        &self,
+        mut layouter: impl Layouter<F>,
+        bits: Value<Vec<bool>>,
+        num_bits: usize,
+        value: Option<&AssignedCell<F, F>>,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let config = &self.config;
+
+        layouter.assign_region(
+            || "bit decomposition",
+            |mut region| {
+                let mut acc =
+                    region.assign_advice_from_constant(|| "acc", config.acc, 0, F::ZERO)?;
+                let mut cells = Vec::with_capacity(num_bits);
+                for row in 0..num_bits {
+                    config.selector.enable(&mut region, row)?;
+                    let bit = bits.as_ref().map(|bits| bits[num_bits - 1 - row]);
+                    let bit = region.assign_advice(
+                        || "bit",
+                        config.bit,
+                        row,
+                        || bit.map(|bit| F::from(bit as u64)),
+                    )?;
+                    let next = acc
+                        .value()
+                        .zip(bit.value())
+                        .map(|(acc, bit)| acc.double() + bit);
+                    acc = region.assign_advice(|| "acc", config.acc, row + 1, || next)?;
+                    cells.push(bit);
+                }
+                if let Some(value) = value {
+                    region.constrain_equal(acc.cell(), value.cell())?;
+                }
+
+                cells.reverse();
+                Ok(cells)
+            },
+        )
+    }
+}
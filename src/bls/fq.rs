@@ -0,0 +1,1076 @@
+use std::marker::PhantomData;
+use std::sync::OnceLock;
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    pasta::group::ff::PrimeField,
+    plonk::{
+        Advice, Column, ConstraintSystem, Constraints, Error, Expression, Fixed, Instance, Selector,
+    },
+    poly::Rotation,
+};
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, Zero};
+
+use crate::range::{RangeCheckChip, RangeCheckConfig};
+
+/// Bits per limb of an emulated BLS12-381 base field element.
+pub const LIMB_BITS: usize = 96;
+/// Limbs per element; `4 * 96 = 384` bits covers the 381-bit modulus.
+pub const NUM_LIMBS: usize = 4;
+
+const NUM_QUOTIENT_LIMBS: usize = 5;
+const NUM_CARRIES: usize = 7;
+/// Carries are stored shifted by `2^(CARRY_BITS - 1)` so they can be range
+/// checked as unsigned values.
+const CARRY_BITS: usize = 112;
+/// `a - b + SUB_OFFSET * q` is always positive for `a, b < 2^384`.
+const SUB_OFFSET: u64 = 16;
+/// Columns of the limb-wise product of two elements.
+const NUM_PRODUCT_COLUMNS: usize = 2 * NUM_LIMBS - 1;
+/// Bound on the sum of `|coeff|` in one `sum_products` call, keeping every
+/// accumulator and carry well inside its range.
+const MAX_COEFF_SUM: u64 = 1 << 10;
+
+const MODULUS_HEX: &str = "1a0111ea397fe69a4b1ba7b6434bacd764774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaaab";
+
+/// The BLS12-381 base field modulus `q`.
+pub fn modulus() -> &'static BigUint {
+    static MODULUS: OnceLock<BigUint> = OnceLock::new();
+    MODULUS.get_or_init(|| BigUint::parse_bytes(MODULUS_HEX.as_bytes(), 16).unwrap())
+}
+
+/// Splits `value` into `count` little-endian limbs of `LIMB_BITS` bits.
+pub fn to_limbs(value: &BigUint, count: usize) -> Vec<BigUint> {
+    let mask = (BigUint::one() << LIMB_BITS) - 1u32;
+    (0..count)
+        .map(|i| (value >> (i * LIMB_BITS)) & &mask)
+        .collect()
+}
+
+/// Maps `value` into the native field.
+pub fn big_to_fe<F: PrimeField>(value: &BigUint) -> F {
+    value
+        .to_u64_digits()
+        .iter()
+        .rev()
+        .fold(F::ZERO, |acc, digit| {
+            acc * F::from_u128(1 << 64) + F::from(*digit)
+        })
+}
+
+/// The native field elements an instance column holds for a canonical `value`.
+pub fn fq_to_instance<F: PrimeField>(value: &BigUint) -> Vec<F> {
+    to_limbs(&(value % modulus()), NUM_LIMBS)
+        .iter()
+        .map(big_to_fe)
+        .collect()
+}
+
+pub fn fq_inverse(value: &BigUint) -> BigUint {
+    let q = modulus();
+    value.modpow(&(q - 2u32), q)
+}
+
+/// An emulated base field element. The limbs are range checked to `LIMB_BITS`
+/// bits, so the represented integer is below `2^384` but not necessarily
+/// reduced below `q`.
+#[derive(Clone, Debug)]
+pub struct AssignedFq<F: PrimeField> {
+    pub limbs: [AssignedCell<F, F>; NUM_LIMBS],
+    pub value: Value<BigUint>,
+}
+
+/// `coeff * a * b`, or `coeff * a` without `b`. See `FqChip::sum_products`.
+#[derive(Clone, Copy, Debug)]
+pub struct Term<'a, F: PrimeField> {
+    pub coeff: i64,
+    pub a: &'a AssignedFq<F>,
+    pub b: Option<&'a AssignedFq<F>>,
+}
+
+impl<'a, F: PrimeField> Term<'a, F> {
+    pub fn product(coeff: i64, a: &'a AssignedFq<F>, b: &'a AssignedFq<F>) -> Self {
+        Term {
+            coeff,
+            a,
+            b: Some(b),
+        }
+    }
+
+    pub fn linear(coeff: i64, a: &'a AssignedFq<F>) -> Self {
+        Term { coeff, a, b: None }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+}
+
+#[derive(Clone, Debug)]
+pub struct FqConfig {
+    advice: [Column<Advice>; 8],
+    acc: [Column<Advice>; NUM_PRODUCT_COLUMNS],
+    coeff: Column<Fixed>,
+    s_add: Selector,
+    s_sub: Selector,
+    s_mul: Selector,
+    s_select: Selector,
+    s_bool: Selector,
+    s_product: Selector,
+    s_linear: Selector,
+    s_reduce: Selector,
+    range: RangeCheckConfig,
+}
+
+/// Non-native arithmetic over the BLS12-381 base field.
+///
+/// Every operation witnesses `lhs = quotient * q + result` and proves it over
+/// the integers limb by limb with a carry chain:
+///
+/// row 0: a0 a1 a2 a3 b0 b1 b2 b3
+/// row 1: k0 k1 k2 k3 k4 r0 r1 r2
+/// row 2: r3 c0 c1 c2 c3 c4 c5 c6
+///
+/// `sum_products` proves the same relation for `sum(coeff * a * b)`, adding
+/// one term per row into the limb-wise accumulator columns before a single
+/// reduction, which is what makes tower field arithmetic affordable.
+#[derive(Clone, Debug)]
+pub struct FqChip<F: PrimeField> {
+    config: FqConfig,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: PrimeField> FqChip<F> {
+    pub fn construct(config: FqConfig) -> Self {
+        FqChip {
+            config,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> FqConfig {
+        let advice = [(); 8].map(|_| meta.advice_column());
+        let acc = [(); NUM_PRODUCT_COLUMNS].map(|_| meta.advice_column());
+        let coeff = meta.fixed_column();
+        let constant = meta.fixed_column();
+        let s_add = meta.selector();
+        let s_sub = meta.selector();
+        let s_mul = meta.selector();
+        let s_select = meta.selector();
+        let s_bool = meta.selector();
+        let s_product = meta.selector();
+        let s_linear = meta.selector();
+        let s_reduce = meta.selector();
+
+        meta.enable_constant(constant);
+        for c in advice.iter().chain(acc.iter()) {
+            meta.enable_equality(*c);
+        }
+
+        let range = RangeCheckChip::configure(meta);
+
+        let q_limbs: Vec<F> = to_limbs(modulus(), NUM_LIMBS)
+            .iter()
+            .map(big_to_fe)
+            .collect();
+
+        for (name, op, selector) in [
+            ("fq add", Op::Add, s_add),
+            ("fq sub", Op::Sub, s_sub),
+            ("fq mul", Op::Mul, s_mul),
+        ] {
+            let q_limbs = q_limbs.clone();
+            meta.create_gate(name, |meta| {
+                let a: Vec<_> = (0..NUM_LIMBS)
+                    .map(|i| meta.query_advice(advice[i], Rotation::cur()))
+                    .collect();
+                let b: Vec<_> = (0..NUM_LIMBS)
+                    .map(|i| meta.query_advice(advice[4 + i], Rotation::cur()))
+                    .collect();
+                let quotient: Vec<_> = (0..NUM_QUOTIENT_LIMBS)
+                    .map(|i| meta.query_advice(advice[i], Rotation::next()))
+                    .collect();
+                let r = vec![
+                    meta.query_advice(advice[5], Rotation::next()),
+                    meta.query_advice(advice[6], Rotation::next()),
+                    meta.query_advice(advice[7], Rotation::next()),
+                    meta.query_advice(advice[0], Rotation(2)),
+                ];
+                let carries: Vec<_> = (0..NUM_CARRIES)
+                    .map(|i| meta.query_advice(advice[1 + i], Rotation(2)))
+                    .collect();
+
+                let num_columns = NUM_QUOTIENT_LIMBS + NUM_LIMBS - 1;
+                let mut lhs = vec![Expression::Constant(F::ZERO); num_columns];
+                match op {
+                    Op::Add => {
+                        for i in 0..NUM_LIMBS {
+                            lhs[i] = a[i].clone() + b[i].clone();
+                        }
+                    }
+                    Op::Sub => {
+                        let offset = F::from(SUB_OFFSET);
+                        for i in 0..NUM_LIMBS {
+                            lhs[i] = a[i].clone() - b[i].clone()
+                                + Expression::Constant(q_limbs[i] * offset);
+                        }
+                    }
+                    Op::Mul => {
+                        for i in 0..NUM_LIMBS {
+                            for j in 0..NUM_LIMBS {
+                                lhs[i + j] = lhs[i + j].clone() + a[i].clone() * b[j].clone();
+                            }
+                        }
+                    }
+                }
+
+                let selector = meta.query_selector(selector);
+                Constraints::with_selector(
+                    selector,
+                    carry_constraints(lhs, &quotient, &r, &carries, &q_limbs),
+                )
+            });
+        }
+
+        meta.create_gate("fq select", |meta| {
+            let bit = meta.query_advice(advice[0], Rotation::next());
+            let one = Expression::Constant(F::ONE);
+            let mut constraints = vec![bit.clone() * (one - bit.clone())];
+            for i in 0..NUM_LIMBS {
+                let a = meta.query_advice(advice[i], Rotation::cur());
+                let b = meta.query_advice(advice[4 + i], Rotation::cur());
+                let r = meta.query_advice(advice[1 + i], Rotation::next());
+                constraints.push(r - (b.clone() + bit.clone() * (a - b)));
+            }
+
+            let s_select = meta.query_selector(s_select);
+            Constraints::with_selector(s_select, constraints)
+        });
+
+        meta.create_gate("bool", |meta| {
+            let one = Expression::Constant(F::ONE);
+            let constraints: Vec<_> = advice
+                .iter()
+                .map(|c| {
+                    let bit = meta.query_advice(*c, Rotation::cur());
+                    bit.clone() * (one.clone() - bit)
+                })
+                .collect();
+
+            let s_bool = meta.query_selector(s_bool);
+            Constraints::with_selector(s_bool, constraints)
+        });
+
+        meta.create_gate("fq product term", |meta| {
+            let coeff = meta.query_fixed(coeff, Rotation::cur());
+            let constraints: Vec<_> = (0..NUM_PRODUCT_COLUMNS)
+                .map(|k| {
+                    let mut sum = Expression::Constant(F::ZERO);
+                    for i in 0..NUM_LIMBS {
+                        if k >= i && k - i < NUM_LIMBS {
+                            let a = meta.query_advice(advice[i], Rotation::cur());
+                            let b = meta.query_advice(advice[4 + k - i], Rotation::cur());
+                            sum = sum + a * b;
+                        }
+                    }
+                    let cur = meta.query_advice(acc[k], Rotation::cur());
+                    let next = meta.query_advice(acc[k], Rotation::next());
+                    next - cur - coeff.clone() * sum
+                })
+                .collect();
+
+            let s_product = meta.query_selector(s_product);
+            Constraints::with_selector(s_product, constraints)
+        });
+
+        meta.create_gate("fq linear term", |meta| {
+            let coeff = meta.query_fixed(coeff, Rotation::cur());
+            let constraints: Vec<_> = (0..NUM_PRODUCT_COLUMNS)
+                .map(|k| {
+                    let cur = meta.query_advice(acc[k], Rotation::cur());
+                    let next = meta.query_advice(acc[k], Rotation::next());
+                    if k < NUM_LIMBS {
+                        let a = meta.query_advice(advice[k], Rotation::cur());
+                        next - cur - coeff.clone() * a
+                    } else {
+                        next - cur
+                    }
+                })
+                .collect();
+
+            let s_linear = meta.query_selector(s_linear);
+            Constraints::with_selector(s_linear, constraints)
+        });
+
+        meta.create_gate("fq reduce", |meta| {
+            let mut lhs: Vec<_> = acc
+                .iter()
+                .map(|c| meta.query_advice(*c, Rotation::cur()))
+                .collect();
+            lhs.push(Expression::Constant(F::ZERO));
+            let quotient: Vec<_> = (0..NUM_QUOTIENT_LIMBS)
+                .map(|i| meta.query_advice(advice[i], Rotation::cur()))
+                .collect();
+            let r = vec![
+                meta.query_advice(advice[5], Rotation::cur()),
+                meta.query_advice(advice[6], Rotation::cur()),
+                meta.query_advice(advice[7], Rotation::cur()),
+                meta.query_advice(advice[0], Rotation::next()),
+            ];
+            let carries: Vec<_> = (0..NUM_CARRIES)
+                .map(|i| meta.query_advice(advice[1 + i], Rotation::next()))
+                .collect();
+
+            let s_reduce = meta.query_selector(s_reduce);
+            Constraints::with_selector(
+                s_reduce,
+                carry_constraints(lhs, &quotient, &r, &carries, &q_limbs),
+            )
+        });
+
+        FqConfig {
+            advice,
+            acc,
+            coeff,
+            s_add,
+            s_sub,
+            s_mul,
+            s_select,
+            s_bool,
+            s_product,
+            s_linear,
+            s_reduce,
+            range,
+        }
+    }
+
+    pub fn range_chip(&self) -> RangeCheckChip<F> {
+        RangeCheckChip::construct(self.config.range.clone())
+    }
+
+    /// Loads the lookup table backing all limb range checks. Must be called once
+    /// per circuit.
+    pub fn load_table(&self, layouter: impl Layouter<F>) -> Result<(), Error> {
+        self.range_chip().load_table(layouter)
+    }
+
+    pub fn load_private(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: Value<BigUint>,
+    ) -> Result<AssignedFq<F>, Error> {
+        let config = &self.config;
+        let limbs = value.as_ref().map(|v| to_limbs(v, NUM_LIMBS));
+
+        let cells = layouter.assign_region(
+            || "load private fq",
+            |mut region| {
+                (0..NUM_LIMBS)
+                    .map(|i| {
+                        let limb = limbs.as_ref().map(|limbs| big_to_fe(&limbs[i]));
+                        region.assign_advice(|| "limb", config.advice[i], 0, || limb)
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            },
+        )?;
+
+        self.range_chip()
+            .range_check(layouter.namespace(|| "limbs"), &cells, LIMB_BITS)?;
+
+        Ok(AssignedFq {
+            limbs: cells.try_into().unwrap(),
+            value,
+        })
+    }
+
+    pub fn load_constant(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: &BigUint,
+    ) -> Result<AssignedFq<F>, Error> {
+        let config = &self.config;
+        let limbs = to_limbs(value, NUM_LIMBS);
+
+        let cells = layouter.assign_region(
+            || "load constant fq",
+            |mut region| {
+                (0..NUM_LIMBS)
+                    .map(|i| {
+                        region.assign_advice_from_constant(
+                            || "limb",
+                            config.advice[i],
+                            0,
+                            big_to_fe(&limbs[i]),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            },
+        )?;
+
+        Ok(AssignedFq {
+            limbs: cells.try_into().unwrap(),
+            value: Value::known(value.clone()),
+        })
+    }
+
+    /// Loads an element whose limbs sit in `instance` rows `row..row + NUM_LIMBS`.
+    pub fn load_instance(
+        &self,
+        mut layouter: impl Layouter<F>,
+        instance: Column<Instance>,
+        row: usize,
+    ) -> Result<AssignedFq<F>, Error> {
+        let config = &self.config;
+
+        let cells = layouter.assign_region(
+            || "load instance fq",
+            |mut region| {
+                (0..NUM_LIMBS)
+                    .map(|i| {
+                        region.assign_advice_from_instance(
+                            || "limb",
+                            instance,
+                            row + i,
+                            config.advice[i],
+                            0,
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            },
+        )?;
+
+        self.range_chip()
+            .range_check(layouter.namespace(|| "limbs"), &cells, LIMB_BITS)?;
+
+        let value = cells
+            .iter()
+            .rev()
+            .fold(Value::known(BigUint::zero()), |acc, cell| {
+                acc.zip(cell.value())
+                    .map(|(acc, limb)| (acc << LIMB_BITS) + fe_to_big(limb))
+            });
+
+        Ok(AssignedFq {
+            limbs: cells.try_into().unwrap(),
+            value,
+        })
+    }
+
+    /// Constrains the limbs of `a` to the instance rows `row..row + NUM_LIMBS`.
+    /// `a` must be reduced, e.g. via `assert_equal` against a loaded instance.
+    pub fn expose_public(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &AssignedFq<F>,
+        instance: Column<Instance>,
+        row: usize,
+    ) -> Result<(), Error> {
+        for (i, limb) in a.limbs.iter().enumerate() {
+            layouter.constrain_instance(limb.cell(), instance, row + i)?;
+        }
+        Ok(())
+    }
+
+    pub fn add(
+        &self,
+        layouter: impl Layouter<F>,
+        a: &AssignedFq<F>,
+        b: &AssignedFq<F>,
+    ) -> Result<AssignedFq<F>, Error> {
+        self.assign_op(layouter, Op::Add, a, b, false)
+            .map(|r| r.unwrap())
+    }
+
+    pub fn sub(
+        &self,
+        layouter: impl Layouter<F>,
+        a: &AssignedFq<F>,
+        b: &AssignedFq<F>,
+    ) -> Result<AssignedFq<F>, Error> {
+        self.assign_op(layouter, Op::Sub, a, b, false)
+            .map(|r| r.unwrap())
+    }
+
+    pub fn neg(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &AssignedFq<F>,
+    ) -> Result<AssignedFq<F>, Error> {
+        let zero = self.load_constant(layouter.namespace(|| "zero"), &BigUint::zero())?;
+        self.sub(layouter.namespace(|| "0 - a"), &zero, a)
+    }
+
+    pub fn double(
+        &self,
+        layouter: impl Layouter<F>,
+        a: &AssignedFq<F>,
+    ) -> Result<AssignedFq<F>, Error> {
+        self.add(layouter, a, a)
+    }
+
+    pub fn mul(
+        &self,
+        layouter: impl Layouter<F>,
+        a: &AssignedFq<F>,
+        b: &AssignedFq<F>,
+    ) -> Result<AssignedFq<F>, Error> {
+        self.assign_op(layouter, Op::Mul, a, b, false)
+            .map(|r| r.unwrap())
+    }
+
+    pub fn square(
+        &self,
+        layouter: impl Layouter<F>,
+        a: &AssignedFq<F>,
+    ) -> Result<AssignedFq<F>, Error> {
+        self.mul(layouter, a, a)
+    }
+
+    /// Constrains `a = b (mod q)`.
+    pub fn assert_equal(
+        &self,
+        layouter: impl Layouter<F>,
+        a: &AssignedFq<F>,
+        b: &AssignedFq<F>,
+    ) -> Result<(), Error> {
+        self.assign_op(layouter, Op::Sub, a, b, true).map(|_| ())
+    }
+
+    /// Returns `a^-1`, constraining `a != 0 (mod q)` on the way.
+    pub fn invert(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &AssignedFq<F>,
+    ) -> Result<AssignedFq<F>, Error> {
+        let inv = a.value.as_ref().map(fq_inverse);
+        let inv = self.load_private(layouter.namespace(|| "witness inverse"), inv)?;
+        let product = self.mul(layouter.namespace(|| "a * a^-1"), a, &inv)?;
+        let one = self.load_constant(layouter.namespace(|| "one"), &BigUint::one())?;
+        self.assert_equal(layouter.namespace(|| "a * a^-1 = 1"), &product, &one)?;
+        Ok(inv)
+    }
+
+    /// Returns `a / b`, failing to verify if `b = 0 (mod q)`.
+    pub fn div(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &AssignedFq<F>,
+        b: &AssignedFq<F>,
+    ) -> Result<AssignedFq<F>, Error> {
+        let b_inv = self.invert(layouter.namespace(|| "b^-1"), b)?;
+        self.mul(layouter.namespace(|| "a * b^-1"), a, &b_inv)
+    }
+
+    /// Returns `a` if `bit` is one and `b` if it is zero. `bit` is boolean
+    /// constrained.
+    pub fn select(
+        &self,
+        mut layouter: impl Layouter<F>,
+        bit: &AssignedCell<F, F>,
+        a: &AssignedFq<F>,
+        b: &AssignedFq<F>,
+    ) -> Result<AssignedFq<F>, Error> {
+        let config = &self.config;
+        let value = bit
+            .value()
+            .zip(a.value.as_ref().zip(b.value.as_ref()))
+            .map(|(bit, (a, b))| if *bit == F::ONE { a.clone() } else { b.clone() });
+
+        let limbs = layouter.assign_region(
+            || "fq select",
+            |mut region| {
+                config.s_select.enable(&mut region, 0)?;
+                bit.copy_advice(|| "bit", &mut region, config.advice[0], 1)?;
+                (0..NUM_LIMBS)
+                    .map(|i| {
+                        a.limbs[i].copy_advice(|| "a", &mut region, config.advice[i], 0)?;
+                        b.limbs[i].copy_advice(|| "b", &mut region, config.advice[4 + i], 0)?;
+                        let r = bit
+                            .value()
+                            .zip(a.limbs[i].value().zip(b.limbs[i].value()))
+                            .map(|(bit, (a, b))| if *bit == F::ONE { *a } else { *b });
+                        region.assign_advice(|| "r", config.advice[1 + i], 1, || r)
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            },
+        )?;
+
+        Ok(AssignedFq {
+            limbs: limbs.try_into().unwrap(),
+            value,
+        })
+    }
+
+    /// Witnesses `bits` as boolean-constrained native cells.
+    pub fn assign_bits(
+        &self,
+        mut layouter: impl Layouter<F>,
+        bits: Value<Vec<bool>>,
+        num_bits: usize,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let config = &self.config;
+        let width = config.advice.len();
+        let bits = bits.transpose_vec(num_bits);
+
+        layouter.assign_region(
+            || "assign bits",
+            |mut region| {
+                let mut cells = Vec::with_capacity(num_bits);
+                for row in 0..num_bits.div_ceil(width) {
+                    config.s_bool.enable(&mut region, row)?;
+                    for (i, column) in config.advice.iter().enumerate() {
+                        let index = row * width + i;
+                        if index < num_bits {
+                            let bit = bits[index].map(|b| F::from(b as u64));
+                            cells.push(region.assign_advice(|| "bit", *column, row, || bit)?);
+                        } else {
+                            region.assign_advice(
+                                || "padding",
+                                *column,
+                                row,
+                                || Value::known(F::ZERO),
+                            )?;
+                        }
+                    }
+                }
+                Ok(cells)
+            },
+        )
+    }
+
+    /// Returns `sum(coeff * a * b) + constant (mod q)` using a single reduction,
+    /// at the cost of one row per term.
+    pub fn sum_products(
+        &self,
+        layouter: impl Layouter<F>,
+        terms: &[Term<F>],
+        constant: i64,
+    ) -> Result<AssignedFq<F>, Error> {
+        self.assign_sum(layouter, terms, constant, false)
+            .map(|r| r.unwrap())
+    }
+
+    /// Constrains `sum(coeff * a * b) + constant = 0 (mod q)`.
+    pub fn assert_sum_zero(
+        &self,
+        layouter: impl Layouter<F>,
+        terms: &[Term<F>],
+        constant: i64,
+    ) -> Result<(), Error> {
+        self.assign_sum(layouter, terms, constant, true).map(|_| ())
+    }
+
+    /// Lays out `lhs = quotient * q + r` for `op`. With `assert_zero` the result
+    /// is pinned to zero instead of witnessed, and `None` is returned.
+    fn assign_op(
+        &self,
+        mut layouter: impl Layouter<F>,
+        op: Op,
+        a: &AssignedFq<F>,
+        b: &AssignedFq<F>,
+        assert_zero: bool,
+    ) -> Result<Option<AssignedFq<F>>, Error> {
+        let config = &self.config;
+        let q = modulus();
+
+        let witness = a.value.as_ref().zip(b.value.as_ref()).map(|(a, b)| {
+            let lhs = match op {
+                Op::Add => a + b,
+                Op::Sub => a + q * SUB_OFFSET - b,
+                Op::Mul => a * b,
+            };
+            let r = if assert_zero {
+                BigUint::zero()
+            } else {
+                &lhs % q
+            };
+            let quotient = (&lhs - &r) / q;
+            let a_limbs = to_limbs(a, NUM_LIMBS);
+            let b_limbs = to_limbs(b, NUM_LIMBS);
+            let carries = compute_carries(op_columns(op, &a_limbs, &b_limbs), &quotient, &r);
+            (
+                to_limbs(&quotient, NUM_QUOTIENT_LIMBS),
+                to_limbs(&r, NUM_LIMBS),
+                carries,
+                r,
+            )
+        });
+        let value = witness.as_ref().map(|w| w.3.clone());
+
+        // Additions and subtractions only ever need the lowest quotient limb,
+        // which also shortens the carry chain to three entries.
+        let (used_quotient, used_carries) = match op {
+            Op::Mul => (NUM_QUOTIENT_LIMBS, NUM_CARRIES),
+            Op::Add | Op::Sub => (1, NUM_LIMBS - 1),
+        };
+
+        let (quotient, r, carries) = layouter.assign_region(
+            || format!("fq {:?}", op),
+            |mut region| {
+                match op {
+                    Op::Add => config.s_add.enable(&mut region, 0)?,
+                    Op::Sub => config.s_sub.enable(&mut region, 0)?,
+                    Op::Mul => config.s_mul.enable(&mut region, 0)?,
+                }
+                for i in 0..NUM_LIMBS {
+                    a.limbs[i].copy_advice(|| "a", &mut region, config.advice[i], 0)?;
+                    b.limbs[i].copy_advice(|| "b", &mut region, config.advice[4 + i], 0)?;
+                }
+
+                let mut quotient = vec![];
+                for i in 0..NUM_QUOTIENT_LIMBS {
+                    let column = config.advice[i];
+                    if i < used_quotient {
+                        let limb = witness.as_ref().map(|w| big_to_fe(&w.0[i]));
+                        quotient.push(region.assign_advice(|| "quotient", column, 1, || limb)?);
+                    } else {
+                        region.assign_advice_from_constant(|| "quotient", column, 1, F::ZERO)?;
+                    }
+                }
+
+                let r_positions = [(5, 1), (6, 1), (7, 1), (0, 2)];
+                let mut r = vec![];
+                for (i, (column, offset)) in r_positions.into_iter().enumerate() {
+                    let column = config.advice[column];
+                    if assert_zero {
+                        region.assign_advice_from_constant(|| "r", column, offset, F::ZERO)?;
+                    } else {
+                        let limb = witness.as_ref().map(|w| big_to_fe(&w.1[i]));
+                        r.push(region.assign_advice(|| "r", column, offset, || limb)?);
+                    }
+                }
+
+                let mut carries = vec![];
+                for i in 0..NUM_CARRIES {
+                    let column = config.advice[1 + i];
+                    if i < used_carries {
+                        let carry = witness.as_ref().map(|w| big_to_fe(&w.2[i]));
+                        carries.push(region.assign_advice(|| "carry", column, 2, || carry)?);
+                    } else {
+                        region.assign_advice_from_constant(
+                            || "carry",
+                            column,
+                            2,
+                            big_to_fe::<F>(&carry_offset()),
+                        )?;
+                    }
+                }
+
+                Ok((quotient, r, carries))
+            },
+        )?;
+
+        let range = self.range_chip();
+        let limbs: Vec<_> = quotient.into_iter().chain(r.iter().cloned()).collect();
+        range.range_check(layouter.namespace(|| "limbs"), &limbs, LIMB_BITS)?;
+        range.range_check(layouter.namespace(|| "carries"), &carries, CARRY_BITS)?;
+
+        Ok((!assert_zero).then(|| AssignedFq {
+            limbs: r.try_into().unwrap(),
+            value,
+        }))
+    }
+
+    /// Lays out the accumulation of `terms` followed by one reduction:
+    ///
+    /// row i:     a0 a1 a2 a3 b0 b1 b2 b3 | acc_i (coeff_i in a fixed column)
+    /// row n:     k0 k1 k2 k3 k4 r0 r1 r2 | acc_n
+    /// row n + 1: r3 c0 c1 c2 c3 c4 c5 c6
+    ///
+    /// `acc_0` is a multiple of `q` plus `constant`, large enough to keep the
+    /// accumulated integer positive whatever the signs of the terms.
+    fn assign_sum(
+        &self,
+        mut layouter: impl Layouter<F>,
+        terms: &[Term<F>],
+        constant: i64,
+        assert_zero: bool,
+    ) -> Result<Option<AssignedFq<F>>, Error> {
+        assert!(!terms.is_empty());
+        let coeff_sum: u64 = terms.iter().map(|t| t.coeff.unsigned_abs()).sum();
+        assert!(
+            coeff_sum <= MAX_COEFF_SUM,
+            "too many terms in one reduction"
+        );
+
+        let config = &self.config;
+        let q = modulus();
+
+        let negative = terms
+            .iter()
+            .filter(|t| t.coeff < 0)
+            .fold(BigUint::zero(), |acc, t| {
+                let bits = if t.b.is_some() { 2 } else { 1 } * NUM_LIMBS * LIMB_BITS;
+                acc + (BigUint::from(t.coeff.unsigned_abs()) << bits)
+            });
+        let constant = if constant < 0 {
+            q - BigUint::from(constant.unsigned_abs()) % q
+        } else {
+            BigUint::from(constant as u64)
+        };
+        let offset = (negative + q - 1u32) / q * q + constant;
+        let mut offset_columns = to_limbs(&offset, NUM_PRODUCT_COLUMNS - 1);
+        offset_columns.push(&offset >> ((NUM_PRODUCT_COLUMNS - 1) * LIMB_BITS));
+
+        let operands: Value<Vec<(BigUint, Option<BigUint>)>> = terms
+            .iter()
+            .map(|t| match t.b {
+                Some(b) => {
+                    t.a.value
+                        .clone()
+                        .zip(b.value.clone())
+                        .map(|(a, b)| (a, Some(b)))
+                }
+                None => t.a.value.clone().map(|a| (a, None)),
+            })
+            .collect();
+
+        let witness = operands.map(|operands| {
+            let mut acc: Vec<BigInt> = offset_columns.iter().cloned().map(BigInt::from).collect();
+            let mut rows = vec![acc.clone()];
+            for (term, (a, b)) in terms.iter().zip(operands) {
+                let a = to_limbs(&a, NUM_LIMBS);
+                let coeff = BigInt::from(term.coeff);
+                match b {
+                    Some(b) => {
+                        let b = to_limbs(&b, NUM_LIMBS);
+                        for i in 0..NUM_LIMBS {
+                            for j in 0..NUM_LIMBS {
+                                acc[i + j] += &coeff * BigInt::from(&a[i] * &b[j]);
+                            }
+                        }
+                    }
+                    None => {
+                        for i in 0..NUM_LIMBS {
+                            acc[i] += &coeff * BigInt::from(a[i].clone());
+                        }
+                    }
+                }
+                rows.push(acc.clone());
+            }
+
+            let lhs = acc
+                .iter()
+                .rev()
+                .fold(BigInt::zero(), |sum, column| (sum << LIMB_BITS) + column)
+                .to_biguint()
+                .expect("accumulated value is positive");
+            let r = if assert_zero {
+                BigUint::zero()
+            } else {
+                &lhs % q
+            };
+            let quotient = (&lhs - &r) / q;
+            let carries = compute_carries(acc, &quotient, &r);
+            (
+                rows,
+                to_limbs(&quotient, NUM_QUOTIENT_LIMBS),
+                to_limbs(&r, NUM_LIMBS),
+                carries,
+                r,
+            )
+        });
+        let value = witness.as_ref().map(|w| w.4.clone());
+        let n = terms.len();
+
+        let (quotient, r, carries) = layouter.assign_region(
+            || "fq sum of products",
+            |mut region| {
+                for (k, column) in config.acc.iter().enumerate() {
+                    region.assign_advice_from_constant(
+                        || "offset",
+                        *column,
+                        0,
+                        big_to_fe::<F>(&offset_columns[k]),
+                    )?;
+                }
+
+                for (row, term) in terms.iter().enumerate() {
+                    let coeff = F::from(term.coeff.unsigned_abs());
+                    let coeff = if term.coeff < 0 { -coeff } else { coeff };
+                    region.assign_fixed(|| "coeff", config.coeff, row, || Value::known(coeff))?;
+                    for i in 0..NUM_LIMBS {
+                        term.a.limbs[i].copy_advice(|| "a", &mut region, config.advice[i], row)?;
+                    }
+                    match term.b {
+                        Some(b) => {
+                            config.s_product.enable(&mut region, row)?;
+                            for i in 0..NUM_LIMBS {
+                                b.limbs[i].copy_advice(
+                                    || "b",
+                                    &mut region,
+                                    config.advice[4 + i],
+                                    row,
+                                )?;
+                            }
+                        }
+                        None => config.s_linear.enable(&mut region, row)?,
+                    }
+                    for (k, column) in config.acc.iter().enumerate() {
+                        let acc = witness
+                            .as_ref()
+                            .map(|w| bigint_to_fe::<F>(&w.0[row + 1][k]));
+                        region.assign_advice(|| "acc", *column, row + 1, || acc)?;
+                    }
+                }
+
+                config.s_reduce.enable(&mut region, n)?;
+                let mut quotient = vec![];
+                for i in 0..NUM_QUOTIENT_LIMBS {
+                    let limb = witness.as_ref().map(|w| big_to_fe(&w.1[i]));
+                    quotient.push(region.assign_advice(
+                        || "quotient",
+                        config.advice[i],
+                        n,
+                        || limb,
+                    )?);
+                }
+
+                let r_positions = [(5, n), (6, n), (7, n), (0, n + 1)];
+                let mut r = vec![];
+                for (i, (column, offset)) in r_positions.into_iter().enumerate() {
+                    let column = config.advice[column];
+                    if assert_zero {
+                        region.assign_advice_from_constant(|| "r", column, offset, F::ZERO)?;
+                    } else {
+                        let limb = witness.as_ref().map(|w| big_to_fe(&w.2[i]));
+                        r.push(region.assign_advice(|| "r", column, offset, || limb)?);
+                    }
+                }
+
+                let mut carries = vec![];
+                for i in 0..NUM_CARRIES {
+                    let carry = witness.as_ref().map(|w| big_to_fe(&w.3[i]));
+                    carries.push(region.assign_advice(
+                        || "carry",
+                        config.advice[1 + i],
+                        n + 1,
+                        || carry,
+                    )?);
+                }
+
+                Ok((quotient, r, carries))
+            },
+        )?;
+
+        let range = self.range_chip();
+        let limbs: Vec<_> = quotient.into_iter().chain(r.iter().cloned()).collect();
+        range.range_check(layouter.namespace(|| "limbs"), &limbs, LIMB_BITS)?;
+        range.range_check(layouter.namespace(|| "carries"), &carries, CARRY_BITS)?;
+
+        Ok((!assert_zero).then(|| AssignedFq {
+            limbs: r.try_into().unwrap(),
+            value,
+        }))
+    }
+}
+
+fn carry_offset() -> BigUint {
+    BigUint::one() << (CARRY_BITS - 1)
+}
+
+/// Column `k` of `lhs - quotient * q - r` must equal `c_k * 2^96 - c_{k-1}`,
+/// with `c_{-1} = 0` and the last carry forced to zero.
+fn carry_constraints<F: PrimeField>(
+    lhs: Vec<Expression<F>>,
+    quotient: &[Expression<F>],
+    r: &[Expression<F>],
+    carries: &[Expression<F>],
+    q_limbs: &[F],
+) -> Vec<Expression<F>> {
+    let base = Expression::Constant(big_to_fe(&(BigUint::one() << LIMB_BITS)));
+    let offset = Expression::Constant(big_to_fe(&carry_offset()));
+    let carry = |k: usize| carries[k].clone() - offset.clone();
+
+    lhs.into_iter()
+        .enumerate()
+        .map(|(k, mut column)| {
+            for (i, quotient) in quotient.iter().enumerate() {
+                if k >= i && k - i < NUM_LIMBS {
+                    column = column - quotient.clone() * Expression::Constant(q_limbs[k - i]);
+                }
+            }
+            if k < NUM_LIMBS {
+                column = column - r[k].clone();
+            }
+            if k > 0 {
+                column = column + carry(k - 1);
+            }
+            if k < NUM_CARRIES {
+                column = column - carry(k) * base.clone();
+            }
+            column
+        })
+        .collect()
+}
+
+/// The integer columns `lhs` of `op` before reduction.
+fn op_columns(op: Op, a: &[BigUint], b: &[BigUint]) -> Vec<BigInt> {
+    let q_limbs = to_limbs(modulus(), NUM_LIMBS);
+    let mut columns = vec![BigInt::zero(); NUM_PRODUCT_COLUMNS];
+    for i in 0..NUM_LIMBS {
+        match op {
+            Op::Add => columns[i] += BigInt::from(&a[i] + &b[i]),
+            Op::Sub => {
+                columns[i] +=
+                    BigInt::from(&a[i] + &q_limbs[i] * SUB_OFFSET) - BigInt::from(b[i].clone())
+            }
+            Op::Mul => {
+                for j in 0..NUM_LIMBS {
+                    columns[i + j] += BigInt::from(&a[i] * &b[j]);
+                }
+            }
+        }
+    }
+    columns
+}
+
+/// Native counterpart of `carry_constraints`, returning the shifted carry
+/// cells. If `lhs != quotient * q + r` the chain does not close and the gate
+/// rejects the witness.
+fn compute_carries(lhs: Vec<BigInt>, quotient: &BigUint, r: &BigUint) -> Vec<BigUint> {
+    let num_columns = NUM_QUOTIENT_LIMBS + NUM_LIMBS - 1;
+    let q_limbs = to_limbs(modulus(), NUM_LIMBS);
+    let quotient = to_limbs(quotient, NUM_QUOTIENT_LIMBS);
+    let r = to_limbs(r, NUM_LIMBS);
+
+    let mut columns = lhs;
+    columns.resize(num_columns, BigInt::zero());
+    for (i, quotient) in quotient.iter().enumerate() {
+        for (j, q) in q_limbs.iter().enumerate() {
+            columns[i + j] -= BigInt::from(quotient * q);
+        }
+    }
+    for (k, r) in r.iter().enumerate() {
+        columns[k] -= BigInt::from(r.clone());
+    }
+
+    let offset = BigInt::from(carry_offset());
+    let mut carry = BigInt::zero();
+    let mut carries = Vec::with_capacity(NUM_CARRIES);
+    for column in columns.into_iter().take(NUM_CARRIES) {
+        carry = (column + carry) >> LIMB_BITS;
+        // Out of range carries only come from a bad witness; clamp them so the
+        // range check, not the witness generator, reports the failure.
+        let shifted = (&carry + &offset).to_biguint().unwrap_or_default();
+        carries.push(shifted.min((BigUint::one() << CARRY_BITS) - 1u32));
+    }
+    carries
+}
+
+fn fe_to_big<F: PrimeField>(value: &F) -> BigUint {
+    BigUint::from_bytes_le(value.to_repr().as_ref())
+}
+
+fn bigint_to_fe<F: PrimeField>(value: &BigInt) -> F {
+    let magnitude = big_to_fe::<F>(value.magnitude());
+    if value.sign() == num_bigint::Sign::Minus {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
@@ -0,0 +1,435 @@
+use halo2_proofs::{
+    circuit::{Layouter, Value},
+    pasta::group::ff::PrimeField,
+    plonk::{ConstraintSystem, Error},
+};
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+
+use super::fq::{modulus, FqConfig};
+use super::fq2::{
+    fq2_add, fq2_inverse, fq2_mul, fq2_mul_by_nonresidue, fq2_pow, fq2_sub, AssignedFq2, Fq2,
+    Fq2Chip, Fq2Ref, Fq2Sum,
+};
+
+/// A native `Fq6 = Fq2[v] / (v^3 - xi)` element `c0 + c1 * v + c2 * v^2`.
+pub type Fq6 = [Fq2; 3];
+/// A native `Fq12 = Fq6[w] / (w^2 - v)` element, stored as its coefficients
+/// over Fq2 in `w^0, ..., w^5`.
+pub type Fq12 = [Fq2; 6];
+
+fn fq2_zero() -> Fq2 {
+    [BigUint::zero(), BigUint::zero()]
+}
+
+pub fn fq6_mul(a: &Fq6, b: &Fq6) -> Fq6 {
+    let xi = |x: Fq2| fq2_mul_by_nonresidue(&x);
+    let c0 = fq2_add(
+        &fq2_mul(&a[0], &b[0]),
+        &xi(fq2_add(&fq2_mul(&a[1], &b[2]), &fq2_mul(&a[2], &b[1]))),
+    );
+    let c1 = fq2_add(
+        &fq2_add(&fq2_mul(&a[0], &b[1]), &fq2_mul(&a[1], &b[0])),
+        &xi(fq2_mul(&a[2], &b[2])),
+    );
+    let c2 = fq2_add(
+        &fq2_add(&fq2_mul(&a[0], &b[2]), &fq2_mul(&a[1], &b[1])),
+        &fq2_mul(&a[2], &b[0]),
+    );
+    [c0, c1, c2]
+}
+
+pub fn fq6_inverse(a: &Fq6) -> Fq6 {
+    let xi = |x: Fq2| fq2_mul_by_nonresidue(&x);
+    let t0 = fq2_sub(&fq2_mul(&a[0], &a[0]), &xi(fq2_mul(&a[1], &a[2])));
+    let t1 = fq2_sub(&xi(fq2_mul(&a[2], &a[2])), &fq2_mul(&a[0], &a[1]));
+    let t2 = fq2_sub(&fq2_mul(&a[1], &a[1]), &fq2_mul(&a[0], &a[2]));
+    let norm = fq2_add(
+        &fq2_mul(&a[0], &t0),
+        &xi(fq2_add(&fq2_mul(&a[2], &t1), &fq2_mul(&a[1], &t2))),
+    );
+    let norm_inv = fq2_inverse(&norm);
+    [
+        fq2_mul(&t0, &norm_inv),
+        fq2_mul(&t1, &norm_inv),
+        fq2_mul(&t2, &norm_inv),
+    ]
+}
+
+/// `(c0 + c1 * w)^-1 = (c0 - c1 * w) / (c0^2 - c1^2 * v)`.
+pub fn fq12_inverse(a: &Fq12) -> Fq12 {
+    let c0 = [a[0].clone(), a[2].clone(), a[4].clone()];
+    let c1 = [a[1].clone(), a[3].clone(), a[5].clone()];
+    let c1_squared = fq6_mul(&c1, &c1);
+    let c1_squared_v = [
+        fq2_mul_by_nonresidue(&c1_squared[2]),
+        c1_squared[0].clone(),
+        c1_squared[1].clone(),
+    ];
+    let norm = fq6_mul(&c0, &c0);
+    let norm = [0, 1, 2].map(|i| fq2_sub(&norm[i], &c1_squared_v[i]));
+    let norm_inv = fq6_inverse(&norm);
+    let r0 = fq6_mul(&c0, &norm_inv);
+    let r1 = fq6_mul(&c1, &norm_inv).map(|c| fq2_sub(&fq2_zero(), &c));
+    [
+        r0[0].clone(),
+        r1[0].clone(),
+        r0[1].clone(),
+        r1[1].clone(),
+        r0[2].clone(),
+        r1[2].clone(),
+    ]
+}
+
+/// `gamma_i = xi^(i * (q^power - 1) / 6)`, so that the `power`-th Frobenius
+/// map sends `c * w^i` to `frob(c) * gamma_i * w^i`.
+pub fn frobenius_coefficients(power: u32) -> [Fq2; 6] {
+    let q = modulus();
+    let xi = [BigUint::one(), BigUint::one()];
+    let exponent = (q.pow(power) - 1u32) / 6u32;
+    let gamma = fq2_pow(&xi, &exponent);
+    let mut coefficients = vec![[BigUint::one(), BigUint::zero()]];
+    for i in 1..6 {
+        coefficients.push(fq2_mul(&coefficients[i - 1], &gamma));
+    }
+    coefficients.try_into().unwrap()
+}
+
+/// An emulated `c0 + c1 * v + c2 * v^2`.
+#[derive(Clone, Debug)]
+pub struct AssignedFq6<F: PrimeField> {
+    pub c0: AssignedFq2<F>,
+    pub c1: AssignedFq2<F>,
+    pub c2: AssignedFq2<F>,
+}
+
+/// An emulated `c0 + c1 * w`.
+#[derive(Clone, Debug)]
+pub struct AssignedFq12<F: PrimeField> {
+    pub c0: AssignedFq6<F>,
+    pub c1: AssignedFq6<F>,
+}
+
+/// Coefficients of an Fq12 operand in `w^0, ..., w^5`; sparse operands such
+/// as line functions leave some components as `None`.
+pub type Fq12Ref<'a, F> = [Fq2Ref<'a, F>; 6];
+
+impl<F: PrimeField> AssignedFq12<F> {
+    /// Coefficients in `w^0, ..., w^5`, using `v = w^2`.
+    pub fn coeffs(&self) -> [&AssignedFq2<F>; 6] {
+        [
+            &self.c0.c0,
+            &self.c1.c0,
+            &self.c0.c1,
+            &self.c1.c1,
+            &self.c0.c2,
+            &self.c1.c2,
+        ]
+    }
+
+    pub fn from_coeffs(coeffs: [AssignedFq2<F>; 6]) -> Self {
+        let [w0, w1, w2, w3, w4, w5] = coeffs;
+        AssignedFq12 {
+            c0: AssignedFq6 {
+                c0: w0,
+                c1: w2,
+                c2: w4,
+            },
+            c1: AssignedFq6 {
+                c0: w1,
+                c1: w3,
+                c2: w5,
+            },
+        }
+    }
+
+    pub fn as_ref(&self) -> Fq12Ref<'_, F> {
+        self.coeffs().map(|c| c.as_ref())
+    }
+
+    pub fn value(&self) -> Value<Fq12> {
+        self.coeffs()
+            .iter()
+            .map(|c| c.value())
+            .collect::<Value<Vec<_>>>()
+            .map(|coeffs| coeffs.try_into().unwrap())
+    }
+}
+
+/// The coefficients of `a * b` in `w^0, ..., w^5`, reducing `w^6 = xi`.
+fn product_sums<'a, F: PrimeField>(a: Fq12Ref<'a, F>, b: Fq12Ref<'a, F>) -> Vec<Fq2Sum<'a, F>> {
+    (0..6)
+        .map(|k| {
+            let mut sum = Fq2Sum::new();
+            for (i, a) in a.iter().enumerate() {
+                let j = (k + 6 - i) % 6;
+                sum.product(1, *a, b[j], i + j >= 6);
+            }
+            sum
+        })
+        .collect()
+}
+
+/// One output of the cyclotomic squaring: `(x, y)` is squared in
+/// `Fq4 = Fq2[y] / (y^2 - xi)` to `x^2 + xi * y^2` (or `2xy` for the `cross`
+/// half), scaled by 3, and `linear` is added.
+fn fq4_square<'a, F: PrimeField>(
+    x: Fq2Ref<'a, F>,
+    y: Fq2Ref<'a, F>,
+    cross: bool,
+    linear: (i64, Fq2Ref<'a, F>),
+) -> Fq2Sum<'a, F> {
+    let mut sum = Fq2Sum::new();
+    if cross {
+        sum.product(6, x, y, false);
+    } else {
+        sum.product(3, x, x, false);
+        sum.product(3, y, y, true);
+    }
+    sum.linear(linear.0, linear.1, false);
+    sum
+}
+
+/// Arithmetic over the BLS12-381 `Fq12` tower on top of `Fq2Chip`.
+///
+/// Products are computed directly on the coefficients over Fq2 in `w`, with
+/// `w^6 = xi`, so each output coefficient is a single `sum_products`
+/// reduction per component instead of a chain of Fq2 and Fq6 operations.
+#[derive(Clone, Debug)]
+pub struct Fq12Chip<F: PrimeField> {
+    fq2: Fq2Chip<F>,
+}
+
+impl<F: PrimeField> Fq12Chip<F> {
+    pub fn construct(config: FqConfig) -> Self {
+        Fq12Chip {
+            fq2: Fq2Chip::construct(config),
+        }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> FqConfig {
+        Fq2Chip::configure(meta)
+    }
+
+    pub fn fq2(&self) -> &Fq2Chip<F> {
+        &self.fq2
+    }
+
+    pub fn load_private(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: Value<Fq12>,
+    ) -> Result<AssignedFq12<F>, Error> {
+        let coeffs = value.transpose_vec(6);
+        let coeffs = coeffs
+            .into_iter()
+            .enumerate()
+            .map(|(i, c)| {
+                self.fq2
+                    .load_private(layouter.namespace(|| format!("w^{}", i)), c)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(AssignedFq12::from_coeffs(coeffs.try_into().unwrap()))
+    }
+
+    pub fn load_constant(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: &Fq12,
+    ) -> Result<AssignedFq12<F>, Error> {
+        let coeffs = value
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                self.fq2
+                    .load_constant(layouter.namespace(|| format!("w^{}", i)), c)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(AssignedFq12::from_coeffs(coeffs.try_into().unwrap()))
+    }
+
+    pub fn load_one(&self, layouter: impl Layouter<F>) -> Result<AssignedFq12<F>, Error> {
+        let mut one: Fq12 = [(); 6].map(|_| fq2_zero());
+        one[0][0] = BigUint::one();
+        self.load_constant(layouter, &one)
+    }
+
+    pub fn mul(
+        &self,
+        layouter: impl Layouter<F>,
+        a: &AssignedFq12<F>,
+        b: &AssignedFq12<F>,
+    ) -> Result<AssignedFq12<F>, Error> {
+        self.mul_sparse(layouter, a.as_ref(), b.as_ref())
+    }
+
+    /// Multiplies two possibly sparse operands, skipping zero components.
+    pub fn mul_sparse(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Fq12Ref<F>,
+        b: Fq12Ref<F>,
+    ) -> Result<AssignedFq12<F>, Error> {
+        self.finish(layouter, product_sums(a, b))
+    }
+
+    pub fn square(
+        &self,
+        layouter: impl Layouter<F>,
+        a: &AssignedFq12<F>,
+    ) -> Result<AssignedFq12<F>, Error> {
+        let a = a.as_ref();
+        let sums = (0..6)
+            .map(|k| {
+                let mut sum = Fq2Sum::new();
+                for (i, a_i) in a.iter().enumerate() {
+                    for (j, a_j) in a.iter().enumerate().skip(i) {
+                        if (i + j) % 6 == k {
+                            let coeff = if i == j { 1 } else { 2 };
+                            sum.product(coeff, *a_i, *a_j, i + j >= 6);
+                        }
+                    }
+                }
+                sum
+            })
+            .collect();
+        self.finish(layouter, sums)
+    }
+
+    /// Squaring for elements of the cyclotomic subgroup, following Granger and
+    /// Scott, "Faster Squaring in the Cyclotomic Subgroup of Sixth Degree
+    /// Extensions".
+    pub fn cyclotomic_square(
+        &self,
+        layouter: impl Layouter<F>,
+        a: &AssignedFq12<F>,
+    ) -> Result<AssignedFq12<F>, Error> {
+        let [w0, w1, w2, w3, w4, w5] = a.as_ref();
+        let z0 = fq4_square(w0, w3, false, (-2, w0));
+        let z1 = fq4_square(w0, w3, true, (2, w3));
+        let z4 = fq4_square(w1, w4, false, (-2, w2));
+        let z5 = fq4_square(w1, w4, true, (2, w5));
+        let z3 = fq4_square(w2, w5, false, (-2, w4));
+        let mut z2 = Fq2Sum::new();
+        z2.product(6, w2, w5, true);
+        z2.linear(2, w1, false);
+
+        self.finish(layouter, vec![z0, z2, z4, z1, z3, z5])
+    }
+
+    /// `c0 - c1 * w`, which is `a^(q^6)` and, in the cyclotomic subgroup,
+    /// `a^-1`.
+    pub fn conjugate(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &AssignedFq12<F>,
+    ) -> Result<AssignedFq12<F>, Error> {
+        let fq2 = &self.fq2;
+        let c1 = AssignedFq6 {
+            c0: fq2.neg(layouter.namespace(|| "c1.c0"), &a.c1.c0)?,
+            c1: fq2.neg(layouter.namespace(|| "c1.c1"), &a.c1.c1)?,
+            c2: fq2.neg(layouter.namespace(|| "c1.c2"), &a.c1.c2)?,
+        };
+        Ok(AssignedFq12 {
+            c0: a.c0.clone(),
+            c1,
+        })
+    }
+
+    /// Returns `a^(q^power)`.
+    pub fn frobenius_map(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &AssignedFq12<F>,
+        power: u32,
+    ) -> Result<AssignedFq12<F>, Error> {
+        let fq2 = &self.fq2;
+        let gammas = frobenius_coefficients(power);
+        let odd = power % 2 == 1;
+
+        let mut coeffs = vec![];
+        for (i, (c, gamma)) in a.coeffs().into_iter().zip(gammas.iter()).enumerate() {
+            let mut layouter = layouter.namespace(|| format!("w^{}", i));
+            let coeff = if i == 0 {
+                if odd {
+                    fq2.conjugate(layouter.namespace(|| "conjugate"), c)?
+                } else {
+                    c.clone()
+                }
+            } else {
+                let real = gamma[1].is_zero();
+                let gamma = fq2.load_constant(layouter.namespace(|| "gamma"), gamma)?;
+                let mut sum = Fq2Sum::new();
+                if real {
+                    sum.scale(1, [Some(&c.c0), None], &gamma.c0);
+                    sum.scale(if odd { -1 } else { 1 }, [None, Some(&c.c1)], &gamma.c0);
+                } else if odd {
+                    sum.conjugate_product(1, c.as_ref(), gamma.as_ref());
+                } else {
+                    sum.product(1, c.as_ref(), gamma.as_ref(), false);
+                }
+                sum.finish(fq2.fq(), layouter.namespace(|| "gamma * c"), [0, 0])?
+            };
+            coeffs.push(coeff);
+        }
+        Ok(AssignedFq12::from_coeffs(coeffs.try_into().unwrap()))
+    }
+
+    /// Returns `a^-1`, constraining `a * a^-1 = 1`.
+    pub fn invert(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &AssignedFq12<F>,
+    ) -> Result<AssignedFq12<F>, Error> {
+        let inv = self.load_private(
+            layouter.namespace(|| "witness inverse"),
+            a.value().map(|a| fq12_inverse(&a)),
+        )?;
+        for (k, sum) in product_sums(a.as_ref(), inv.as_ref())
+            .into_iter()
+            .enumerate()
+        {
+            let constant = if k == 0 { [-1, 0] } else { [0, 0] };
+            sum.assert_zero(
+                self.fq2.fq(),
+                layouter.namespace(|| format!("a * a^-1 = 1, w^{}", k)),
+                constant,
+            )?;
+        }
+        Ok(inv)
+    }
+
+    /// Constrains `a = 1`.
+    pub fn assert_one(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &AssignedFq12<F>,
+    ) -> Result<(), Error> {
+        let one = self.load_one(layouter.namespace(|| "one"))?;
+        for (i, (a, one)) in a.coeffs().into_iter().zip(one.coeffs()).enumerate() {
+            self.fq2
+                .assert_equal(layouter.namespace(|| format!("w^{}", i)), a, one)?;
+        }
+        Ok(())
+    }
+
+    /// Reduces one `Fq2Sum` per coefficient of `w^0, ..., w^5`.
+    fn finish(
+        &self,
+        mut layouter: impl Layouter<F>,
+        sums: Vec<Fq2Sum<F>>,
+    ) -> Result<AssignedFq12<F>, Error> {
+        let coeffs = sums
+            .iter()
+            .enumerate()
+            .map(|(i, sum)| {
+                sum.finish(
+                    self.fq2.fq(),
+                    layouter.namespace(|| format!("w^{}", i)),
+                    [0, 0],
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(AssignedFq12::from_coeffs(coeffs.try_into().unwrap()))
+    }
+}
@@ -0,0 +1,376 @@
+use halo2_proofs::{
+    circuit::{Layouter, Value},
+    pasta::group::ff::PrimeField,
+    plonk::{Column, ConstraintSystem, Error, Instance},
+};
+use num_bigint::BigUint;
+use num_traits::Zero;
+
+use super::fq::{fq_inverse, modulus, AssignedFq, FqChip, FqConfig, Term, NUM_LIMBS};
+
+/// A native `Fq2 = Fq[u] / (u^2 + 1)` element `c0 + c1 * u`.
+pub type Fq2 = [BigUint; 2];
+
+pub fn fq2_add(a: &Fq2, b: &Fq2) -> Fq2 {
+    let q = modulus();
+    [(&a[0] + &b[0]) % q, (&a[1] + &b[1]) % q]
+}
+
+pub fn fq2_sub(a: &Fq2, b: &Fq2) -> Fq2 {
+    let q = modulus();
+    [
+        (&a[0] % q + q - &b[0] % q) % q,
+        (&a[1] % q + q - &b[1] % q) % q,
+    ]
+}
+
+pub fn fq2_mul(a: &Fq2, b: &Fq2) -> Fq2 {
+    let q = modulus();
+    let re = (&a[0] * &b[0] + q * q - (&a[1] * &b[1]) % (q * q)) % q;
+    let im = (&a[0] * &b[1] + &a[1] * &b[0]) % q;
+    [re, im]
+}
+
+/// Multiplies by the non-residue `xi = u + 1` used to build Fq6.
+pub fn fq2_mul_by_nonresidue(a: &Fq2) -> Fq2 {
+    let q = modulus();
+    [(&a[0] % q + q - &a[1] % q) % q, (&a[0] + &a[1]) % q]
+}
+
+/// Inverse of a nonzero element; zero maps to zero.
+pub fn fq2_inverse(a: &Fq2) -> Fq2 {
+    let q = modulus();
+    let norm = (&a[0] * &a[0] + &a[1] * &a[1]) % q;
+    let norm_inv = fq_inverse(&norm);
+    [(&a[0] * &norm_inv) % q, ((q - &a[1] % q) * &norm_inv) % q]
+}
+
+pub fn fq2_pow(a: &Fq2, exponent: &BigUint) -> Fq2 {
+    let mut result = [BigUint::from(1u32), BigUint::zero()];
+    for i in (0..exponent.bits()).rev() {
+        result = fq2_mul(&result, &result);
+        if exponent.bit(i) {
+            result = fq2_mul(&result, a);
+        }
+    }
+    result
+}
+
+/// An emulated `c0 + c1 * u`.
+#[derive(Clone, Debug)]
+pub struct AssignedFq2<F: PrimeField> {
+    pub c0: AssignedFq<F>,
+    pub c1: AssignedFq<F>,
+}
+
+impl<F: PrimeField> AssignedFq2<F> {
+    pub fn value(&self) -> Value<Fq2> {
+        let q = modulus();
+        self.c0
+            .value
+            .clone()
+            .zip(self.c1.value.clone())
+            .map(|(c0, c1)| [c0 % q, c1 % q])
+    }
+
+    /// Both components, for building a `Fq2Sum` over possibly sparse operands.
+    pub fn as_ref(&self) -> Fq2Ref<'_, F> {
+        [Some(&self.c0), Some(&self.c1)]
+    }
+}
+
+/// Components of an Fq2 operand; `None` stands for a known zero.
+pub type Fq2Ref<'a, F> = [Option<&'a AssignedFq<F>>; 2];
+
+/// Collects `sum(coeff * a * b)` over Fq2 as two Fq `sum_products` calls, one
+/// per component, so a whole Fq2 expression costs two reductions.
+#[derive(Debug)]
+pub struct Fq2Sum<'a, F: PrimeField> {
+    c0: Vec<Term<'a, F>>,
+    c1: Vec<Term<'a, F>>,
+}
+
+impl<'a, F: PrimeField> Default for Fq2Sum<'a, F> {
+    fn default() -> Self {
+        Fq2Sum {
+            c0: vec![],
+            c1: vec![],
+        }
+    }
+}
+
+impl<'a, F: PrimeField> Fq2Sum<'a, F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `coeff * a * b`, times `xi = u + 1` if `times_xi` is set.
+    pub fn product(&mut self, coeff: i64, a: Fq2Ref<'a, F>, b: Fq2Ref<'a, F>, times_xi: bool) {
+        let re = [(coeff, a[0], b[0]), (-coeff, a[1], b[1])];
+        let im = [(coeff, a[0], b[1]), (coeff, a[1], b[0])];
+        self.push_products(re, im, times_xi);
+    }
+
+    /// Adds `coeff * conj(a) * b`.
+    pub fn conjugate_product(&mut self, coeff: i64, a: Fq2Ref<'a, F>, b: Fq2Ref<'a, F>) {
+        let re = [(coeff, a[0], b[0]), (coeff, a[1], b[1])];
+        let im = [(coeff, a[0], b[1]), (-coeff, a[1], b[0])];
+        self.push_products(re, im, false);
+    }
+
+    /// Adds `coeff * a * s` for an Fq element `s`.
+    pub fn scale(&mut self, coeff: i64, a: Fq2Ref<'a, F>, s: &'a AssignedFq<F>) {
+        let re = [(coeff, a[0], Some(s)), (0, None, None)];
+        let im = [(coeff, a[1], Some(s)), (0, None, None)];
+        self.push_products(re, im, false);
+    }
+
+    /// Adds `coeff * a`, times `xi` if `times_xi` is set.
+    pub fn linear(&mut self, coeff: i64, a: Fq2Ref<'a, F>, times_xi: bool) {
+        let (re, im) = if times_xi {
+            (
+                [(coeff, a[0]), (-coeff, a[1])],
+                [(coeff, a[0]), (coeff, a[1])],
+            )
+        } else {
+            ([(coeff, a[0]), (0, None)], [(coeff, a[1]), (0, None)])
+        };
+        for (terms, parts) in [(&mut self.c0, re), (&mut self.c1, im)] {
+            for (coeff, a) in parts {
+                if let Some(a) = a {
+                    if coeff != 0 {
+                        terms.push(Term::linear(coeff, a));
+                    }
+                }
+            }
+        }
+    }
+
+    /// `re` and `im` are the terms of `a * b`; with `times_xi` they are mapped
+    /// through `(re + im * u) * (1 + u) = (re - im) + (re + im) * u`.
+    fn push_products(
+        &mut self,
+        re: [ProductPart<'a, F>; 2],
+        im: [ProductPart<'a, F>; 2],
+        times_xi: bool,
+    ) {
+        if times_xi {
+            push_parts(&mut self.c0, 1, &re);
+            push_parts(&mut self.c0, -1, &im);
+            push_parts(&mut self.c1, 1, &re);
+            push_parts(&mut self.c1, 1, &im);
+        } else {
+            push_parts(&mut self.c0, 1, &re);
+            push_parts(&mut self.c1, 1, &im);
+        }
+    }
+
+    /// Reduces the collected sum, plus `constant`.
+    pub fn finish(
+        &self,
+        fq: &FqChip<F>,
+        mut layouter: impl Layouter<F>,
+        constant: [i64; 2],
+    ) -> Result<AssignedFq2<F>, Error> {
+        let mut reduce = |name: &'static str, terms: &[Term<'a, F>], constant: i64| {
+            if terms.is_empty() {
+                let magnitude = BigUint::from(constant.unsigned_abs());
+                let constant = if constant < 0 {
+                    modulus() - magnitude
+                } else {
+                    magnitude
+                };
+                fq.load_constant(layouter.namespace(|| name), &constant)
+            } else {
+                fq.sum_products(layouter.namespace(|| name), terms, constant)
+            }
+        };
+        let c0 = reduce("c0", &self.c0, constant[0])?;
+        let c1 = reduce("c1", &self.c1, constant[1])?;
+        Ok(AssignedFq2 { c0, c1 })
+    }
+
+    /// Constrains the collected sum plus `constant` to zero.
+    pub fn assert_zero(
+        &self,
+        fq: &FqChip<F>,
+        mut layouter: impl Layouter<F>,
+        constant: [i64; 2],
+    ) -> Result<(), Error> {
+        fq.assert_sum_zero(layouter.namespace(|| "c0"), &self.c0, constant[0])?;
+        fq.assert_sum_zero(layouter.namespace(|| "c1"), &self.c1, constant[1])
+    }
+}
+
+type ProductPart<'a, F> = (i64, Option<&'a AssignedFq<F>>, Option<&'a AssignedFq<F>>);
+
+/// Pushes `sign * coeff * a * b` for every part with both operands present.
+fn push_parts<'a, F: PrimeField>(
+    terms: &mut Vec<Term<'a, F>>,
+    sign: i64,
+    parts: &[ProductPart<'a, F>],
+) {
+    for (coeff, a, b) in parts {
+        if let (Some(a), Some(b)) = (a, b) {
+            if *coeff != 0 {
+                terms.push(Term::product(sign * coeff, a, b));
+            }
+        }
+    }
+}
+
+/// Arithmetic over `Fq2 = Fq[u] / (u^2 + 1)` on top of `FqChip`.
+#[derive(Clone, Debug)]
+pub struct Fq2Chip<F: PrimeField> {
+    fq: FqChip<F>,
+}
+
+impl<F: PrimeField> Fq2Chip<F> {
+    pub fn construct(config: FqConfig) -> Self {
+        Fq2Chip {
+            fq: FqChip::construct(config),
+        }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> FqConfig {
+        FqChip::configure(meta)
+    }
+
+    pub fn fq(&self) -> &FqChip<F> {
+        &self.fq
+    }
+
+    pub fn load_private(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: Value<Fq2>,
+    ) -> Result<AssignedFq2<F>, Error> {
+        let (c0, c1) = value.map(|[c0, c1]| (c0, c1)).unzip();
+        let c0 = self.fq.load_private(layouter.namespace(|| "c0"), c0)?;
+        let c1 = self.fq.load_private(layouter.namespace(|| "c1"), c1)?;
+        Ok(AssignedFq2 { c0, c1 })
+    }
+
+    pub fn load_constant(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: &Fq2,
+    ) -> Result<AssignedFq2<F>, Error> {
+        let c0 = self
+            .fq
+            .load_constant(layouter.namespace(|| "c0"), &value[0])?;
+        let c1 = self
+            .fq
+            .load_constant(layouter.namespace(|| "c1"), &value[1])?;
+        Ok(AssignedFq2 { c0, c1 })
+    }
+
+    /// Loads an element whose limbs sit in `2 * NUM_LIMBS` instance rows
+    /// starting at `row`, `c0` first.
+    pub fn load_instance(
+        &self,
+        mut layouter: impl Layouter<F>,
+        instance: Column<Instance>,
+        row: usize,
+    ) -> Result<AssignedFq2<F>, Error> {
+        let c0 = self
+            .fq
+            .load_instance(layouter.namespace(|| "c0"), instance, row)?;
+        let c1 = self
+            .fq
+            .load_instance(layouter.namespace(|| "c1"), instance, row + NUM_LIMBS)?;
+        Ok(AssignedFq2 { c0, c1 })
+    }
+
+    pub fn add(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &AssignedFq2<F>,
+        b: &AssignedFq2<F>,
+    ) -> Result<AssignedFq2<F>, Error> {
+        let c0 = self.fq.add(layouter.namespace(|| "c0"), &a.c0, &b.c0)?;
+        let c1 = self.fq.add(layouter.namespace(|| "c1"), &a.c1, &b.c1)?;
+        Ok(AssignedFq2 { c0, c1 })
+    }
+
+    pub fn sub(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &AssignedFq2<F>,
+        b: &AssignedFq2<F>,
+    ) -> Result<AssignedFq2<F>, Error> {
+        let c0 = self.fq.sub(layouter.namespace(|| "c0"), &a.c0, &b.c0)?;
+        let c1 = self.fq.sub(layouter.namespace(|| "c1"), &a.c1, &b.c1)?;
+        Ok(AssignedFq2 { c0, c1 })
+    }
+
+    pub fn neg(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &AssignedFq2<F>,
+    ) -> Result<AssignedFq2<F>, Error> {
+        let c0 = self.fq.neg(layouter.namespace(|| "c0"), &a.c0)?;
+        let c1 = self.fq.neg(layouter.namespace(|| "c1"), &a.c1)?;
+        Ok(AssignedFq2 { c0, c1 })
+    }
+
+    pub fn conjugate(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &AssignedFq2<F>,
+    ) -> Result<AssignedFq2<F>, Error> {
+        let c1 = self.fq.neg(layouter.namespace(|| "c1"), &a.c1)?;
+        Ok(AssignedFq2 {
+            c0: a.c0.clone(),
+            c1,
+        })
+    }
+
+    pub fn mul(
+        &self,
+        layouter: impl Layouter<F>,
+        a: &AssignedFq2<F>,
+        b: &AssignedFq2<F>,
+    ) -> Result<AssignedFq2<F>, Error> {
+        let mut sum = Fq2Sum::new();
+        sum.product(1, a.as_ref(), b.as_ref(), false);
+        sum.finish(&self.fq, layouter, [0, 0])
+    }
+
+    pub fn square(
+        &self,
+        layouter: impl Layouter<F>,
+        a: &AssignedFq2<F>,
+    ) -> Result<AssignedFq2<F>, Error> {
+        self.mul(layouter, a, a)
+    }
+
+    /// Constrains `a = b (mod q)` componentwise.
+    pub fn assert_equal(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &AssignedFq2<F>,
+        b: &AssignedFq2<F>,
+    ) -> Result<(), Error> {
+        self.fq
+            .assert_equal(layouter.namespace(|| "c0"), &a.c0, &b.c0)?;
+        self.fq
+            .assert_equal(layouter.namespace(|| "c1"), &a.c1, &b.c1)
+    }
+
+    /// Returns `a^-1`, constraining `a != 0` on the way.
+    pub fn invert(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &AssignedFq2<F>,
+    ) -> Result<AssignedFq2<F>, Error> {
+        let inv = self.load_private(
+            layouter.namespace(|| "witness inverse"),
+            a.value().map(|a| fq2_inverse(&a)),
+        )?;
+        let mut sum = Fq2Sum::new();
+        sum.product(1, a.as_ref(), inv.as_ref(), false);
+        sum.assert_zero(&self.fq, layouter.namespace(|| "a * a^-1 = 1"), [-1, 0])?;
+        Ok(inv)
+    }
+}
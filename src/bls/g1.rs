@@ -0,0 +1,273 @@
+use bls12_381::{G1Affine, G1Projective, Scalar};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    pasta::group::ff::PrimeField,
+    plonk::{Column, ConstraintSystem, Error, Instance},
+};
+use num_bigint::BigUint;
+
+use super::fq::{AssignedFq, FqChip, FqConfig, NUM_LIMBS};
+use super::g1_coordinates;
+
+/// Number of bits used to represent a BLS12-381 scalar.
+pub const SCALAR_BITS: usize = 255;
+
+/// An affine G1 point with emulated coordinates. The point at infinity has no
+/// representation.
+#[derive(Clone, Debug)]
+pub struct AssignedG1<F: PrimeField> {
+    pub x: AssignedFq<F>,
+    pub y: AssignedFq<F>,
+}
+
+/// G1 arithmetic on top of `FqChip`. Additions use the incomplete formula and
+/// constrain `x_p != x_q`, so exceptional inputs fail to verify instead of
+/// producing a wrong point.
+#[derive(Clone, Debug)]
+pub struct G1Chip<F: PrimeField> {
+    fq: FqChip<F>,
+}
+
+impl<F: PrimeField> G1Chip<F> {
+    pub fn construct(config: FqConfig) -> Self {
+        G1Chip {
+            fq: FqChip::construct(config),
+        }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> FqConfig {
+        FqChip::configure(meta)
+    }
+
+    pub fn fq(&self) -> &FqChip<F> {
+        &self.fq
+    }
+
+    pub fn load_private(
+        &self,
+        mut layouter: impl Layouter<F>,
+        point: Value<G1Affine>,
+    ) -> Result<AssignedG1<F>, Error> {
+        let coordinates = point.map(|p| g1_coordinates(&p));
+        let x = self.fq.load_private(
+            layouter.namespace(|| "x"),
+            coordinates.as_ref().map(|c| c.0.clone()),
+        )?;
+        let y = self.fq.load_private(
+            layouter.namespace(|| "y"),
+            coordinates.as_ref().map(|c| c.1.clone()),
+        )?;
+        Ok(AssignedG1 { x, y })
+    }
+
+    pub fn load_constant(
+        &self,
+        mut layouter: impl Layouter<F>,
+        point: &G1Affine,
+    ) -> Result<AssignedG1<F>, Error> {
+        let (x, y) = g1_coordinates(point);
+        let x = self.fq.load_constant(layouter.namespace(|| "x"), &x)?;
+        let y = self.fq.load_constant(layouter.namespace(|| "y"), &y)?;
+        Ok(AssignedG1 { x, y })
+    }
+
+    /// Loads a point whose coordinates sit in `2 * NUM_LIMBS` instance rows
+    /// starting at `row`, `x` first.
+    pub fn load_instance(
+        &self,
+        mut layouter: impl Layouter<F>,
+        instance: Column<Instance>,
+        row: usize,
+    ) -> Result<AssignedG1<F>, Error> {
+        let x = self
+            .fq
+            .load_instance(layouter.namespace(|| "x"), instance, row)?;
+        let y = self
+            .fq
+            .load_instance(layouter.namespace(|| "y"), instance, row + NUM_LIMBS)?;
+        Ok(AssignedG1 { x, y })
+    }
+
+    /// Constrains `y^2 = x^3 + 4`.
+    pub fn assert_on_curve(
+        &self,
+        mut layouter: impl Layouter<F>,
+        p: &AssignedG1<F>,
+    ) -> Result<(), Error> {
+        let fq = &self.fq;
+        let y2 = fq.square(layouter.namespace(|| "y^2"), &p.y)?;
+        let x2 = fq.square(layouter.namespace(|| "x^2"), &p.x)?;
+        let x3 = fq.mul(layouter.namespace(|| "x^3"), &x2, &p.x)?;
+        let b = fq.load_constant(layouter.namespace(|| "b"), &BigUint::from(4u32))?;
+        let rhs = fq.add(layouter.namespace(|| "x^3 + b"), &x3, &b)?;
+        fq.assert_equal(layouter.namespace(|| "y^2 = x^3 + b"), &y2, &rhs)
+    }
+
+    pub fn assert_equal(
+        &self,
+        mut layouter: impl Layouter<F>,
+        p: &AssignedG1<F>,
+        q: &AssignedG1<F>,
+    ) -> Result<(), Error> {
+        self.fq
+            .assert_equal(layouter.namespace(|| "x"), &p.x, &q.x)?;
+        self.fq.assert_equal(layouter.namespace(|| "y"), &p.y, &q.y)
+    }
+
+    pub fn neg(
+        &self,
+        mut layouter: impl Layouter<F>,
+        p: &AssignedG1<F>,
+    ) -> Result<AssignedG1<F>, Error> {
+        let y = self.fq.neg(layouter.namespace(|| "-y"), &p.y)?;
+        Ok(AssignedG1 { x: p.x.clone(), y })
+    }
+
+    /// Returns `p + q` for `p != ±q`.
+    pub fn add(
+        &self,
+        mut layouter: impl Layouter<F>,
+        p: &AssignedG1<F>,
+        q: &AssignedG1<F>,
+    ) -> Result<AssignedG1<F>, Error> {
+        let fq = &self.fq;
+        let dy = fq.sub(layouter.namespace(|| "y_q - y_p"), &q.y, &p.y)?;
+        let dx = fq.sub(layouter.namespace(|| "x_q - x_p"), &q.x, &p.x)?;
+        let lambda = fq.div(layouter.namespace(|| "lambda"), &dy, &dx)?;
+        self.finish_add(layouter, &lambda, p, &q.x)
+    }
+
+    /// Returns `2p`. G1 has no points of order two, so `y != 0` on the curve.
+    pub fn double(
+        &self,
+        mut layouter: impl Layouter<F>,
+        p: &AssignedG1<F>,
+    ) -> Result<AssignedG1<F>, Error> {
+        let fq = &self.fq;
+        let x2 = fq.square(layouter.namespace(|| "x^2"), &p.x)?;
+        let two_x2 = fq.double(layouter.namespace(|| "2x^2"), &x2)?;
+        let three_x2 = fq.add(layouter.namespace(|| "3x^2"), &two_x2, &x2)?;
+        let two_y = fq.double(layouter.namespace(|| "2y"), &p.y)?;
+        let lambda = fq.div(layouter.namespace(|| "lambda"), &three_x2, &two_y)?;
+        self.finish_add(layouter, &lambda, p, &p.x)
+    }
+
+    /// `x_r = lambda^2 - x_p - x_q`, `y_r = lambda * (x_p - x_r) - y_p`.
+    fn finish_add(
+        &self,
+        mut layouter: impl Layouter<F>,
+        lambda: &AssignedFq<F>,
+        p: &AssignedG1<F>,
+        x_q: &AssignedFq<F>,
+    ) -> Result<AssignedG1<F>, Error> {
+        let fq = &self.fq;
+        let lambda2 = fq.square(layouter.namespace(|| "lambda^2"), lambda)?;
+        let x = fq.sub(layouter.namespace(|| "lambda^2 - x_p"), &lambda2, &p.x)?;
+        let x = fq.sub(layouter.namespace(|| "x_r"), &x, x_q)?;
+        let dx = fq.sub(layouter.namespace(|| "x_p - x_r"), &p.x, &x)?;
+        let y = fq.mul(layouter.namespace(|| "lambda * (x_p - x_r)"), lambda, &dx)?;
+        let y = fq.sub(layouter.namespace(|| "y_r"), &y, &p.y)?;
+        Ok(AssignedG1 { x, y })
+    }
+
+    /// Returns `p` if `bit` is one and `q` otherwise.
+    pub fn select(
+        &self,
+        mut layouter: impl Layouter<F>,
+        bit: &AssignedCell<F, F>,
+        p: &AssignedG1<F>,
+        q: &AssignedG1<F>,
+    ) -> Result<AssignedG1<F>, Error> {
+        let x = self
+            .fq
+            .select(layouter.namespace(|| "x"), bit, &p.x, &q.x)?;
+        let y = self
+            .fq
+            .select(layouter.namespace(|| "y"), bit, &p.y, &q.y)?;
+        Ok(AssignedG1 { x, y })
+    }
+
+    /// Witnesses the little-endian bits of a scalar.
+    pub fn assign_scalar(
+        &self,
+        layouter: impl Layouter<F>,
+        scalar: Value<Scalar>,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let bits = scalar.map(|s| scalar_bits(&s));
+        self.fq.assign_bits(layouter, bits, SCALAR_BITS)
+    }
+
+    /// Returns `k * base` for a constant `base`, where `bits` are the
+    /// little-endian bits of `k`.
+    ///
+    /// The accumulator starts at `offset_point()` so it is never the identity;
+    /// the offset is removed again at the end.
+    pub fn fixed_base_mul(
+        &self,
+        mut layouter: impl Layouter<F>,
+        bits: &[AssignedCell<F, F>],
+        base: &G1Affine,
+    ) -> Result<AssignedG1<F>, Error> {
+        let offset = offset_point();
+        let mut acc = self.load_constant(layouter.namespace(|| "offset"), &offset)?;
+        let mut multiple = G1Projective::from(base);
+        for (i, bit) in bits.iter().enumerate() {
+            let mut layouter = layouter.namespace(|| format!("bit {}", i));
+            let p = self.load_constant(layouter.namespace(|| "2^i * base"), &multiple.into())?;
+            let sum = self.add(layouter.namespace(|| "acc + 2^i * base"), &acc, &p)?;
+            acc = self.select(layouter.namespace(|| "select"), bit, &sum, &acc)?;
+            multiple = multiple.double();
+        }
+
+        let neg_offset = self.load_constant(layouter.namespace(|| "-offset"), &(-offset))?;
+        self.add(layouter.namespace(|| "remove offset"), &acc, &neg_offset)
+    }
+
+    /// Returns `k * p` by double-and-add from the most significant bit, where
+    /// `bits` are the little-endian bits of `k`.
+    pub fn variable_base_mul(
+        &self,
+        mut layouter: impl Layouter<F>,
+        bits: &[AssignedCell<F, F>],
+        p: &AssignedG1<F>,
+    ) -> Result<AssignedG1<F>, Error> {
+        let offset = offset_point();
+        let mut acc = self.load_constant(layouter.namespace(|| "offset"), &offset)?;
+        for (i, bit) in bits.iter().enumerate().rev() {
+            let mut layouter = layouter.namespace(|| format!("bit {}", i));
+            let doubled = self.double(layouter.namespace(|| "2 * acc"), &acc)?;
+            let sum = self.add(layouter.namespace(|| "2 * acc + p"), &doubled, p)?;
+            acc = self.select(layouter.namespace(|| "select"), bit, &sum, &doubled)?;
+        }
+
+        let shifted_offset =
+            (0..bits.len()).fold(G1Projective::from(offset), |acc, _| acc.double());
+        let neg_offset = self.load_constant(
+            layouter.namespace(|| "-2^n * offset"),
+            &G1Affine::from(-shifted_offset),
+        )?;
+        self.add(layouter.namespace(|| "remove offset"), &acc, &neg_offset)
+    }
+}
+
+/// Little-endian bits of `scalar`, truncated to `SCALAR_BITS`.
+pub fn scalar_bits(scalar: &Scalar) -> Vec<bool> {
+    let bytes = scalar.to_bytes();
+    (0..SCALAR_BITS)
+        .map(|i| (bytes[i / 8] >> (i % 8)) & 1 == 1)
+        .collect()
+}
+
+/// Starting point for scalar multiplication accumulators, so they are never
+/// the identity. Its discrete log (digits of pi) is public; soundness comes
+/// from the `x_p != x_q` check in `add`, the offset only has to make that
+/// check pass for honest witnesses.
+pub fn offset_point() -> G1Affine {
+    let k = Scalar::from_raw([
+        0x243f_6a88_85a3_08d3,
+        0x1319_8a2e_0370_7344,
+        0xa409_3822_299f_31d0,
+        0x082e_fa98_ec4e_6c89,
+    ]);
+    G1Affine::from(G1Affine::generator() * k)
+}
@@ -0,0 +1,289 @@
+use bls12_381::G2Affine;
+use halo2_proofs::{
+    circuit::{Layouter, Value},
+    pasta::group::ff::PrimeField,
+    plonk::{Column, ConstraintSystem, Error, Instance},
+};
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+
+use super::fq::{modulus, FqConfig, NUM_LIMBS};
+use super::fq2::{fq2_inverse, fq2_mul, fq2_pow, fq2_sub, AssignedFq2, Fq2, Fq2Chip, Fq2Sum};
+use super::g2_coordinates;
+
+/// `|x|` for the BLS12-381 parameter `x = -0xd201000000010000`.
+pub const BLS_X: u64 = 0xd201_0000_0001_0000;
+
+/// An affine point on the twist `y^2 = x^3 + 4(u + 1)` over Fq2. The point
+/// at infinity has no representation.
+#[derive(Clone, Debug)]
+pub struct AssignedG2<F: PrimeField> {
+    pub x: AssignedFq2<F>,
+    pub y: AssignedFq2<F>,
+}
+
+/// G2 arithmetic on top of `Fq2Chip`. Slopes are witnessed and checked with a
+/// single product, and are returned alongside the result so the Miller loop
+/// can reuse them for its line functions.
+#[derive(Clone, Debug)]
+pub struct G2Chip<F: PrimeField> {
+    fq2: Fq2Chip<F>,
+}
+
+impl<F: PrimeField> G2Chip<F> {
+    pub fn construct(config: FqConfig) -> Self {
+        G2Chip {
+            fq2: Fq2Chip::construct(config),
+        }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> FqConfig {
+        Fq2Chip::configure(meta)
+    }
+
+    pub fn fq2(&self) -> &Fq2Chip<F> {
+        &self.fq2
+    }
+
+    pub fn load_private(
+        &self,
+        mut layouter: impl Layouter<F>,
+        point: Value<G2Affine>,
+    ) -> Result<AssignedG2<F>, Error> {
+        let (x, y) = point.map(|p| g2_coordinates(&p)).unzip();
+        let x = self.fq2.load_private(layouter.namespace(|| "x"), x)?;
+        let y = self.fq2.load_private(layouter.namespace(|| "y"), y)?;
+        Ok(AssignedG2 { x, y })
+    }
+
+    /// Loads a point whose coordinates sit in `4 * NUM_LIMBS` instance rows
+    /// starting at `row`, in the order `x.c0, x.c1, y.c0, y.c1`.
+    pub fn load_instance(
+        &self,
+        mut layouter: impl Layouter<F>,
+        instance: Column<Instance>,
+        row: usize,
+    ) -> Result<AssignedG2<F>, Error> {
+        let x = self
+            .fq2
+            .load_instance(layouter.namespace(|| "x"), instance, row)?;
+        let y =
+            self.fq2
+                .load_instance(layouter.namespace(|| "y"), instance, row + 2 * NUM_LIMBS)?;
+        Ok(AssignedG2 { x, y })
+    }
+
+    /// Constrains `y^2 = x^3 + 4(u + 1)`.
+    pub fn assert_on_curve(
+        &self,
+        mut layouter: impl Layouter<F>,
+        p: &AssignedG2<F>,
+    ) -> Result<(), Error> {
+        let x2 = self.fq2.square(layouter.namespace(|| "x^2"), &p.x)?;
+        let mut sum = Fq2Sum::new();
+        sum.product(1, p.y.as_ref(), p.y.as_ref(), false);
+        sum.product(-1, x2.as_ref(), p.x.as_ref(), false);
+        sum.assert_zero(
+            self.fq2.fq(),
+            layouter.namespace(|| "y^2 = x^3 + b"),
+            [-4, -4],
+        )
+    }
+
+    pub fn assert_equal(
+        &self,
+        mut layouter: impl Layouter<F>,
+        p: &AssignedG2<F>,
+        q: &AssignedG2<F>,
+    ) -> Result<(), Error> {
+        self.fq2
+            .assert_equal(layouter.namespace(|| "x"), &p.x, &q.x)?;
+        self.fq2
+            .assert_equal(layouter.namespace(|| "y"), &p.y, &q.y)
+    }
+
+    pub fn neg(
+        &self,
+        mut layouter: impl Layouter<F>,
+        p: &AssignedG2<F>,
+    ) -> Result<AssignedG2<F>, Error> {
+        let y = self.fq2.neg(layouter.namespace(|| "-y"), &p.y)?;
+        Ok(AssignedG2 { x: p.x.clone(), y })
+    }
+
+    /// Returns `p + q` and the slope of the line through them, for `p != ±q`.
+    ///
+    /// `x_q - x_p` is constrained to be invertible: without it `p = q` would
+    /// leave the slope unconstrained.
+    pub fn add_with_slope(
+        &self,
+        mut layouter: impl Layouter<F>,
+        p: &AssignedG2<F>,
+        q: &AssignedG2<F>,
+    ) -> Result<(AssignedG2<F>, AssignedFq2<F>), Error> {
+        let fq = self.fq2.fq();
+        let dx =
+            q.x.value()
+                .zip(p.x.value())
+                .map(|(xq, xp)| fq2_sub(&xq, &xp));
+        let dy =
+            q.y.value()
+                .zip(p.y.value())
+                .map(|(yq, yp)| fq2_sub(&yq, &yp));
+
+        let dx_inv = self.fq2.load_private(
+            layouter.namespace(|| "witness (x_q - x_p)^-1"),
+            dx.as_ref().map(fq2_inverse),
+        )?;
+        let mut sum = Fq2Sum::new();
+        sum.product(1, dx_inv.as_ref(), q.x.as_ref(), false);
+        sum.product(-1, dx_inv.as_ref(), p.x.as_ref(), false);
+        sum.assert_zero(fq, layouter.namespace(|| "x_q - x_p != 0"), [-1, 0])?;
+
+        let lambda = self.fq2.load_private(
+            layouter.namespace(|| "witness lambda"),
+            dy.zip(dx).map(|(dy, dx)| fq2_mul(&dy, &fq2_inverse(&dx))),
+        )?;
+        let mut sum = Fq2Sum::new();
+        sum.product(1, lambda.as_ref(), q.x.as_ref(), false);
+        sum.product(-1, lambda.as_ref(), p.x.as_ref(), false);
+        sum.linear(-1, q.y.as_ref(), false);
+        sum.linear(1, p.y.as_ref(), false);
+        sum.assert_zero(fq, layouter.namespace(|| "lambda * dx = dy"), [0, 0])?;
+
+        let r = self.finish_add(layouter, &lambda, p, &q.x)?;
+        Ok((r, lambda))
+    }
+
+    /// Returns `2p` and the slope of the tangent at `p`. The twist has no
+    /// points with `y = 0`, so `2 * lambda * y = 3 * x^2` fixes the slope.
+    pub fn double_with_slope(
+        &self,
+        mut layouter: impl Layouter<F>,
+        p: &AssignedG2<F>,
+    ) -> Result<(AssignedG2<F>, AssignedFq2<F>), Error> {
+        let lambda = p.x.value().zip(p.y.value()).map(|(x, y)| {
+            let three_x2 = fq2_mul(&[BigUint::from(3u32), BigUint::zero()], &fq2_mul(&x, &x));
+            let two_y = fq2_mul(&[BigUint::from(2u32), BigUint::zero()], &y);
+            fq2_mul(&three_x2, &fq2_inverse(&two_y))
+        });
+        let lambda = self
+            .fq2
+            .load_private(layouter.namespace(|| "witness lambda"), lambda)?;
+        let mut sum = Fq2Sum::new();
+        sum.product(2, lambda.as_ref(), p.y.as_ref(), false);
+        sum.product(-3, p.x.as_ref(), p.x.as_ref(), false);
+        sum.assert_zero(
+            self.fq2.fq(),
+            layouter.namespace(|| "2 * lambda * y = 3 * x^2"),
+            [0, 0],
+        )?;
+
+        let r = self.finish_add(layouter, &lambda, p, &p.x)?;
+        Ok((r, lambda))
+    }
+
+    pub fn add(
+        &self,
+        layouter: impl Layouter<F>,
+        p: &AssignedG2<F>,
+        q: &AssignedG2<F>,
+    ) -> Result<AssignedG2<F>, Error> {
+        self.add_with_slope(layouter, p, q).map(|(r, _)| r)
+    }
+
+    pub fn double(
+        &self,
+        layouter: impl Layouter<F>,
+        p: &AssignedG2<F>,
+    ) -> Result<AssignedG2<F>, Error> {
+        self.double_with_slope(layouter, p).map(|(r, _)| r)
+    }
+
+    /// `x_r = lambda^2 - x_p - x_q`, `y_r = lambda * (x_p - x_r) - y_p`.
+    fn finish_add(
+        &self,
+        mut layouter: impl Layouter<F>,
+        lambda: &AssignedFq2<F>,
+        p: &AssignedG2<F>,
+        x_q: &AssignedFq2<F>,
+    ) -> Result<AssignedG2<F>, Error> {
+        let fq = self.fq2.fq();
+        let mut sum = Fq2Sum::new();
+        sum.product(1, lambda.as_ref(), lambda.as_ref(), false);
+        sum.linear(-1, p.x.as_ref(), false);
+        sum.linear(-1, x_q.as_ref(), false);
+        let x = sum.finish(fq, layouter.namespace(|| "x_r"), [0, 0])?;
+
+        let mut sum = Fq2Sum::new();
+        sum.product(1, lambda.as_ref(), p.x.as_ref(), false);
+        sum.product(-1, lambda.as_ref(), x.as_ref(), false);
+        sum.linear(-1, p.y.as_ref(), false);
+        let y = sum.finish(fq, layouter.namespace(|| "y_r"), [0, 0])?;
+
+        Ok(AssignedG2 { x, y })
+    }
+
+    /// The untwist-Frobenius-twist endomorphism
+    /// `psi(x, y) = (conj(x) / xi^((q - 1) / 3), conj(y) / xi^((q - 1) / 2))`.
+    pub fn psi(
+        &self,
+        mut layouter: impl Layouter<F>,
+        p: &AssignedG2<F>,
+    ) -> Result<AssignedG2<F>, Error> {
+        let fq = self.fq2.fq();
+        let (coeff_x, coeff_y) = psi_coefficients();
+        let coeff_x = self
+            .fq2
+            .load_constant(layouter.namespace(|| "psi x coefficient"), &coeff_x)?;
+        let coeff_y = self
+            .fq2
+            .load_constant(layouter.namespace(|| "psi y coefficient"), &coeff_y)?;
+
+        let mut sum = Fq2Sum::new();
+        sum.conjugate_product(1, p.x.as_ref(), coeff_x.as_ref());
+        let x = sum.finish(fq, layouter.namespace(|| "x"), [0, 0])?;
+        let mut sum = Fq2Sum::new();
+        sum.conjugate_product(1, p.y.as_ref(), coeff_y.as_ref());
+        let y = sum.finish(fq, layouter.namespace(|| "y"), [0, 0])?;
+
+        Ok(AssignedG2 { x, y })
+    }
+
+    /// Returns `[x]p` for the (negative) curve parameter `x`.
+    pub fn mul_by_x(
+        &self,
+        mut layouter: impl Layouter<F>,
+        p: &AssignedG2<F>,
+    ) -> Result<AssignedG2<F>, Error> {
+        let mut acc = p.clone();
+        for i in (0..63 - BLS_X.leading_zeros()).rev() {
+            let mut layouter = layouter.namespace(|| format!("bit {}", i));
+            acc = self.double(layouter.namespace(|| "double"), &acc)?;
+            if (BLS_X >> i) & 1 == 1 {
+                acc = self.add(layouter.namespace(|| "add"), &acc, p)?;
+            }
+        }
+        self.neg(layouter.namespace(|| "negate"), &acc)
+    }
+
+    /// Constrains `p` to the order-r subgroup via `psi(p) = [x]p`, see
+    /// <https://eprint.iacr.org/2021/1130>. `p` must be on the curve.
+    pub fn assert_in_subgroup(
+        &self,
+        mut layouter: impl Layouter<F>,
+        p: &AssignedG2<F>,
+    ) -> Result<(), Error> {
+        let psi = self.psi(layouter.namespace(|| "psi(p)"), p)?;
+        let x_p = self.mul_by_x(layouter.namespace(|| "[x]p"), p)?;
+        self.assert_equal(layouter.namespace(|| "psi(p) = [x]p"), &psi, &x_p)
+    }
+}
+
+fn psi_coefficients() -> (Fq2, Fq2) {
+    let q = modulus();
+    let xi = [BigUint::one(), BigUint::one()];
+    let coeff_x = fq2_inverse(&fq2_pow(&xi, &((q - 1u32) / 3u32)));
+    let coeff_y = fq2_inverse(&fq2_pow(&xi, &((q - 1u32) / 2u32)));
+    (coeff_x, coeff_y)
+}
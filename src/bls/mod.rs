@@ -0,0 +1,62 @@
+//! Emulated BLS12-381 arithmetic over a Pasta field.
+
+use bls12_381::{G1Affine, G2Affine};
+use halo2_proofs::pasta::group::ff::PrimeField;
+use num_bigint::BigUint;
+
+use fq2::Fq2;
+
+pub mod fq;
+pub mod fq12;
+pub mod fq2;
+pub mod g1;
+pub mod g2;
+pub mod native;
+pub mod pairing;
+
+/// Affine coordinates of a non-identity G1 point.
+pub fn g1_coordinates(p: &G1Affine) -> (BigUint, BigUint) {
+    let bytes = p.to_uncompressed();
+    let mut x = bytes[..48].to_vec();
+    // Clear the compression, infinity and sort flags.
+    x[0] &= 0x1f;
+    (
+        BigUint::from_bytes_be(&x),
+        BigUint::from_bytes_be(&bytes[48..]),
+    )
+}
+
+/// Affine coordinates of a non-identity G2 point.
+pub fn g2_coordinates(p: &G2Affine) -> (Fq2, Fq2) {
+    let bytes = p.to_uncompressed();
+    let mut x_c1 = bytes[..48].to_vec();
+    x_c1[0] &= 0x1f;
+    let x = [
+        BigUint::from_bytes_be(&bytes[48..96]),
+        BigUint::from_bytes_be(&x_c1),
+    ];
+    let y = [
+        BigUint::from_bytes_be(&bytes[144..]),
+        BigUint::from_bytes_be(&bytes[96..144]),
+    ];
+    (x, y)
+}
+
+/// Instance cells for a G1 point, laid out as expected by
+/// `G1Chip::load_instance`.
+pub fn g1_to_instance<F: PrimeField>(p: &G1Affine) -> Vec<F> {
+    let (x, y) = g1_coordinates(p);
+    let mut cells = fq::fq_to_instance(&x);
+    cells.extend(fq::fq_to_instance::<F>(&y));
+    cells
+}
+
+/// Instance cells for a G2 point, laid out as expected by
+/// `G2Chip::load_instance`.
+pub fn g2_to_instance<F: PrimeField>(p: &G2Affine) -> Vec<F> {
+    let (x, y) = g2_coordinates(p);
+    x.iter()
+        .chain(y.iter())
+        .flat_map(|c| fq::fq_to_instance::<F>(c))
+        .collect()
+}
@@ -0,0 +1,162 @@
+//! Out-of-circuit BLS signatures in the minimal-pubkey-size variant: public
+//! keys in G1, signatures and message hashes in G2. This is the reference
+//! the circuits in `examples/bls.rs` are checked against.
+
+use bls12_381::{
+    hash_to_curve::{ExpandMsgXmd, HashToCurve, HashToField},
+    multi_miller_loop, G1Affine, G1Projective, G2Affine, G2Prepared, G2Projective, Scalar,
+};
+use halo2_proofs::pasta::group::Group;
+use sha2::Sha256;
+
+/// Domain separation tag of the basic scheme with hash-to-G2 (RFC 9380
+/// `BLS12381G2_XMD:SHA-256_SSWU_RO_`).
+pub const DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_";
+
+/// Domain separation tag for deriving secret keys from seed material.
+pub const KEYGEN_DST: &[u8] = b"HALO2-LEARNING-BLS-KEYGEN-V01";
+
+/// Derives a key pair from `ikm` by hashing it to a scalar. Deterministic,
+/// but not the HKDF-based `KeyGen` of the IETF draft.
+pub fn keygen(ikm: &[u8]) -> (Scalar, G1Affine) {
+    let mut sk = [Scalar::zero()];
+    Scalar::hash_to_field::<ExpandMsgXmd<Sha256>>(ikm, KEYGEN_DST, &mut sk);
+    let sk = sk[0];
+    assert!(sk != Scalar::zero(), "degenerate key material");
+    (sk, public_key(&sk))
+}
+
+pub fn public_key(sk: &Scalar) -> G1Affine {
+    G1Affine::from(G1Affine::generator() * sk)
+}
+
+/// Hashes `msg` to G2 with `DST`.
+pub fn hash_to_g2(msg: &[u8]) -> G2Affine {
+    G2Affine::from(<G2Projective as HashToCurve<ExpandMsgXmd<Sha256>>>::hash_to_curve(msg, DST))
+}
+
+pub fn sign(sk: &Scalar, msg: &[u8]) -> G2Affine {
+    G2Affine::from(hash_to_g2(msg) * sk)
+}
+
+/// Checks `e(pubkey, H(msg)) = e(G1, signature)`. The points are assumed to
+/// be decoded with subgroup checks, as `from_compressed` does.
+pub fn verify(pubkey: &G1Affine, msg: &[u8], signature: &G2Affine) -> bool {
+    if bool::from(pubkey.is_identity()) {
+        return false;
+    }
+    pairing_product_is_one(&[
+        (*pubkey, hash_to_g2(msg)),
+        (-G1Affine::generator(), *signature),
+    ])
+}
+
+pub fn aggregate_signatures(signatures: &[G2Affine]) -> G2Affine {
+    G2Affine::from(
+        signatures
+            .iter()
+            .map(G2Projective::from)
+            .sum::<G2Projective>(),
+    )
+}
+
+pub fn aggregate_public_keys(pubkeys: &[G1Affine]) -> G1Affine {
+    G1Affine::from(pubkeys.iter().map(G1Projective::from).sum::<G1Projective>())
+}
+
+/// Checks an aggregate signature by `pubkeys` on one message against their
+/// aggregate key. Only sound for keys with a proof of possession.
+pub fn fast_aggregate_verify(pubkeys: &[G1Affine], msg: &[u8], signature: &G2Affine) -> bool {
+    !pubkeys.is_empty() && verify(&aggregate_public_keys(pubkeys), msg, signature)
+}
+
+/// Checks an aggregate signature over distinct messages, one per key:
+/// `prod e(pubkey_i, H(msg_i)) = e(G1, signature)`.
+pub fn aggregate_verify(pubkeys: &[G1Affine], msgs: &[&[u8]], signature: &G2Affine) -> bool {
+    if pubkeys.is_empty()
+        || pubkeys.len() != msgs.len()
+        || pubkeys
+            .iter()
+            .any(|pubkey| bool::from(pubkey.is_identity()))
+    {
+        return false;
+    }
+    let mut pairs: Vec<_> = pubkeys
+        .iter()
+        .zip(msgs)
+        .map(|(pubkey, msg)| (*pubkey, hash_to_g2(msg)))
+        .collect();
+    pairs.push((-G1Affine::generator(), *signature));
+    pairing_product_is_one(&pairs)
+}
+
+fn pairing_product_is_one(pairs: &[(G1Affine, G2Affine)]) -> bool {
+    let prepared: Vec<_> = pairs.iter().map(|(_, q)| G2Prepared::from(*q)).collect();
+    let terms: Vec<_> = pairs
+        .iter()
+        .zip(&prepared)
+        .map(|((p, _), q)| (p, q))
+        .collect();
+    bool::from(
+        multi_miller_loop(&terms)
+            .final_exponentiation()
+            .is_identity(),
+    )
+}
+
+/// A fixed signing case. Points are hex encoded in their compressed form, the
+/// secret key as a big-endian integer.
+pub struct TestVector {
+    pub ikm: &'static [u8],
+    pub msg: &'static [u8],
+    pub sk: &'static str,
+    pub pubkey: &'static str,
+    pub signature: &'static str,
+}
+
+/// Fixed outputs of `keygen` and `sign`. Any in-circuit verifier must accept
+/// `(pubkey, hash_to_g2(msg), signature)` for each of them.
+pub const TEST_VECTORS: [TestVector; 3] = [
+    TestVector {
+        ikm: b"halo2-learning test key 0",
+        msg: b"",
+        sk: "406bcd6bb0f443a5fc451b6ebb75057c2835e31c6a36d2f9ba5ac65bc2b64a37",
+        pubkey: "923df50e27870567462f703351e7b1c964c1656e775217e7358c4a886ca598c0\
+                 24cede1281b45d054896bf0e6136c136",
+        signature: "ad79227abb9642e54cb6a859e7abbb7f067ea55c9e28b3142aeabfaa2a6fc5b7\
+                    d4a63e184f74eac8fe55197480a2110a06087841eb0852d7a3a9e5b87c0c26e4\
+                    89c03ebb4f35ff965dd8e87e0ef0109d984815f8b8e384098f6562b07812c2d6",
+    },
+    TestVector {
+        ikm: b"halo2-learning test key 1",
+        msg: b"abc",
+        sk: "43244f13915e314ac880f93cc01b66d495d4fc7b2e8db8c866cf231691bb59d2",
+        pubkey: "84a92c364cfc0861e4355032443d6e9115c3a037bb8a812c1c9b531bcfbc52f2\
+                 d2936f835bdc0e313b1e095fbb89a956",
+        signature: "8532624bf88eac05a654d597585beae564ec5a6e18ff48600538751581d0b198\
+                    a1f9c8d512c666c9c2aa69a76cb900440d74f31a4ef347f41d99fa295046e61c\
+                    3b25776290fb7415cb7655a1abc7212518a2c8adc6efb8ae1fd61ed874e7bf5b",
+    },
+    TestVector {
+        ikm: b"halo2-learning test key 2",
+        msg: b"halo2 learning: BLS over a Pasta circuit",
+        sk: "63509a25c239d108281b728c136623cc77747079e3e351949f61cfd51f0bef3a",
+        pubkey: "95b4cbee72d67988cc54ff79871e29948b0fb0d0f663797c4c76b4e9b880a931\
+                 687d5a05480f2b160ed13ff31e5f2811",
+        signature: "b60f154eeb29d68ce9296d29e457f5e313b08c389789c88e023e38bc5176630f\
+                    acd17856e440a0fe9b664e89784b92400797b184bb9c881eeddead7538a6ac39\
+                    56d4c5f31bf322810fea08547d868e319a320eb28797692654d074029cabb851",
+    },
+];
+
+/// Lower-case hex encoding, used to compare against `TEST_VECTORS`.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// `to_hex` of the big-endian encoding of `sk`.
+pub fn scalar_to_hex(sk: &Scalar) -> String {
+    let mut bytes = sk.to_bytes();
+    bytes.reverse();
+    to_hex(&bytes)
+}
@@ -0,0 +1,211 @@
+use halo2_proofs::{
+    circuit::Layouter,
+    pasta::group::ff::PrimeField,
+    plonk::{ConstraintSystem, Error},
+};
+
+use super::fq::{AssignedFq, FqConfig};
+use super::fq12::{AssignedFq12, Fq12Chip};
+use super::fq2::{AssignedFq2, Fq2Sum};
+use super::g1::AssignedG1;
+use super::g2::{AssignedG2, G2Chip, BLS_X};
+
+/// A line function evaluated at a G1 point and scaled by `w^3`:
+/// `c0 + c2 * w^2 + c3 * w^3`.
+struct Line<F: PrimeField> {
+    c0: AssignedFq2<F>,
+    c2: AssignedFq2<F>,
+    c3: AssignedFq<F>,
+}
+
+/// The optimal ate pairing on BLS12-381.
+///
+/// G2 points stay in affine coordinates on the twist; the slope of each
+/// doubling and addition step is witnessed once and shared between the point
+/// arithmetic and the line function.
+#[derive(Clone, Debug)]
+pub struct PairingChip<F: PrimeField> {
+    g2: G2Chip<F>,
+    fq12: Fq12Chip<F>,
+}
+
+impl<F: PrimeField> PairingChip<F> {
+    pub fn construct(config: FqConfig) -> Self {
+        PairingChip {
+            g2: G2Chip::construct(config.clone()),
+            fq12: Fq12Chip::construct(config),
+        }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> FqConfig {
+        Fq12Chip::configure(meta)
+    }
+
+    pub fn g2(&self) -> &G2Chip<F> {
+        &self.g2
+    }
+
+    pub fn fq12(&self) -> &Fq12Chip<F> {
+        &self.fq12
+    }
+
+    /// Constrains `prod e(p_i, q_i) = 1`.
+    pub fn assert_product_is_one(
+        &self,
+        mut layouter: impl Layouter<F>,
+        pairs: &[(&AssignedG1<F>, &AssignedG2<F>)],
+    ) -> Result<(), Error> {
+        let f = self.miller_loop(layouter.namespace(|| "miller loop"), pairs)?;
+        let f = self.final_exponentiation(layouter.namespace(|| "final exponentiation"), &f)?;
+        self.fq12
+            .assert_one(layouter.namespace(|| "product is one"), &f)
+    }
+
+    /// Shared Miller loop over `|x|` for all pairs.
+    ///
+    /// `x` is negative, so each Miller value should be conjugated at the end.
+    /// After the final exponentiation that is an inversion, which does not
+    /// change whether the product is one, so it is skipped.
+    pub fn miller_loop(
+        &self,
+        mut layouter: impl Layouter<F>,
+        pairs: &[(&AssignedG1<F>, &AssignedG2<F>)],
+    ) -> Result<AssignedFq12<F>, Error> {
+        let mut f = self.fq12.load_one(layouter.namespace(|| "one"))?;
+        let mut ts: Vec<AssignedG2<F>> = pairs.iter().map(|(_, q)| (*q).clone()).collect();
+
+        let top_bit = 63 - BLS_X.leading_zeros();
+        for i in (0..top_bit).rev() {
+            let mut layouter = layouter.namespace(|| format!("bit {}", i));
+            if i + 1 != top_bit {
+                f = self.fq12.square(layouter.namespace(|| "f^2"), &f)?;
+            }
+
+            for (j, ((p, q), t)) in pairs.iter().zip(ts.iter_mut()).enumerate() {
+                let mut layouter = layouter.namespace(|| format!("pair {}", j));
+                let (doubled, lambda) =
+                    self.g2.double_with_slope(layouter.namespace(|| "2t"), t)?;
+                let line = self.line(layouter.namespace(|| "tangent"), &lambda, t, p)?;
+                f = self.mul_by_line(layouter.namespace(|| "f * tangent"), &f, &line)?;
+                *t = doubled;
+
+                if (BLS_X >> i) & 1 == 1 {
+                    let (sum, lambda) =
+                        self.g2
+                            .add_with_slope(layouter.namespace(|| "t + q"), t, q)?;
+                    let line = self.line(layouter.namespace(|| "chord"), &lambda, t, p)?;
+                    f = self.mul_by_line(layouter.namespace(|| "f * chord"), &f, &line)?;
+                    *t = sum;
+                }
+            }
+        }
+
+        Ok(f)
+    }
+
+    /// Raises `f` to `(q^12 - 1) / r`, using the same addition chain as the
+    /// `bls12_381` crate.
+    pub fn final_exponentiation(
+        &self,
+        mut layouter: impl Layouter<F>,
+        f: &AssignedFq12<F>,
+    ) -> Result<AssignedFq12<F>, Error> {
+        let fq12 = &self.fq12;
+
+        // Easy part: f^((q^6 - 1) * (q^2 + 1)).
+        let t0 = fq12.conjugate(layouter.namespace(|| "f^(q^6)"), f)?;
+        let t1 = fq12.invert(layouter.namespace(|| "f^-1"), f)?;
+        let t1 = fq12.mul(layouter.namespace(|| "f^(q^6 - 1)"), &t0, &t1)?;
+        let t2 = fq12.frobenius_map(layouter.namespace(|| "frobenius^2"), &t1, 2)?;
+        let t2 = fq12.mul(layouter.namespace(|| "easy part"), &t2, &t1)?;
+
+        // Hard part: (q^4 - q^2 + 1) / r, now in the cyclotomic subgroup.
+        let t1 = fq12.cyclotomic_square(layouter.namespace(|| "t2^2"), &t2)?;
+        let t1 = fq12.conjugate(layouter.namespace(|| "t1"), &t1)?;
+        let t3 = self.cyclotomic_exp(layouter.namespace(|| "t3"), &t2)?;
+        let t4 = fq12.cyclotomic_square(layouter.namespace(|| "t4"), &t3)?;
+        let t5 = fq12.mul(layouter.namespace(|| "t5"), &t1, &t3)?;
+        let t1 = self.cyclotomic_exp(layouter.namespace(|| "t1 = t5^x"), &t5)?;
+        let t0 = self.cyclotomic_exp(layouter.namespace(|| "t0 = t1^x"), &t1)?;
+        let t6 = self.cyclotomic_exp(layouter.namespace(|| "t6 = t0^x"), &t0)?;
+        let t6 = fq12.mul(layouter.namespace(|| "t6 * t4"), &t6, &t4)?;
+        let t4 = self.cyclotomic_exp(layouter.namespace(|| "t4 = t6^x"), &t6)?;
+        let t5 = fq12.conjugate(layouter.namespace(|| "conj(t5)"), &t5)?;
+        let t5_t2 = fq12.mul(layouter.namespace(|| "t5 * t2"), &t5, &t2)?;
+        let t4 = fq12.mul(layouter.namespace(|| "t4 * t5 * t2"), &t4, &t5_t2)?;
+        let t5 = fq12.conjugate(layouter.namespace(|| "conj(t2)"), &t2)?;
+        let t1 = fq12.mul(layouter.namespace(|| "t1 * t2"), &t1, &t2)?;
+        let t1 = fq12.frobenius_map(layouter.namespace(|| "t1^(q^3)"), &t1, 3)?;
+        let t6 = fq12.mul(layouter.namespace(|| "t6 * t5"), &t6, &t5)?;
+        let t6 = fq12.frobenius_map(layouter.namespace(|| "t6^q"), &t6, 1)?;
+        let t3 = fq12.mul(layouter.namespace(|| "t3 * t0"), &t3, &t0)?;
+        let t3 = fq12.frobenius_map(layouter.namespace(|| "t3^(q^2)"), &t3, 2)?;
+        let t3 = fq12.mul(layouter.namespace(|| "t3 * t1"), &t3, &t1)?;
+        let t3 = fq12.mul(layouter.namespace(|| "t3 * t6"), &t3, &t6)?;
+        fq12.mul(layouter.namespace(|| "t3 * t4"), &t3, &t4)
+    }
+
+    /// Returns `f^x` for `f` in the cyclotomic subgroup.
+    fn cyclotomic_exp(
+        &self,
+        mut layouter: impl Layouter<F>,
+        f: &AssignedFq12<F>,
+    ) -> Result<AssignedFq12<F>, Error> {
+        let mut acc = f.clone();
+        for i in (0..63 - BLS_X.leading_zeros()).rev() {
+            let mut layouter = layouter.namespace(|| format!("bit {}", i));
+            acc = self
+                .fq12
+                .cyclotomic_square(layouter.namespace(|| "square"), &acc)?;
+            if (BLS_X >> i) & 1 == 1 {
+                acc = self.fq12.mul(layouter.namespace(|| "mul"), &acc, f)?;
+            }
+        }
+        self.fq12.conjugate(layouter.namespace(|| "x < 0"), &acc)
+    }
+
+    /// The line through `t` with slope `lambda`, evaluated at `p` and scaled
+    /// by `w^3` (which the final exponentiation removes):
+    /// `(lambda * x_t - y_t) - lambda * x_p * w^2 + y_p * w^3`.
+    fn line(
+        &self,
+        mut layouter: impl Layouter<F>,
+        lambda: &AssignedFq2<F>,
+        t: &AssignedG2<F>,
+        p: &AssignedG1<F>,
+    ) -> Result<Line<F>, Error> {
+        let fq = self.g2.fq2().fq();
+
+        let mut sum = Fq2Sum::new();
+        sum.product(1, lambda.as_ref(), t.x.as_ref(), false);
+        sum.linear(-1, t.y.as_ref(), false);
+        let c0 = sum.finish(fq, layouter.namespace(|| "lambda * x_t - y_t"), [0, 0])?;
+
+        let mut sum = Fq2Sum::new();
+        sum.scale(-1, lambda.as_ref(), &p.x);
+        let c2 = sum.finish(fq, layouter.namespace(|| "-lambda * x_p"), [0, 0])?;
+
+        Ok(Line {
+            c0,
+            c2,
+            c3: p.y.clone(),
+        })
+    }
+
+    fn mul_by_line(
+        &self,
+        layouter: impl Layouter<F>,
+        f: &AssignedFq12<F>,
+        line: &Line<F>,
+    ) -> Result<AssignedFq12<F>, Error> {
+        let line = [
+            line.c0.as_ref(),
+            [None, None],
+            line.c2.as_ref(),
+            [Some(&line.c3), None],
+            [None, None],
+            [None, None],
+        ];
+        self.fq12.mul_sparse(layouter, f.as_ref(), line)
+    }
+}
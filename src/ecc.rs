@@ -0,0 +1,418 @@
+//! Pallas arithmetic over its own base field, i.e. in circuits over `Fp`.
+
+use halo2_proofs::{
+    arithmetic::{CurveAffine, CurveExt, Field},
+    circuit::{AssignedCell, Layouter, Value},
+    pasta::{
+        group::{Curve, Group},
+        pallas, Fp,
+    },
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Instance, Selector},
+    poly::Rotation,
+};
+
+/// An affine Pallas point. The identity has no representation.
+#[derive(Clone, Debug)]
+pub struct AssignedPoint {
+    pub x: AssignedCell<Fp, Fp>,
+    pub y: AssignedCell<Fp, Fp>,
+}
+
+impl AssignedPoint {
+    pub fn value(&self) -> Value<pallas::Affine> {
+        self.x
+            .value()
+            .zip(self.y.value())
+            .map(|(x, y)| pallas::Affine::from_xy(*x, *y).unwrap())
+    }
+}
+
+/// `(x, y)` of a point other than the identity.
+pub fn coordinates(point: &pallas::Affine) -> (Fp, Fp) {
+    let coordinates = point.coordinates().unwrap();
+    (*coordinates.x(), *coordinates.y())
+}
+
+#[derive(Clone, Debug)]
+pub struct EccConfig {
+    advice: [Column<Advice>; 8],
+    s_add: Selector,
+    s_double: Selector,
+    s_select: Selector,
+    s_on_curve: Selector,
+}
+
+/// Affine Pallas arithmetic, one row per operation:
+///
+/// | gate     | 0   | 1   | 2   | 3   | 4      | 5   | 6   | 7     |
+/// |----------|-----|-----|-----|-----|--------|-----|-----|-------|
+/// | add      | x_p | y_p | x_q | y_q | lambda | x_r | y_r | alpha |
+/// | double   | x_p | y_p |     |     | lambda | x_r | y_r |       |
+/// | select   | x_p | y_p | x_q | y_q | bit    | x_r | y_r |       |
+/// | on curve | x   | y   |     |     |        |     |     |       |
+///
+/// Additions use the incomplete formula with `alpha = 1 / (x_q - x_p)`, so
+/// exceptional inputs fail to verify instead of producing a wrong point.
+/// Pallas has odd order, so doubling a point on the curve never divides by
+/// zero.
+///
+/// The circuit must enable a constant column for `load_constant`.
+#[derive(Clone, Debug)]
+pub struct EccChip {
+    config: EccConfig,
+}
+
+impl EccChip {
+    pub fn construct(config: EccConfig) -> Self {
+        EccChip { config }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<Fp>) -> EccConfig {
+        let advice = [(); 8].map(|_| meta.advice_column());
+        for column in advice {
+            meta.enable_equality(column);
+        }
+        let s_add = meta.selector();
+        let s_double = meta.selector();
+        let s_select = meta.selector();
+        let s_on_curve = meta.selector();
+
+        meta.create_gate("ecc add", |meta| {
+            let [x_p, y_p, x_q, y_q, lambda, x_r, y_r, alpha] =
+                advice.map(|column| meta.query_advice(column, Rotation::cur()));
+            let s_add = meta.query_selector(s_add);
+            let one = Expression::Constant(Fp::one());
+
+            Constraints::with_selector(
+                s_add,
+                vec![
+                    (x_q.clone() - x_p.clone()) * alpha - one,
+                    lambda.clone() * (x_q.clone() - x_p.clone()) - (y_q - y_p.clone()),
+                    lambda.clone() * lambda.clone() - x_p.clone() - x_q - x_r.clone(),
+                    lambda * (x_p - x_r) - y_p - y_r,
+                ],
+            )
+        });
+
+        meta.create_gate("ecc double", |meta| {
+            let [x_p, y_p, lambda, x_r, y_r] =
+                [0, 1, 4, 5, 6].map(|i| meta.query_advice(advice[i], Rotation::cur()));
+            let s_double = meta.query_selector(s_double);
+            let two = Expression::Constant(Fp::from(2));
+            let three = Expression::Constant(Fp::from(3));
+
+            Constraints::with_selector(
+                s_double,
+                vec![
+                    two.clone() * y_p.clone() * lambda.clone() - three * x_p.clone() * x_p.clone(),
+                    lambda.clone() * lambda.clone() - two * x_p.clone() - x_r.clone(),
+                    lambda * (x_p - x_r) - y_p - y_r,
+                ],
+            )
+        });
+
+        meta.create_gate("ecc select", |meta| {
+            let [x_p, y_p, x_q, y_q, bit, x_r, y_r] =
+                [0, 1, 2, 3, 4, 5, 6].map(|i| meta.query_advice(advice[i], Rotation::cur()));
+            let s_select = meta.query_selector(s_select);
+            let one = Expression::Constant(Fp::one());
+
+            Constraints::with_selector(
+                s_select,
+                vec![
+                    bit.clone() * (one - bit.clone()),
+                    x_r - x_q.clone() - bit.clone() * (x_p - x_q),
+                    y_r - y_q.clone() - bit * (y_p - y_q),
+                ],
+            )
+        });
+
+        meta.create_gate("ecc on curve", |meta| {
+            let x = meta.query_advice(advice[0], Rotation::cur());
+            let y = meta.query_advice(advice[1], Rotation::cur());
+            let s_on_curve = meta.query_selector(s_on_curve);
+            let b = Expression::Constant(pallas::Affine::b());
+
+            Constraints::with_selector(
+                s_on_curve,
+                vec![y.clone() * y - x.clone() * x.clone() * x - b],
+            )
+        });
+
+        EccConfig {
+            advice,
+            s_add,
+            s_double,
+            s_select,
+            s_on_curve,
+        }
+    }
+
+    pub fn load_private(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        point: Value<pallas::Affine>,
+    ) -> Result<AssignedPoint, Error> {
+        let config = &self.config;
+        let (x, y) = point.map(|p| coordinates(&p)).unzip();
+
+        layouter.assign_region(
+            || "load private",
+            |mut region| {
+                let x = region.assign_advice(|| "x", config.advice[0], 0, || x)?;
+                let y = region.assign_advice(|| "y", config.advice[1], 0, || y)?;
+                Ok(AssignedPoint { x, y })
+            },
+        )
+    }
+
+    pub fn load_constant(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        point: &pallas::Affine,
+    ) -> Result<AssignedPoint, Error> {
+        let config = &self.config;
+        let (x, y) = coordinates(point);
+
+        layouter.assign_region(
+            || "load constant",
+            |mut region| {
+                let x = region.assign_advice_from_constant(|| "x", config.advice[0], 0, x)?;
+                let y = region.assign_advice_from_constant(|| "y", config.advice[1], 0, y)?;
+                Ok(AssignedPoint { x, y })
+            },
+        )
+    }
+
+    /// Loads a point from two instance rows starting at `row`, `x` first.
+    pub fn load_instance(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        instance: Column<Instance>,
+        row: usize,
+    ) -> Result<AssignedPoint, Error> {
+        let config = &self.config;
+
+        layouter.assign_region(
+            || "load instance",
+            |mut region| {
+                let x = region.assign_advice_from_instance(
+                    || "x",
+                    instance,
+                    row,
+                    config.advice[0],
+                    0,
+                )?;
+                let y = region.assign_advice_from_instance(
+                    || "y",
+                    instance,
+                    row + 1,
+                    config.advice[1],
+                    0,
+                )?;
+                Ok(AssignedPoint { x, y })
+            },
+        )
+    }
+
+    /// Constrains `y^2 = x^3 + 5`.
+    pub fn assert_on_curve(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        p: &AssignedPoint,
+    ) -> Result<(), Error> {
+        let config = &self.config;
+
+        layouter.assign_region(
+            || "on curve",
+            |mut region| {
+                config.s_on_curve.enable(&mut region, 0)?;
+                p.x.copy_advice(|| "x", &mut region, config.advice[0], 0)?;
+                p.y.copy_advice(|| "y", &mut region, config.advice[1], 0)?;
+                Ok(())
+            },
+        )
+    }
+
+    pub fn assert_equal(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        p: &AssignedPoint,
+        q: &AssignedPoint,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "assert equal",
+            |mut region| {
+                region.constrain_equal(p.x.cell(), q.x.cell())?;
+                region.constrain_equal(p.y.cell(), q.y.cell())
+            },
+        )
+    }
+
+    /// Returns `p + q` for `p != ±q`.
+    pub fn add(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        p: &AssignedPoint,
+        q: &AssignedPoint,
+    ) -> Result<AssignedPoint, Error> {
+        let config = &self.config;
+
+        layouter.assign_region(
+            || "add",
+            |mut region| {
+                config.s_add.enable(&mut region, 0)?;
+                let x_p =
+                    p.x.copy_advice(|| "x_p", &mut region, config.advice[0], 0)?;
+                let y_p =
+                    p.y.copy_advice(|| "y_p", &mut region, config.advice[1], 0)?;
+                let x_q =
+                    q.x.copy_advice(|| "x_q", &mut region, config.advice[2], 0)?;
+                let y_q =
+                    q.y.copy_advice(|| "y_q", &mut region, config.advice[3], 0)?;
+
+                let x_p = x_p.value().copied();
+                let y_p = y_p.value().copied();
+                let x_q = x_q.value().copied();
+                let y_q = y_q.value().copied();
+                let alpha = (x_q - x_p).map(|dx| dx.invert().unwrap_or(Fp::zero()));
+                let lambda = (y_q - y_p) * alpha;
+                let x_r = lambda.square() - x_p - x_q;
+                let y_r = lambda * (x_p - x_r) - y_p;
+
+                region.assign_advice(|| "lambda", config.advice[4], 0, || lambda)?;
+                let x = region.assign_advice(|| "x_r", config.advice[5], 0, || x_r)?;
+                let y = region.assign_advice(|| "y_r", config.advice[6], 0, || y_r)?;
+                region.assign_advice(|| "alpha", config.advice[7], 0, || alpha)?;
+                Ok(AssignedPoint { x, y })
+            },
+        )
+    }
+
+    /// Returns `2p`.
+    pub fn double(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        p: &AssignedPoint,
+    ) -> Result<AssignedPoint, Error> {
+        let config = &self.config;
+
+        layouter.assign_region(
+            || "double",
+            |mut region| {
+                config.s_double.enable(&mut region, 0)?;
+                let x_p =
+                    p.x.copy_advice(|| "x_p", &mut region, config.advice[0], 0)?;
+                let y_p =
+                    p.y.copy_advice(|| "y_p", &mut region, config.advice[1], 0)?;
+
+                let x_p = x_p.value().copied();
+                let y_p = y_p.value().copied();
+                let lambda = (x_p.square() * Value::known(Fp::from(3)))
+                    * y_p.double().map(|y| y.invert().unwrap_or(Fp::zero()));
+                let x_r = lambda.square() - x_p.double();
+                let y_r = lambda * (x_p - x_r) - y_p;
+
+                region.assign_advice(|| "lambda", config.advice[4], 0, || lambda)?;
+                let x = region.assign_advice(|| "x_r", config.advice[5], 0, || x_r)?;
+                let y = region.assign_advice(|| "y_r", config.advice[6], 0, || y_r)?;
+                Ok(AssignedPoint { x, y })
+            },
+        )
+    }
+
+    /// Returns `p` if `bit` is one and `q` otherwise.
+    pub fn select(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        bit: &AssignedCell<Fp, Fp>,
+        p: &AssignedPoint,
+        q: &AssignedPoint,
+    ) -> Result<AssignedPoint, Error> {
+        let config = &self.config;
+
+        layouter.assign_region(
+            || "select",
+            |mut region| {
+                config.s_select.enable(&mut region, 0)?;
+                p.x.copy_advice(|| "x_p", &mut region, config.advice[0], 0)?;
+                p.y.copy_advice(|| "y_p", &mut region, config.advice[1], 0)?;
+                q.x.copy_advice(|| "x_q", &mut region, config.advice[2], 0)?;
+                q.y.copy_advice(|| "y_q", &mut region, config.advice[3], 0)?;
+                let bit = bit.copy_advice(|| "bit", &mut region, config.advice[4], 0)?;
+
+                let is_p = bit.value().map(|bit| *bit == Fp::one());
+                let (x, y) =
+                    p.x.value()
+                        .zip(p.y.value())
+                        .zip(q.x.value().zip(q.y.value()))
+                        .zip(is_p)
+                        .map(|((p, q), is_p)| if is_p { (*p.0, *p.1) } else { (*q.0, *q.1) })
+                        .unzip();
+                let x = region.assign_advice(|| "x_r", config.advice[5], 0, || x)?;
+                let y = region.assign_advice(|| "y_r", config.advice[6], 0, || y)?;
+                Ok(AssignedPoint { x, y })
+            },
+        )
+    }
+
+    /// Returns `k * base` for a constant `base`, where `bits` are the
+    /// little-endian bits of `k`.
+    ///
+    /// The accumulator starts at `offset_point()` so it is never the identity;
+    /// the offset is removed again at the end.
+    pub fn fixed_base_mul(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        bits: &[AssignedCell<Fp, Fp>],
+        base: &pallas::Affine,
+    ) -> Result<AssignedPoint, Error> {
+        let offset = offset_point();
+        let mut acc = self.load_constant(layouter.namespace(|| "offset"), &offset)?;
+        let mut multiple = pallas::Point::from(*base);
+        for (i, bit) in bits.iter().enumerate() {
+            let mut layouter = layouter.namespace(|| format!("bit {}", i));
+            let p =
+                self.load_constant(layouter.namespace(|| "2^i * base"), &multiple.to_affine())?;
+            let sum = self.add(layouter.namespace(|| "acc + 2^i * base"), &acc, &p)?;
+            acc = self.select(layouter.namespace(|| "select"), bit, &sum, &acc)?;
+            multiple = multiple.double();
+        }
+
+        let neg_offset = self.load_constant(layouter.namespace(|| "-offset"), &(-offset))?;
+        self.add(layouter.namespace(|| "remove offset"), &acc, &neg_offset)
+    }
+
+    /// Returns `k * p` by double-and-add from the most significant bit, where
+    /// `bits` are the little-endian bits of `k`.
+    pub fn variable_base_mul(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        bits: &[AssignedCell<Fp, Fp>],
+        p: &AssignedPoint,
+    ) -> Result<AssignedPoint, Error> {
+        let offset = offset_point();
+        let mut acc = self.load_constant(layouter.namespace(|| "offset"), &offset)?;
+        for (i, bit) in bits.iter().enumerate().rev() {
+            let mut layouter = layouter.namespace(|| format!("bit {}", i));
+            let doubled = self.double(layouter.namespace(|| "2 * acc"), &acc)?;
+            let sum = self.add(layouter.namespace(|| "2 * acc + p"), &doubled, p)?;
+            acc = self.select(layouter.namespace(|| "select"), bit, &sum, &doubled)?;
+        }
+
+        let shifted_offset =
+            (0..bits.len()).fold(pallas::Point::from(offset), |acc, _| acc.double());
+        let neg_offset = self.load_constant(
+            layouter.namespace(|| "-2^n * offset"),
+            &(-shifted_offset).to_affine(),
+        )?;
+        self.add(layouter.namespace(|| "remove offset"), &acc, &neg_offset)
+    }
+}
+
+/// Starting point for scalar multiplication accumulators, so they are never
+/// the identity. Hashed to the curve, so its discrete log is unknown;
+/// soundness still comes from the `x_p != x_q` check in `add`.
+pub fn offset_point() -> pallas::Affine {
+    pallas::Point::hash_to_curve("halo2-learning:ecc")(b"offset").to_affine()
+}
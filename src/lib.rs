@@ -0,0 +1,13 @@
+pub mod bits;
+pub mod bls;
+pub mod ecc;
+pub mod merkle;
+pub mod mimc;
+pub mod pedersen;
+pub mod poseidon;
+pub mod ram;
+pub mod range;
+pub mod schnorr;
+pub mod shuffle;
+pub mod smt;
+pub mod sorted;
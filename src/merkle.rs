@@ -0,0 +1,304 @@
+use halo2_gadgets::poseidon::primitives::{P128Pow5T3, Spec};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    pasta::group::ff::PrimeField,
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Instance, Selector},
+    poly::Rotation,
+};
+
+use crate::poseidon::{self, PoseidonChip, PoseidonConfig, RATE, WIDTH};
+
+/// An authentication path, leaf to root. `directions[i]` is true when the
+/// node at level `i` is a right child, i.e. `siblings[i]` is on its left.
+#[derive(Clone, Debug)]
+pub struct MerklePath<F> {
+    pub siblings: Vec<F>,
+    pub directions: Vec<bool>,
+}
+
+impl<F: PrimeField> MerklePath<F>
+where
+    P128Pow5T3: Spec<F, WIDTH, RATE>,
+{
+    /// Hashes `leaf` up to the root.
+    pub fn root(&self, leaf: F) -> F {
+        self.siblings
+            .iter()
+            .zip(&self.directions)
+            .fold(leaf, |node, (sibling, is_right)| {
+                if *is_right {
+                    poseidon::hash([*sibling, node])
+                } else {
+                    poseidon::hash([node, *sibling])
+                }
+            })
+    }
+}
+
+/// A Poseidon Merkle tree of fixed depth. Leaves past the ones given are
+/// zero, so only the non-empty part of every level is stored.
+#[derive(Clone, Debug)]
+pub struct MerkleTree<F> {
+    levels: Vec<Vec<F>>,
+    empty: Vec<F>,
+}
+
+impl<F: PrimeField> MerkleTree<F>
+where
+    P128Pow5T3: Spec<F, WIDTH, RATE>,
+{
+    pub fn new(depth: usize, leaves: Vec<F>) -> Self {
+        assert!(leaves.len() <= 1 << depth, "too many leaves");
+
+        // empty[i] is the root of an empty subtree of height i.
+        let mut empty = vec![F::ZERO];
+        for i in 0..depth {
+            empty.push(poseidon::hash([empty[i], empty[i]]));
+        }
+
+        let mut levels = vec![leaves];
+        for i in 0..depth {
+            let level = &levels[i];
+            let next = level
+                .chunks(2)
+                .map(|pair| poseidon::hash([pair[0], *pair.get(1).unwrap_or(&empty[i])]))
+                .collect();
+            levels.push(next);
+        }
+
+        MerkleTree { levels, empty }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.levels.len() - 1
+    }
+
+    pub fn root(&self) -> F {
+        self.levels[self.depth()]
+            .first()
+            .copied()
+            .unwrap_or(self.empty[self.depth()])
+    }
+
+    pub fn path(&self, index: usize) -> MerklePath<F> {
+        assert!(index < 1 << self.depth(), "index out of range");
+        let (siblings, directions) = (0..self.depth())
+            .map(|i| {
+                let node = index >> i;
+                let sibling = self.levels[i]
+                    .get(node ^ 1)
+                    .copied()
+                    .unwrap_or(self.empty[i]);
+                (sibling, node & 1 == 1)
+            })
+            .unzip();
+        MerklePath {
+            siblings,
+            directions,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MerkleConfig<F: PrimeField> {
+    advice: [Column<Advice>; 5],
+    s_swap: Selector,
+    poseidon: PoseidonConfig<F>,
+}
+
+/// Recomputes a Merkle root from a leaf and an authentication path.
+///
+/// One row per level orders the pair before hashing:
+///
+/// node sibling direction left right
+///
+/// with `direction` boolean and `(left, right)` equal to `(node, sibling)`
+/// when it is zero and `(sibling, node)` when it is one.
+#[derive(Clone, Debug)]
+pub struct MerkleChip<F: PrimeField> {
+    config: MerkleConfig<F>,
+}
+
+impl<F: PrimeField> MerkleChip<F>
+where
+    P128Pow5T3: Spec<F, WIDTH, RATE>,
+{
+    pub fn construct(config: MerkleConfig<F>) -> Self {
+        MerkleChip { config }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> MerkleConfig<F> {
+        let advice = [(); 5].map(|_| meta.advice_column());
+        for column in advice {
+            meta.enable_equality(column);
+        }
+        let s_swap = meta.selector();
+
+        meta.create_gate("merkle swap", |meta| {
+            let [node, sibling, direction, left, right] =
+                advice.map(|column| meta.query_advice(column, Rotation::cur()));
+            let s_swap = meta.query_selector(s_swap);
+            let one = Expression::Constant(F::ONE);
+
+            Constraints::with_selector(
+                s_swap,
+                vec![
+                    direction.clone() * (one - direction.clone()),
+                    left - node.clone() - direction.clone() * (sibling.clone() - node.clone()),
+                    right - sibling.clone() - direction * (node - sibling),
+                ],
+            )
+        });
+
+        let poseidon = PoseidonChip::configure(meta);
+
+        MerkleConfig {
+            advice,
+            s_swap,
+            poseidon,
+        }
+    }
+
+    pub fn poseidon(&self) -> PoseidonChip<F> {
+        PoseidonChip::construct(self.config.poseidon.clone())
+    }
+
+    pub fn load_private(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: Value<F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        layouter.assign_region(
+            || "load private",
+            |mut region| region.assign_advice(|| "value", self.config.advice[0], 0, || value),
+        )
+    }
+
+    pub fn load_constant(
+        &self,
+        mut layouter: impl Layouter<F>,
+        constant: F,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        layouter.assign_region(
+            || "load constant",
+            |mut region| {
+                region.assign_advice_from_constant(
+                    || "constant",
+                    self.config.advice[0],
+                    0,
+                    constant,
+                )
+            },
+        )
+    }
+
+    pub fn load_instance(
+        &self,
+        mut layouter: impl Layouter<F>,
+        instance: Column<Instance>,
+        row: usize,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        layouter.assign_region(
+            || "load instance",
+            |mut region| {
+                region.assign_advice_from_instance(
+                    || "value",
+                    instance,
+                    row,
+                    self.config.advice[0],
+                    0,
+                )
+            },
+        )
+    }
+
+    /// Returns the root above `leaf` for a private path of
+    /// `siblings.len()` levels.
+    pub fn compute_root(
+        &self,
+        mut layouter: impl Layouter<F>,
+        leaf: &AssignedCell<F, F>,
+        siblings: &[Value<F>],
+        directions: &[Value<bool>],
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let directions = directions
+            .iter()
+            .enumerate()
+            .map(|(i, direction)| {
+                self.load_private(
+                    layouter.namespace(|| format!("direction {}", i)),
+                    direction.map(|d| F::from(d as u64)),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.compute_root_at_index(layouter, leaf, siblings, &directions)
+    }
+
+    /// Like `compute_root`, with the directions given as cells: the
+    /// little-endian bits of the leaf index, e.g. from `BitsChip::decompose`.
+    /// Each is constrained to be boolean.
+    pub fn compute_root_at_index(
+        &self,
+        mut layouter: impl Layouter<F>,
+        leaf: &AssignedCell<F, F>,
+        siblings: &[Value<F>],
+        index_bits: &[AssignedCell<F, F>],
+    ) -> Result<AssignedCell<F, F>, Error> {
+        assert_eq!(siblings.len(), index_bits.len());
+        let poseidon = self.poseidon();
+
+        let mut node = leaf.clone();
+        for (i, (sibling, direction)) in siblings.iter().zip(index_bits).enumerate() {
+            let mut layouter = layouter.namespace(|| format!("level {}", i));
+            let sibling = self.load_private(layouter.namespace(|| "sibling"), *sibling)?;
+            let pair = self.swap(
+                layouter.namespace(|| "order pair"),
+                &node,
+                &sibling,
+                direction,
+            )?;
+            node = poseidon.hash(layouter.namespace(|| "hash pair"), &pair)?;
+        }
+        Ok(node)
+    }
+
+    /// Returns `[node, sibling]`, swapped if `direction` is one. `direction`
+    /// is constrained to be boolean.
+    pub fn swap(
+        &self,
+        mut layouter: impl Layouter<F>,
+        node: &AssignedCell<F, F>,
+        sibling: &AssignedCell<F, F>,
+        direction: &AssignedCell<F, F>,
+    ) -> Result<[AssignedCell<F, F>; 2], Error> {
+        let config = &self.config;
+
+        layouter.assign_region(
+            || "merkle swap",
+            |mut region| {
+                config.s_swap.enable(&mut region, 0)?;
+                let node = node.copy_advice(|| "node", &mut region, config.advice[0], 0)?;
+                let sibling =
+                    sibling.copy_advice(|| "sibling", &mut region, config.advice[1], 0)?;
+                let direction =
+                    direction.copy_advice(|| "direction", &mut region, config.advice[2], 0)?;
+
+                let (left, right) = node
+                    .value()
+                    .zip(sibling.value())
+                    .zip(direction.value())
+                    .map(|((node, sibling), direction)| {
+                        if *direction == F::ONE {
+                            (*sibling, *node)
+                        } else {
+                            (*node, *sibling)
+                        }
+                    })
+                    .unzip();
+                let left = region.assign_advice(|| "left", config.advice[3], 0, || left)?;
+                let right = region.assign_advice(|| "right", config.advice[4], 0, || right)?;
+                Ok([left, right])
+            },
+        )
+    }
+}
@@ -0,0 +1,188 @@
+//! MiMC-x^7 with a zero key, made one-way by feeding the input forward:
+//! `H(x) = E(x) + x` where `E` is `ROUNDS` rounds of `x -> (x + c_i)^7`.
+//! `x^7` is a permutation of both Pasta fields, as `7` does not divide
+//! `p - 1`.
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Region, Value},
+    pasta::group::ff::PrimeField,
+    plonk::{Advice, Column, ConstraintSystem, Error, Fixed, Selector},
+    poly::Rotation,
+};
+use sha2::{Digest, Sha256};
+
+/// `ceil(log_7(p))` for the 255-bit Pasta fields.
+pub const ROUNDS: usize = 91;
+
+/// Rows taken by one hash in `MimcChip`.
+pub const ROWS_PER_HASH: usize = ROUNDS + 3;
+
+/// Round constants: `c_0 = 0`, then successive SHA-256 digests of a fixed
+/// seed read as big-endian integers modulo `p`.
+pub fn round_constants<F: PrimeField>() -> Vec<F> {
+    let mut digest = Sha256::digest(b"halo2-learning mimc7");
+    let mut constants = vec![F::ZERO];
+    for _ in 1..ROUNDS {
+        let constant = digest.iter().fold(F::ZERO, |acc, byte| {
+            acc * F::from(256) + F::from(*byte as u64)
+        });
+        constants.push(constant);
+        digest = Sha256::digest(&digest);
+    }
+    constants
+}
+
+fn round<F: PrimeField>(x: F, constant: F) -> F {
+    let t = x + constant;
+    let t2 = t.square();
+    t2.square() * t2 * t
+}
+
+/// Native counterpart of `MimcChip::hash`.
+pub fn hash<F: PrimeField>(input: F) -> F {
+    round_constants().into_iter().fold(input, round) + input
+}
+
+#[derive(Clone, Debug)]
+pub struct MimcConfig {
+    advice: Column<Advice>,
+    constants: Column<Fixed>,
+    s_round: Selector,
+    s_feed: Selector,
+}
+
+/// One hash over a single advice column:
+///
+/// row          advice    constants
+/// 0            x_0       c_0
+/// i            x_i       c_i
+/// ROUNDS       x_91
+/// ROUNDS + 1   x_0
+/// ROUNDS + 2   h
+///
+/// with `x_{i+1} = (x_i + c_i)^7` on the round rows and `h = x_91 + x_0`,
+/// the second `x_0` copied from the first.
+#[derive(Clone, Debug)]
+pub struct MimcChip<F: PrimeField> {
+    config: MimcConfig,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: PrimeField> MimcChip<F> {
+    pub fn construct(config: MimcConfig) -> Self {
+        MimcChip {
+            config,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> MimcConfig {
+        let advice = meta.advice_column();
+        let constants = meta.fixed_column();
+        let s_round = meta.selector();
+        let s_feed = meta.selector();
+        meta.enable_equality(advice);
+
+        meta.create_gate("mimc round", |meta| {
+            let s_round = meta.query_selector(s_round);
+            let cur = meta.query_advice(advice, Rotation::cur());
+            let next = meta.query_advice(advice, Rotation::next());
+            let constant = meta.query_fixed(constants, Rotation::cur());
+
+            let t = cur + constant;
+            let t2 = t.clone() * t.clone();
+            vec![s_round * (next - t2.clone() * t2.clone() * t2 * t)]
+        });
+
+        meta.create_gate("mimc feed forward", |meta| {
+            let s_feed = meta.query_selector(s_feed);
+            let permuted = meta.query_advice(advice, Rotation::cur());
+            let input = meta.query_advice(advice, Rotation::next());
+            let output = meta.query_advice(advice, Rotation(2));
+
+            vec![s_feed * (permuted + input - output)]
+        });
+
+        MimcConfig {
+            advice,
+            constants,
+            s_round,
+            s_feed,
+        }
+    }
+
+    pub fn load_private(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: Value<F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        layouter.assign_region(
+            || "load private",
+            |mut region| region.assign_advice(|| "value", self.config.advice, 0, || value),
+        )
+    }
+
+    pub fn hash(
+        &self,
+        layouter: impl Layouter<F>,
+        input: &AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        self.hash_chain(layouter, input, 1)
+    }
+
+    /// Returns `H^n(input)`, the hashes stacked in one region.
+    pub fn hash_chain(
+        &self,
+        mut layouter: impl Layouter<F>,
+        input: &AssignedCell<F, F>,
+        n: usize,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let constants = round_constants();
+
+        layouter.assign_region(
+            || "mimc chain",
+            |mut region| {
+                let mut node = input.clone();
+                for i in 0..n {
+                    node = self.assign_hash(&mut region, i * ROWS_PER_HASH, &node, &constants)?;
+                }
+                Ok(node)
+            },
+        )
+    }
+
+    fn assign_hash(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        input: &AssignedCell<F, F>,
+        constants: &[F],
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let config = &self.config;
+
+        let input = input.copy_advice(|| "x_0", region, config.advice, offset)?;
+        let mut x = input.value().copied();
+        for (i, constant) in constants.iter().enumerate() {
+            config.s_round.enable(region, offset + i)?;
+            region.assign_fixed(
+                || "c_i",
+                config.constants,
+                offset + i,
+                || Value::known(*constant),
+            )?;
+            x = x.map(|x| round(x, *constant));
+            region.assign_advice(|| "x_i", config.advice, offset + i + 1, || x)?;
+        }
+
+        config.s_feed.enable(region, offset + ROUNDS)?;
+        input.copy_advice(|| "x_0", region, config.advice, offset + ROUNDS + 1)?;
+        region.assign_advice(
+            || "h",
+            config.advice,
+            offset + ROUNDS + 2,
+            || x + input.value(),
+        )
+    }
+}
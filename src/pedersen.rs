@@ -0,0 +1,241 @@
+//! Pedersen commitments over Pallas: `commit(v, r) = v * G + r * H`, with
+//! `H` hashed to the curve so that nobody knows its discrete log relative
+//! to `G`. Values are base field elements, all of which are below the
+//! scalar field order; blinding factors are scalars.
+//!
+//! In circuit the commitment is built from the fixed-base multiplications
+//! of the `halo2_gadgets` ECC chip, which needs `G` and `H` with their
+//! window tables.
+
+use std::sync::OnceLock;
+
+use halo2_gadgets::{
+    ecc::{
+        self,
+        chip::{
+            find_zs_and_us, BaseFieldElem, EccChip, EccConfig, FixedPoint, FullScalar, ShortScalar,
+            H, NUM_WINDOWS, NUM_WINDOWS_SHORT,
+        },
+        FixedPointBaseField, FixedPoints, Point, ScalarFixed,
+    },
+    sinsemilla::primitives as sinsemilla,
+    utilities::lookup_range_check::LookupRangeCheckConfig,
+};
+use halo2_proofs::{
+    arithmetic::CurveExt,
+    circuit::{AssignedCell, Layouter, Value},
+    pasta::{
+        group::{ff::PrimeField, prime::PrimeCurveAffine, Curve},
+        pallas, Fp, Fq,
+    },
+    plonk::{ConstraintSystem, Error, TableColumn},
+};
+
+/// The blinding generator.
+pub fn generator_h() -> pallas::Affine {
+    pallas::Point::hash_to_curve("halo2-learning:pedersen")(b"H").to_affine()
+}
+
+/// `value` as the same integer in the scalar field, which is how the base
+/// field multiplication reads it. On Pallas `p < q`, so nothing wraps.
+fn to_scalar(value: Fp) -> Fq {
+    Fq::from_repr(value.to_repr()).unwrap()
+}
+
+/// Native counterpart of `PedersenChip::commit`.
+pub fn commit(value: Fp, blind: Fq) -> pallas::Affine {
+    (pallas::Affine::generator() * to_scalar(value) + generator_h() * blind).to_affine()
+}
+
+/// The fixed bases of the ECC chip. It wants one for each kind of scalar:
+/// `G` for values, as base field elements, `H` for full-width blinding
+/// factors, and `G` again for short signed scalars, which nothing here
+/// multiplies by.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PedersenBases;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ValueBase;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlindBase;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ShortValueBase;
+
+impl FixedPoints<pallas::Affine> for PedersenBases {
+    type FullScalar = BlindBase;
+    type ShortScalar = ShortValueBase;
+    type Base = ValueBase;
+}
+
+/// The `z` and `u` of each window, as found by `find_zs_and_us`.
+type Windows = Vec<(u64, [Fp; H])>;
+
+/// Finding the windows is a search, so each base does it once.
+fn windows(
+    cache: &'static OnceLock<Windows>,
+    base: pallas::Affine,
+    num_windows: usize,
+) -> &'static Windows {
+    cache.get_or_init(|| find_zs_and_us(base, num_windows).unwrap())
+}
+
+fn zs(windows: &Windows) -> Vec<u64> {
+    windows.iter().map(|(z, _)| *z).collect()
+}
+
+fn us(windows: &Windows) -> Vec<[[u8; 32]; H]> {
+    windows
+        .iter()
+        .map(|(_, us)| us.map(|u| u.to_repr()))
+        .collect()
+}
+
+impl ValueBase {
+    fn windows(&self) -> &'static Windows {
+        static WINDOWS: OnceLock<Windows> = OnceLock::new();
+        windows(&WINDOWS, self.generator(), NUM_WINDOWS)
+    }
+}
+
+impl FixedPoint<pallas::Affine> for ValueBase {
+    type FixedScalarKind = BaseFieldElem;
+
+    fn generator(&self) -> pallas::Affine {
+        pallas::Affine::generator()
+    }
+
+    fn u(&self) -> Vec<[[u8; 32]; H]> {
+        us(self.windows())
+    }
+
+    fn z(&self) -> Vec<u64> {
+        zs(self.windows())
+    }
+}
+
+impl BlindBase {
+    fn windows(&self) -> &'static Windows {
+        static WINDOWS: OnceLock<Windows> = OnceLock::new();
+        windows(&WINDOWS, self.generator(), NUM_WINDOWS)
+    }
+}
+
+impl FixedPoint<pallas::Affine> for BlindBase {
+    type FixedScalarKind = FullScalar;
+
+    fn generator(&self) -> pallas::Affine {
+        generator_h()
+    }
+
+    fn u(&self) -> Vec<[[u8; 32]; H]> {
+        us(self.windows())
+    }
+
+    fn z(&self) -> Vec<u64> {
+        zs(self.windows())
+    }
+}
+
+impl ShortValueBase {
+    fn windows(&self) -> &'static Windows {
+        static WINDOWS: OnceLock<Windows> = OnceLock::new();
+        windows(&WINDOWS, self.generator(), NUM_WINDOWS_SHORT)
+    }
+}
+
+impl FixedPoint<pallas::Affine> for ShortValueBase {
+    type FixedScalarKind = ShortScalar;
+
+    fn generator(&self) -> pallas::Affine {
+        pallas::Affine::generator()
+    }
+
+    fn u(&self) -> Vec<[[u8; 32]; H]> {
+        us(self.windows())
+    }
+
+    fn z(&self) -> Vec<u64> {
+        zs(self.windows())
+    }
+}
+
+/// The ECC chip over the commitment's fixed bases.
+pub type PedersenEccChip = EccChip<PedersenBases>;
+
+/// A point of the ECC chip, as `PedersenChip::commit` returns it.
+pub type PedersenPoint = Point<pallas::Affine, PedersenEccChip>;
+
+#[derive(Clone, Debug)]
+pub struct PedersenConfig {
+    ecc: EccConfig<PedersenBases>,
+    table_idx: TableColumn,
+}
+
+/// Opens a commitment in circuit on the `halo2_gadgets` ECC chip:
+/// `value * G` is its fixed-base multiplication by a base field element,
+/// which decomposes the value into 3-bit windows, `blind * H` the one by a
+/// full-width scalar, and the two are added with complete addition.
+///
+/// The circuit must enable a constant column and call `load_table` once,
+/// which fills the `2^10` rows of the chip's range check table.
+#[derive(Clone, Debug)]
+pub struct PedersenChip {
+    config: PedersenConfig,
+}
+
+impl PedersenChip {
+    pub fn construct(config: PedersenConfig) -> Self {
+        PedersenChip { config }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<Fp>) -> PedersenConfig {
+        let advices = [(); 10].map(|_| meta.advice_column());
+        let lagrange_coeffs = [(); 8].map(|_| meta.fixed_column());
+        let table_idx = meta.lookup_table_column();
+        let range_check = LookupRangeCheckConfig::configure(meta, advices[9], table_idx);
+        let ecc = PedersenEccChip::configure(meta, advices, lagrange_coeffs, range_check);
+        PedersenConfig { ecc, table_idx }
+    }
+
+    pub fn ecc(&self) -> PedersenEccChip {
+        PedersenEccChip::construct(self.config.ecc.clone())
+    }
+
+    pub fn load_table(&self, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+        layouter.assign_table(
+            || "table_idx",
+            |mut table| {
+                for i in 0..(1 << sinsemilla::K) {
+                    table.assign_cell(
+                        || "table_idx",
+                        self.config.table_idx,
+                        i,
+                        || Value::known(Fp::from(i as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Returns `value * G + blind * H`.
+    pub fn commit(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        value: &AssignedCell<Fp, Fp>,
+        blind: Value<Fq>,
+    ) -> Result<PedersenPoint, Error> {
+        let ecc = self.ecc();
+
+        let value_base = FixedPointBaseField::from_inner(ecc.clone(), ValueBase);
+        let value_point = value_base.mul(layouter.namespace(|| "value * G"), value.clone())?;
+
+        let blind = ScalarFixed::new(ecc.clone(), layouter.namespace(|| "blind"), blind)?;
+        let blind_base = ecc::FixedPoint::from_inner(ecc, BlindBase);
+        let (blind_point, _) = blind_base.mul(layouter.namespace(|| "blind * H"), blind)?;
+
+        value_point.add(layouter.namespace(|| "value * G + blind * H"), &blind_point)
+    }
+}
@@ -0,0 +1,125 @@
+use halo2_gadgets::poseidon::{
+    primitives::{self as poseidon, ConstantLength, P128Pow5T3, Spec},
+    Hash, Pow5Chip, Pow5Config,
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter},
+    pasta::group::ff::PrimeField,
+    plonk::{Advice, Column, ConstraintSystem, Error},
+};
+
+/// Width of the Poseidon state.
+pub const WIDTH: usize = 3;
+/// Field elements absorbed per permutation.
+pub const RATE: usize = 2;
+/// Message elements absorbed per link of `hash_slice`.
+pub const CHUNK: usize = 6;
+
+#[derive(Clone, Debug)]
+pub struct PoseidonConfig<F: PrimeField> {
+    pow5: Pow5Config<F, WIDTH, RATE>,
+    state: Column<Advice>,
+}
+
+/// Poseidon (`P128Pow5T3`) over the circuit field, with the
+/// `ConstantLength` domain of `halo2_gadgets`, so hashes match
+/// `poseidon::primitives::Hash` and `hash` below.
+#[derive(Clone, Debug)]
+pub struct PoseidonChip<F: PrimeField> {
+    config: PoseidonConfig<F>,
+}
+
+impl<F: PrimeField> PoseidonChip<F>
+where
+    P128Pow5T3: Spec<F, WIDTH, RATE>,
+{
+    pub fn construct(config: PoseidonConfig<F>) -> Self {
+        PoseidonChip { config }
+    }
+
+    /// Allocates its own columns: the state, a partial S-box column and two
+    /// sets of round constants. The first of those fixed columns is enabled
+    /// as a constant column.
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> PoseidonConfig<F> {
+        let state = [(); WIDTH].map(|_| meta.advice_column());
+        let partial_sbox = meta.advice_column();
+        let rc_a = [(); WIDTH].map(|_| meta.fixed_column());
+        let rc_b = [(); WIDTH].map(|_| meta.fixed_column());
+        meta.enable_constant(rc_b[0]);
+
+        let pow5 = Pow5Chip::configure::<P128Pow5T3>(meta, state, partial_sbox, rc_a, rc_b);
+        PoseidonConfig {
+            pow5,
+            state: state[0],
+        }
+    }
+
+    /// Returns the Poseidon hash of `message`. The length is part of the
+    /// domain, so messages of different lengths never collide.
+    pub fn hash<const L: usize>(
+        &self,
+        mut layouter: impl Layouter<F>,
+        message: &[AssignedCell<F, F>; L],
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let chip = Pow5Chip::construct(self.config.pow5.clone());
+        let hasher = Hash::<_, _, P128Pow5T3, ConstantLength<L>, WIDTH, RATE>::init(
+            chip,
+            layouter.namespace(|| "init"),
+        )?;
+        hasher.hash(layouter.namespace(|| "hash"), message.clone())
+    }
+
+    /// Returns the hash of a message of any length, as the chain
+    /// `h_0 = len, h_{i+1} = hash([h_i, chunk_i])` over chunks of `CHUNK`
+    /// elements, the last one padded with zeros.
+    pub fn hash_slice(
+        &self,
+        mut layouter: impl Layouter<F>,
+        message: &[AssignedCell<F, F>],
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let mut load = |value: F| {
+            layouter.assign_region(
+                || "constant",
+                |mut region| {
+                    region.assign_advice_from_constant(|| "constant", self.config.state, 0, value)
+                },
+            )
+        };
+        let mut h = load(F::from(message.len() as u64))?;
+        let zero = load(F::ZERO)?;
+
+        for (i, chunk) in message.chunks(CHUNK).enumerate() {
+            let mut link = vec![h];
+            link.extend_from_slice(chunk);
+            link.resize(CHUNK + 1, zero.clone());
+            h = self.hash::<{ CHUNK + 1 }>(
+                layouter.namespace(|| format!("link {}", i)),
+                &link.try_into().unwrap(),
+            )?;
+        }
+        Ok(h)
+    }
+}
+
+/// Native counterpart of `PoseidonChip::hash`.
+pub fn hash<F: PrimeField, const L: usize>(message: [F; L]) -> F
+where
+    P128Pow5T3: Spec<F, WIDTH, RATE>,
+{
+    poseidon::Hash::<_, P128Pow5T3, ConstantLength<L>, WIDTH, RATE>::init().hash(message)
+}
+
+/// Native counterpart of `PoseidonChip::hash_slice`.
+pub fn hash_slice<F: PrimeField>(message: &[F]) -> F
+where
+    P128Pow5T3: Spec<F, WIDTH, RATE>,
+{
+    message
+        .chunks(CHUNK)
+        .fold(F::from(message.len() as u64), |h, chunk| {
+            let mut link = [F::ZERO; CHUNK + 1];
+            link[0] = h;
+            link[1..=chunk.len()].copy_from_slice(chunk);
+            hash(link)
+        })
+}
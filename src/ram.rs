@@ -0,0 +1,346 @@
+//! Read-write memory for trace circuits. A step circuit records each memory
+//! access as `(addr, time, is_write, value)`; `RamChip` sorts a copy of the
+//! accesses by address, then time, proves the copy a permutation of the
+//! original and checks every sorted access against the one before it: a
+//! read returns the last value written to its address, or zero if there was
+//! none.
+
+use std::marker::PhantomData;
+
+use halo2_gadgets::poseidon::primitives::{P128Pow5T3, Spec};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    pasta::group::ff::PrimeField,
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Selector},
+    poly::Rotation,
+};
+
+use crate::{
+    bits::le_bits,
+    poseidon::{self, PoseidonChip, PoseidonConfig, RATE, WIDTH},
+    range::{RangeCheckChip, RangeCheckConfig},
+};
+
+/// Bits of the gap between consecutive sorted addresses, or between
+/// consecutive timestamps at one address.
+pub const GAP_BITS: usize = 32;
+
+/// One access, as assigned by the step circuit. `is_write` is boolean; a
+/// read's `value` is the value it returned.
+#[derive(Clone, Debug)]
+pub struct MemoryAccess<F: PrimeField> {
+    pub addr: AssignedCell<F, F>,
+    pub time: AssignedCell<F, F>,
+    pub is_write: AssignedCell<F, F>,
+    pub value: AssignedCell<F, F>,
+}
+
+impl<F: PrimeField> MemoryAccess<F> {
+    fn cells(&self) -> [&AssignedCell<F, F>; 4] {
+        [&self.addr, &self.time, &self.is_write, &self.value]
+    }
+
+    fn values(&self) -> Value<[F; 4]> {
+        let [addr, time, is_write, value] = self.cells().map(|cell| cell.value().copied());
+        addr.zip(time)
+            .zip(is_write)
+            .zip(value)
+            .map(|(((addr, time), is_write), value)| [addr, time, is_write, value])
+    }
+}
+
+/// Orders field elements as integers.
+fn integer_key<F: PrimeField>(value: &F) -> Vec<bool> {
+    let mut bits = le_bits(value);
+    bits.reverse();
+    bits
+}
+
+#[derive(Clone, Debug)]
+pub struct RamConfig<F: PrimeField> {
+    original: [Column<Advice>; 4],
+    sorted: [Column<Advice>; 4],
+    same: Column<Advice>,
+    gap: Column<Advice>,
+    z: Column<Advice>,
+    alpha: Column<Advice>,
+    gamma: Column<Advice>,
+    s_first: Selector,
+    s_next: Selector,
+    s_product: Selector,
+    range: RangeCheckConfig,
+    poseidon: PoseidonConfig<F>,
+}
+
+/// One region, a row per access:
+///
+/// original[0..4] sorted[0..4] same gap z alpha gamma
+///
+/// `same` marks a sorted access to the address of the row above, and `gap`
+/// is `time - time_prev - 1` if so and `addr - addr_prev - 1` otherwise.
+/// Range-checking `gap` to `GAP_BITS` orders the copy strictly by address,
+/// then time, so addresses and timestamps are taken to be below
+/// `2^GAP_BITS`, and timestamps at one address to be distinct.
+///
+/// `z` runs from one in the first row to one past the last, multiplied on
+/// each row by `(gamma - e(original)) / (gamma - e(sorted))` with
+/// `e(a, t, w, v) = a + alpha * t + alpha^2 * w + alpha^3 * v`. `alpha` is
+/// the `hash_slice` of every original and sorted field and `gamma` is its
+/// hash, so the prover commits to both lists before learning either
+/// challenge.
+///
+/// The circuit must load the range table once, with `load_table` or a
+/// `RangeCheckChip` of its own, and enable a constant column.
+#[derive(Clone, Debug)]
+pub struct RamChip<F: PrimeField> {
+    config: RamConfig<F>,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: PrimeField> RamChip<F>
+where
+    P128Pow5T3: Spec<F, WIDTH, RATE>,
+{
+    pub fn construct(config: RamConfig<F>) -> Self {
+        RamChip {
+            config,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> RamConfig<F> {
+        let original = [(); 4].map(|_| meta.advice_column());
+        let sorted = [(); 4].map(|_| meta.advice_column());
+        let same = meta.advice_column();
+        let gap = meta.advice_column();
+        let z = meta.advice_column();
+        let alpha = meta.advice_column();
+        let gamma = meta.advice_column();
+        for column in original
+            .into_iter()
+            .chain(sorted)
+            .chain([gap, z, alpha, gamma])
+        {
+            meta.enable_equality(column);
+        }
+        let s_first = meta.selector();
+        let s_next = meta.selector();
+        let s_product = meta.selector();
+
+        let one = || Expression::Constant(F::ONE);
+
+        meta.create_gate("ram first access", |meta| {
+            let s_first = meta.query_selector(s_first);
+            let is_write = meta.query_advice(sorted[2], Rotation::cur());
+            let value = meta.query_advice(sorted[3], Rotation::cur());
+            Constraints::with_selector(
+                s_first,
+                vec![
+                    is_write.clone() * (one() - is_write.clone()),
+                    (one() - is_write) * value,
+                ],
+            )
+        });
+
+        meta.create_gate("ram next access", |meta| {
+            let s_next = meta.query_selector(s_next);
+            let [addr_prev, time_prev, _, value_prev] =
+                sorted.map(|column| meta.query_advice(column, Rotation::prev()));
+            let [addr, time, is_write, value] =
+                sorted.map(|column| meta.query_advice(column, Rotation::cur()));
+            let same = meta.query_advice(same, Rotation::cur());
+            let gap = meta.query_advice(gap, Rotation::cur());
+
+            let expected_gap = same.clone() * (time - time_prev - one())
+                + (one() - same.clone()) * (addr.clone() - addr_prev.clone() - one());
+            Constraints::with_selector(
+                s_next,
+                vec![
+                    is_write.clone() * (one() - is_write.clone()),
+                    same.clone() * (one() - same.clone()),
+                    same.clone() * (addr - addr_prev),
+                    gap - expected_gap,
+                    // A read returns the previous value at its address, or
+                    // zero at a new address.
+                    (one() - is_write) * (value - same * value_prev),
+                ],
+            )
+        });
+
+        meta.create_gate("ram permutation", |meta| {
+            let s_product = meta.query_selector(s_product);
+            let alpha = meta.query_advice(alpha, Rotation::cur());
+            let gamma = meta.query_advice(gamma, Rotation::cur());
+            let z_cur = meta.query_advice(z, Rotation::cur());
+            let z_next = meta.query_advice(z, Rotation::next());
+            let mut compress = |columns: [Column<Advice>; 4]| {
+                columns
+                    .iter()
+                    .rev()
+                    .fold(Expression::Constant(F::ZERO), |acc, column| {
+                        acc * alpha.clone() + meta.query_advice(*column, Rotation::cur())
+                    })
+            };
+            let e_original = compress(original);
+            let e_sorted = compress(sorted);
+
+            Constraints::with_selector(
+                s_product,
+                vec![z_next * (gamma.clone() - e_sorted) - z_cur * (gamma - e_original)],
+            )
+        });
+
+        let range = RangeCheckChip::configure(meta);
+        let poseidon = PoseidonChip::configure(meta);
+
+        RamConfig {
+            original,
+            sorted,
+            same,
+            gap,
+            z,
+            alpha,
+            gamma,
+            s_first,
+            s_next,
+            s_product,
+            range,
+            poseidon,
+        }
+    }
+
+    pub fn load_table(&self, layouter: impl Layouter<F>) -> Result<(), Error> {
+        RangeCheckChip::construct(self.config.range.clone()).load_table(layouter)
+    }
+
+    /// Constrains `accesses`, in any order, to be consistent.
+    pub fn assert_consistent(
+        &self,
+        mut layouter: impl Layouter<F>,
+        accesses: &[MemoryAccess<F>],
+    ) -> Result<(), Error> {
+        let config = &self.config;
+        let original: Value<Vec<[F; 4]>> = accesses.iter().map(|access| access.values()).collect();
+        let sorted = original.as_ref().map(|original| {
+            let mut sorted = original.clone();
+            sorted.sort_by_key(|[addr, time, _, _]| (integer_key(addr), integer_key(time)));
+            sorted
+        });
+        let alpha = original
+            .as_ref()
+            .zip(sorted.as_ref())
+            .map(|(original, sorted)| {
+                let fields: Vec<F> = original.iter().chain(sorted).flatten().copied().collect();
+                poseidon::hash_slice(&fields)
+            });
+        let gamma = alpha.map(|alpha| poseidon::hash([alpha]));
+
+        let (sorted_cells, gaps, challenges) = layouter.assign_region(
+            || "ram",
+            |mut region| {
+                let mut sorted_cells = Vec::with_capacity(accesses.len() * 4);
+                let mut gaps = Vec::with_capacity(accesses.len());
+                let mut challenges: Option<[AssignedCell<F, F>; 2]> = None;
+                let mut z = region.assign_advice_from_constant(|| "z_0", config.z, 0, F::ONE)?;
+
+                for (row, access) in accesses.iter().enumerate() {
+                    config.s_product.enable(&mut region, row)?;
+                    if row == 0 {
+                        config.s_first.enable(&mut region, row)?;
+                    } else {
+                        config.s_next.enable(&mut region, row)?;
+                    }
+
+                    for (cell, column) in access.cells().into_iter().zip(config.original) {
+                        cell.copy_advice(|| "original", &mut region, column, row)?;
+                    }
+                    let fields = sorted.as_ref().map(|sorted| sorted[row]);
+                    for (i, column) in config.sorted.into_iter().enumerate() {
+                        sorted_cells.push(region.assign_advice(
+                            || "sorted",
+                            column,
+                            row,
+                            || fields.map(|fields| fields[i]),
+                        )?);
+                    }
+
+                    if row > 0 {
+                        let pair = sorted.as_ref().map(|sorted| (sorted[row - 1], sorted[row]));
+                        let same = pair.map(|(prev, cur)| prev[0] == cur[0]);
+                        region.assign_advice(
+                            || "same",
+                            config.same,
+                            row,
+                            || same.map(|same| F::from(same as u64)),
+                        )?;
+                        let gap = pair.map(|(prev, cur)| {
+                            if prev[0] == cur[0] {
+                                cur[1] - prev[1] - F::ONE
+                            } else {
+                                cur[0] - prev[0] - F::ONE
+                            }
+                        });
+                        gaps.push(region.assign_advice(|| "gap", config.gap, row, || gap)?);
+                    }
+
+                    let assigned =
+                        [(config.alpha, alpha), (config.gamma, gamma)].map(|(column, value)| {
+                            region.assign_advice(|| "challenge", column, row, || value)
+                        });
+                    let [alpha_cell, gamma_cell] = assigned;
+                    let (alpha_cell, gamma_cell) = (alpha_cell?, gamma_cell?);
+                    match &challenges {
+                        Some([alpha, gamma]) => {
+                            region.constrain_equal(alpha.cell(), alpha_cell.cell())?;
+                            region.constrain_equal(gamma.cell(), gamma_cell.cell())?;
+                        }
+                        None => challenges = Some([alpha_cell, gamma_cell]),
+                    }
+
+                    let next = z
+                        .value()
+                        .copied()
+                        .zip(access.values())
+                        .zip(fields)
+                        .zip(alpha.zip(gamma))
+                        .map(|(((z, original), sorted), (alpha, gamma))| {
+                            let compress = |fields: [F; 4]| {
+                                fields
+                                    .iter()
+                                    .rev()
+                                    .fold(F::ZERO, |acc, field| acc * alpha + field)
+                            };
+                            z * (gamma - compress(original))
+                                * (gamma - compress(sorted)).invert().unwrap_or(F::ZERO)
+                        });
+                    z = region.assign_advice(|| "z", config.z, row + 1, || next)?;
+                }
+
+                region.constrain_constant(z.cell(), F::ONE)?;
+                Ok((sorted_cells, gaps, challenges))
+            },
+        )?;
+
+        let range = RangeCheckChip::construct(config.range.clone());
+        range.range_check(layouter.namespace(|| "gaps"), &gaps, GAP_BITS)?;
+
+        let Some([alpha_cell, gamma_cell]) = challenges else {
+            return Ok(());
+        };
+        let poseidon = PoseidonChip::construct(config.poseidon.clone());
+        let fields: Vec<_> = accesses
+            .iter()
+            .flat_map(|access| access.cells().map(Clone::clone))
+            .chain(sorted_cells)
+            .collect();
+        let alpha = poseidon.hash_slice(layouter.namespace(|| "alpha"), &fields)?;
+        let gamma = poseidon.hash(layouter.namespace(|| "gamma"), std::array::from_ref(&alpha))?;
+        layouter.assign_region(
+            || "challenges",
+            |mut region| {
+                region.constrain_equal(alpha.cell(), alpha_cell.cell())?;
+                region.constrain_equal(gamma.cell(), gamma_cell.cell())
+            },
+        )
+    }
+}
@@ -0,0 +1,156 @@
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    pasta::group::ff::PrimeField,
+    plonk::{
+        Advice, Column, ConstraintSystem, Constraints, Error, Expression, Selector, TableColumn,
+    },
+    poly::Rotation,
+};
+
+/// Width of a single lookup chunk.
+pub const LOOKUP_BITS: usize = 16;
+/// Number of chunks a checked value is split into.
+pub const NUM_CHUNKS: usize = 7;
+/// Largest bit width `RangeCheckChip::range_check` can enforce.
+pub const MAX_BITS: usize = LOOKUP_BITS * NUM_CHUNKS;
+
+/// One row per checked value: `value = sum(chunk_i * 2^(16 * i))`, every chunk
+/// looked up in a `[0, 2^16)` table.
+#[derive(Clone, Debug)]
+pub struct RangeCheckConfig {
+    value: Column<Advice>,
+    chunks: [Column<Advice>; NUM_CHUNKS],
+    table: TableColumn,
+    selector: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub struct RangeCheckChip<F: PrimeField> {
+    config: RangeCheckConfig,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: PrimeField> RangeCheckChip<F> {
+    pub fn construct(config: RangeCheckConfig) -> Self {
+        RangeCheckChip {
+            config,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The circuit must enable a constant column: unused high chunks are pinned
+    /// to zero through it.
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> RangeCheckConfig {
+        let value = meta.advice_column();
+        let chunks = [(); NUM_CHUNKS].map(|_| meta.advice_column());
+        let table = meta.lookup_table_column();
+        let selector = meta.complex_selector();
+
+        meta.enable_equality(value);
+        for chunk in chunks {
+            meta.enable_equality(chunk);
+        }
+
+        meta.create_gate("range check", |meta| {
+            let selector = meta.query_selector(selector);
+            let value = meta.query_advice(value, Rotation::cur());
+            let shift = Expression::Constant(F::from(1 << LOOKUP_BITS));
+            let sum = chunks
+                .iter()
+                .rev()
+                .fold(Expression::Constant(F::ZERO), |acc, chunk| {
+                    acc * shift.clone() + meta.query_advice(*chunk, Rotation::cur())
+                });
+
+            Constraints::with_selector(selector, vec![value - sum])
+        });
+
+        for chunk in chunks {
+            meta.lookup(|meta| {
+                let selector = meta.query_selector(selector);
+                let chunk = meta.query_advice(chunk, Rotation::cur());
+                vec![(selector * chunk, table)]
+            });
+        }
+
+        RangeCheckConfig {
+            value,
+            chunks,
+            table,
+            selector,
+        }
+    }
+
+    pub fn load_table(&self, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        layouter.assign_table(
+            || "range table",
+            |mut table| {
+                for i in 0..(1 << LOOKUP_BITS) {
+                    table.assign_cell(
+                        || "range value",
+                        self.config.table,
+                        i,
+                        || Value::known(F::from(i as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Constrains every cell in `values` to lie in `[0, 2^num_bits)`.
+    ///
+    /// `num_bits` must be a multiple of `LOOKUP_BITS` and at most `MAX_BITS`.
+    pub fn range_check(
+        &self,
+        mut layouter: impl Layouter<F>,
+        values: &[AssignedCell<F, F>],
+        num_bits: usize,
+    ) -> Result<(), Error> {
+        assert!(num_bits.is_multiple_of(LOOKUP_BITS) && num_bits <= MAX_BITS);
+        let used_chunks = num_bits / LOOKUP_BITS;
+        let config = &self.config;
+
+        layouter.assign_region(
+            || "range check",
+            |mut region| {
+                for (offset, value) in values.iter().enumerate() {
+                    config.selector.enable(&mut region, offset)?;
+                    value.copy_advice(|| "value", &mut region, config.value, offset)?;
+
+                    let chunks = value.value().map(|v| decompose(v));
+                    for (i, column) in config.chunks.iter().enumerate() {
+                        if i < used_chunks {
+                            let chunk = chunks.as_ref().map(|chunks| F::from(chunks[i]));
+                            region.assign_advice(|| "chunk", *column, offset, || chunk)?;
+                        } else {
+                            region.assign_advice_from_constant(
+                                || "unused chunk",
+                                *column,
+                                offset,
+                                F::ZERO,
+                            )?;
+                        }
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+/// Splits the little-endian representation of `value` into 16-bit chunks.
+fn decompose<F: PrimeField>(value: &F) -> Vec<u64> {
+    let repr = value.to_repr();
+    repr.as_ref()
+        .chunks(LOOKUP_BITS / 8)
+        .map(|bytes| {
+            bytes
+                .iter()
+                .rev()
+                .fold(0u64, |acc, b| (acc << 8) | *b as u64)
+        })
+        .collect()
+}
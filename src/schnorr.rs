@@ -0,0 +1,147 @@
+//! Schnorr signatures over Pallas with a Poseidon challenge, the native
+//! reference for `examples/schnorr.rs`.
+//!
+//! A message of `L` base field elements is hashed to `m = Poseidon(msg)`.
+//! The challenge is `e = Poseidon(R.x, R.y, pk.x, pk.y, m)`, read as an
+//! integer, and a signature `(R, s)` is valid when `s * G = R + e * pk`.
+//! Challenges of `2^254` or more are rejected, so that the circuit can take
+//! their bits with a unique 254-bit decomposition; the signer retries with
+//! another nonce, which happens with probability below `2^-128`.
+
+use halo2_proofs::pasta::{
+    group::{
+        ff::{FromUniformBytes, PrimeField},
+        prime::PrimeCurveAffine,
+        Curve, Group,
+    },
+    pallas, Fp, Fq,
+};
+use sha2::{Digest, Sha512};
+
+use crate::{bits::le_bits, ecc::coordinates, poseidon};
+
+/// Bits of a valid challenge.
+pub const CHALLENGE_BITS: usize = 254;
+
+/// Domain separation tag for secret keys and nonces.
+pub const KEYGEN_DST: &[u8] = b"HALO2-LEARNING-SCHNORR-V01";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub r: pallas::Affine,
+    pub s: Fq,
+}
+
+fn hash_to_scalar(parts: &[&[u8]]) -> Fq {
+    let mut hasher = Sha512::new();
+    hasher.update(KEYGEN_DST);
+    for part in parts {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    let mut digest = [0; 64];
+    digest.copy_from_slice(&hasher.finalize());
+    Fq::from_uniform_bytes(&digest)
+}
+
+/// Derives a key pair from `ikm`.
+pub fn keygen(ikm: &[u8]) -> (Fq, pallas::Affine) {
+    let sk = hash_to_scalar(&[b"key", ikm]);
+    assert!(sk != Fq::zero(), "degenerate key material");
+    (sk, public_key(&sk))
+}
+
+pub fn public_key(sk: &Fq) -> pallas::Affine {
+    (pallas::Affine::generator() * sk).to_affine()
+}
+
+pub fn message_hash<const L: usize>(msg: [Fp; L]) -> Fp {
+    poseidon::hash(msg)
+}
+
+/// The challenge as a base field element, valid if below `2^254`.
+pub fn challenge(r: &pallas::Affine, pubkey: &pallas::Affine, m: Fp) -> Fp {
+    let (r_x, r_y) = coordinates(r);
+    let (pk_x, pk_y) = coordinates(pubkey);
+    poseidon::hash([r_x, r_y, pk_x, pk_y, m])
+}
+
+/// The same integer as a scalar, which fits as `p < q`.
+fn challenge_scalar(e: Fp) -> Option<Fq> {
+    if le_bits(&e)[CHALLENGE_BITS..].iter().any(|bit| *bit) {
+        return None;
+    }
+    Some(Fq::from_repr(e.to_repr()).unwrap())
+}
+
+/// Signs with a nonce derived from the key and the message.
+pub fn sign<const L: usize>(sk: &Fq, msg: [Fp; L]) -> Signature {
+    let pubkey = public_key(sk);
+    let m = message_hash(msg);
+    for counter in 0u64.. {
+        let k = hash_to_scalar(&[
+            b"nonce",
+            sk.to_repr().as_ref(),
+            m.to_repr().as_ref(),
+            &counter.to_le_bytes(),
+        ]);
+        let r = (pallas::Affine::generator() * k).to_affine();
+        if bool::from(r.is_identity()) {
+            continue;
+        }
+        if let Some(e) = challenge_scalar(challenge(&r, &pubkey, m)) {
+            return Signature { r, s: k + e * sk };
+        }
+    }
+    unreachable!()
+}
+
+pub fn verify<const L: usize>(
+    pubkey: &pallas::Affine,
+    msg: [Fp; L],
+    signature: &Signature,
+) -> bool {
+    if bool::from(pubkey.is_identity()) || bool::from(signature.r.is_identity()) {
+        return false;
+    }
+    let e = match challenge_scalar(challenge(&signature.r, pubkey, message_hash(msg))) {
+        Some(e) => e,
+        None => return false,
+    };
+    let lhs = pallas::Affine::generator() * signature.s;
+    let rhs = pallas::Point::from(signature.r) + pubkey * e;
+    bool::from((lhs - rhs).is_identity())
+}
+
+/// A fixed signing case. The public key is hex encoded in its compressed
+/// form; signatures are deterministic and printed by `examples/schnorr.rs`.
+pub struct TestVector {
+    pub ikm: &'static [u8],
+    pub msg: [u64; 2],
+    pub pubkey: &'static str,
+}
+
+/// Fixed outputs of `keygen`. The circuit must accept `sign(sk, msg)` under
+/// each of them.
+pub const TEST_VECTORS: [TestVector; 3] = [
+    TestVector {
+        ikm: b"halo2-learning test key 0",
+        msg: [0, 0],
+        pubkey: "5c697210d082b306803536dba9490568fa7b5ae740f6e42591b667c63ceb9a8e",
+    },
+    TestVector {
+        ikm: b"halo2-learning test key 1",
+        msg: [1, 2],
+        pubkey: "327bac45cbabe3c9034889cbea1162a4d7adbcb7d7411da9fdac9aea8ff8590a",
+    },
+    TestVector {
+        ikm: b"halo2-learning test key 2",
+        msg: [0x0068_616c_6f32, 0x0073_6368_6e6f_7272],
+        pubkey: "6316c1985d19c2f50ba04e2b02ff376edddf8a5f9e34ec474e8ad7cf1e9ac4a3",
+    },
+];
+
+/// Lower-case hex encoding, used to compare against `TEST_VECTORS`.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,135 @@
+//! Permutation (shuffle) argument between two lists of cells.
+//!
+//! halo2 0.3 has no challenge API, so the challenge comes from the circuit
+//! itself: `gamma` is the Poseidon `hash_slice` of both lists, and
+//! `prod(a_i + gamma) = prod(b_i + gamma)` holds for a `gamma` the prover
+//! could not choose only if the lists are equal as multisets, except with
+//! probability `n / p`.
+
+use std::marker::PhantomData;
+
+use halo2_gadgets::poseidon::primitives::{P128Pow5T3, Spec};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter},
+    pasta::group::ff::PrimeField,
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Selector},
+    poly::Rotation,
+};
+
+use crate::poseidon::{PoseidonChip, PoseidonConfig, RATE, WIDTH};
+
+#[derive(Clone, Debug)]
+pub struct ShuffleConfig<F: PrimeField> {
+    a: Column<Advice>,
+    b: Column<Advice>,
+    z: Column<Advice>,
+    gamma: Column<Advice>,
+    selector: Selector,
+    poseidon: PoseidonConfig<F>,
+}
+
+/// One row per pair of entries:
+///
+/// a b z gamma
+///
+/// with `z_next * (b + gamma) = z * (a + gamma)`, `z` running from one in
+/// the first row to one past the last. `gamma` is copied to every row from
+/// the hash of both lists.
+///
+/// The circuit must enable a constant column.
+#[derive(Clone, Debug)]
+pub struct ShuffleChip<F: PrimeField> {
+    config: ShuffleConfig<F>,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: PrimeField> ShuffleChip<F>
+where
+    P128Pow5T3: Spec<F, WIDTH, RATE>,
+{
+    pub fn construct(config: ShuffleConfig<F>) -> Self {
+        ShuffleChip {
+            config,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> ShuffleConfig<F> {
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+        let z = meta.advice_column();
+        let gamma = meta.advice_column();
+        for column in [a, b, z, gamma] {
+            meta.enable_equality(column);
+        }
+        let selector = meta.selector();
+
+        meta.create_gate("shuffle", |meta| {
+            let selector = meta.query_selector(selector);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let z_cur = meta.query_advice(z, Rotation::cur());
+            let z_next = meta.query_advice(z, Rotation::next());
+            let gamma = meta.query_advice(gamma, Rotation::cur());
+            Constraints::with_selector(
+                selector,
+                vec![z_next * (b + gamma.clone()) - z_cur * (a + gamma)],
+            )
+        });
+
+        let poseidon = PoseidonChip::configure(meta);
+
+        ShuffleConfig {
+            a,
+            b,
+            z,
+            gamma,
+            selector,
+            poseidon,
+        }
+    }
+
+    pub fn poseidon(&self) -> PoseidonChip<F> {
+        PoseidonChip::construct(self.config.poseidon.clone())
+    }
+
+    /// Constrains `b` to be a permutation of `a`. Both must have the same
+    /// length, which is fixed at keygen.
+    pub fn assert_shuffle(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &[AssignedCell<F, F>],
+        b: &[AssignedCell<F, F>],
+    ) -> Result<(), Error> {
+        assert_eq!(a.len(), b.len(), "lists of different lengths");
+        let config = &self.config;
+        let poseidon = self.poseidon();
+        let message: Vec<_> = a.iter().chain(b).cloned().collect();
+        let gamma = poseidon.hash_slice(layouter.namespace(|| "gamma"), &message)?;
+
+        layouter.assign_region(
+            || "shuffle",
+            |mut region| {
+                let mut z = region.assign_advice_from_constant(|| "z_0", config.z, 0, F::ONE)?;
+                for (row, (a, b)) in a.iter().zip(b).enumerate() {
+                    config.selector.enable(&mut region, row)?;
+                    a.copy_advice(|| "a", &mut region, config.a, row)?;
+                    b.copy_advice(|| "b", &mut region, config.b, row)?;
+                    gamma.copy_advice(|| "gamma", &mut region, config.gamma, row)?;
+
+                    let next = z
+                        .value()
+                        .copied()
+                        .zip(a.value().copied())
+                        .zip(b.value().copied())
+                        .zip(gamma.value().copied())
+                        .map(|(((z, a), b), gamma)| {
+                            z * (a + gamma) * (b + gamma).invert().unwrap_or(F::ZERO)
+                        });
+                    z = region.assign_advice(|| "z", config.z, row + 1, || next)?;
+                }
+                region.constrain_constant(z.cell(), F::ONE)
+            },
+        )
+    }
+}
@@ -0,0 +1,413 @@
+//! A sparse Merkle tree of depth 254 over Poseidon, keyed by field elements
+//! below `2^254`. The leaf of `key` sits at index `key`: it is zero when the
+//! key is absent and `hash(key, value)` otherwise.
+//!
+//! The native tree and the chip both compute roots through `SmtHasher` and
+//! `path_nodes`, so the leaf encoding, bit order and pair ordering are
+//! written once.
+
+use std::{collections::HashMap, convert::Infallible, marker::PhantomData};
+
+use halo2_gadgets::poseidon::primitives::{P128Pow5T3, Spec};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    pasta::group::ff::PrimeField,
+    plonk::{Column, ConstraintSystem, Error, Instance},
+};
+
+use crate::{
+    bits::{le_bits, BitsChip, BitsConfig},
+    merkle::{MerkleChip, MerkleConfig},
+    poseidon::{self, RATE, WIDTH},
+};
+
+/// Number of key bits, and levels of the tree.
+pub const DEPTH: usize = 254;
+
+/// The operations a root is computed from.
+pub trait SmtHasher {
+    type Node: Clone;
+    type Bit;
+    type Error;
+
+    /// The `DEPTH` low bits of `key`, least significant first. Keys of
+    /// `2^254` or more have no decomposition.
+    fn key_bits(&mut self, key: &Self::Node) -> Result<Vec<Self::Bit>, Self::Error>;
+
+    fn empty_leaf(&mut self) -> Result<Self::Node, Self::Error>;
+
+    fn hash_leaf(
+        &mut self,
+        key: &Self::Node,
+        value: &Self::Node,
+    ) -> Result<Self::Node, Self::Error>;
+
+    fn hash_node(
+        &mut self,
+        left: &Self::Node,
+        right: &Self::Node,
+    ) -> Result<Self::Node, Self::Error>;
+
+    /// Returns `[node, sibling]`, or `[sibling, node]` if `bit` is set.
+    fn order(
+        &mut self,
+        node: &Self::Node,
+        sibling: &Self::Node,
+        bit: &Self::Bit,
+    ) -> Result<[Self::Node; 2], Self::Error>;
+}
+
+/// Hashes `leaf` up the path given by `bits` and `siblings`, returning every
+/// node on the way: `DEPTH + 1` of them, the root last.
+pub fn path_nodes<H: SmtHasher>(
+    hasher: &mut H,
+    leaf: H::Node,
+    siblings: &[H::Node],
+    bits: &[H::Bit],
+) -> Result<Vec<H::Node>, H::Error> {
+    assert_eq!(siblings.len(), DEPTH);
+    assert_eq!(bits.len(), DEPTH);
+
+    let mut nodes = vec![leaf];
+    for (sibling, bit) in siblings.iter().zip(bits) {
+        let [left, right] = hasher.order(nodes.last().unwrap(), sibling, bit)?;
+        nodes.push(hasher.hash_node(&left, &right)?);
+    }
+    Ok(nodes)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct NativeHasher<F> {
+    _phantom: PhantomData<F>,
+}
+
+impl<F: PrimeField> SmtHasher for NativeHasher<F>
+where
+    P128Pow5T3: Spec<F, WIDTH, RATE>,
+{
+    type Node = F;
+    type Bit = bool;
+    type Error = Infallible;
+
+    fn key_bits(&mut self, key: &F) -> Result<Vec<bool>, Infallible> {
+        let mut bits = le_bits(key);
+        assert!(bits[DEPTH..].iter().all(|bit| !bit), "key exceeds 254 bits");
+        bits.truncate(DEPTH);
+        Ok(bits)
+    }
+
+    fn empty_leaf(&mut self) -> Result<F, Infallible> {
+        Ok(F::ZERO)
+    }
+
+    fn hash_leaf(&mut self, key: &F, value: &F) -> Result<F, Infallible> {
+        Ok(poseidon::hash([*key, *value]))
+    }
+
+    fn hash_node(&mut self, left: &F, right: &F) -> Result<F, Infallible> {
+        Ok(poseidon::hash([*left, *right]))
+    }
+
+    fn order(&mut self, node: &F, sibling: &F, bit: &bool) -> Result<[F; 2], Infallible> {
+        Ok(if *bit {
+            [*sibling, *node]
+        } else {
+            [*node, *sibling]
+        })
+    }
+}
+
+fn unwrap<T>(result: Result<T, Infallible>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(never) => match never {},
+    }
+}
+
+/// The native tree. Only nodes above non-empty leaves are stored; a node at
+/// `level` is keyed by the bits of its index, i.e. `key_bits[level..]`.
+#[derive(Clone, Debug)]
+pub struct SparseMerkleTree<F> {
+    nodes: HashMap<(usize, Vec<bool>), F>,
+    empty: Vec<F>,
+}
+
+impl<F: PrimeField> Default for SparseMerkleTree<F>
+where
+    P128Pow5T3: Spec<F, WIDTH, RATE>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: PrimeField> SparseMerkleTree<F>
+where
+    P128Pow5T3: Spec<F, WIDTH, RATE>,
+{
+    pub fn new() -> Self {
+        let mut hasher = NativeHasher::default();
+
+        // empty[i] is the root of an empty subtree of height i.
+        let mut empty = vec![unwrap(hasher.empty_leaf())];
+        for i in 0..DEPTH {
+            empty.push(unwrap(hasher.hash_node(&empty[i], &empty[i])));
+        }
+
+        SparseMerkleTree {
+            nodes: HashMap::new(),
+            empty,
+        }
+    }
+
+    fn node(&self, level: usize, index: &[bool]) -> F {
+        self.nodes
+            .get(&(level, index.to_vec()))
+            .copied()
+            .unwrap_or(self.empty[level])
+    }
+
+    pub fn root(&self) -> F {
+        self.node(DEPTH, &[])
+    }
+
+    /// The leaf node at `key`: zero if the key is absent.
+    pub fn leaf(&self, key: F) -> F {
+        let bits = unwrap(NativeHasher::default().key_bits(&key));
+        self.node(0, &bits)
+    }
+
+    /// The siblings on the path of `key`, leaf to root.
+    pub fn siblings(&self, key: F) -> Vec<F> {
+        let bits = unwrap(NativeHasher::default().key_bits(&key));
+        (0..DEPTH)
+            .map(|level| {
+                let mut index = bits[level..].to_vec();
+                index[0] = !index[0];
+                self.node(level, &index)
+            })
+            .collect()
+    }
+
+    pub fn insert(&mut self, key: F, value: F) {
+        let leaf = unwrap(NativeHasher::default().hash_leaf(&key, &value));
+        self.set_leaf(key, leaf);
+    }
+
+    pub fn remove(&mut self, key: F) {
+        let leaf = unwrap(NativeHasher::default().empty_leaf());
+        self.set_leaf(key, leaf);
+    }
+
+    fn set_leaf(&mut self, key: F, leaf: F) {
+        let mut hasher = NativeHasher::default();
+        let bits = unwrap(hasher.key_bits(&key));
+        let siblings = self.siblings(key);
+        let nodes = unwrap(path_nodes(&mut hasher, leaf, &siblings, &bits));
+        for (level, node) in nodes.into_iter().enumerate() {
+            self.nodes.insert((level, bits[level..].to_vec()), node);
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SmtConfig<F: PrimeField> {
+    merkle: MerkleConfig<F>,
+    bits: BitsConfig,
+}
+
+/// Sparse Merkle proofs against the native `SparseMerkleTree`.
+///
+/// Keys go through `BitsChip` into `DEPTH` bits, which also bounds them as
+/// `2^254` is below the modulus. The bits then select the pair order at each
+/// level of `MerkleChip`.
+#[derive(Clone, Debug)]
+pub struct SmtChip<F: PrimeField> {
+    config: SmtConfig<F>,
+}
+
+impl<F: PrimeField> SmtChip<F>
+where
+    P128Pow5T3: Spec<F, WIDTH, RATE>,
+{
+    pub fn construct(config: SmtConfig<F>) -> Self {
+        SmtChip { config }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> SmtConfig<F> {
+        let bits = BitsChip::configure(meta);
+        // Also enables the constant column the decomposition starts from.
+        let merkle = MerkleChip::configure(meta);
+
+        SmtConfig { merkle, bits }
+    }
+
+    pub fn load_private(
+        &self,
+        layouter: impl Layouter<F>,
+        value: Value<F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        self.merkle().load_private(layouter, value)
+    }
+
+    pub fn load_instance(
+        &self,
+        layouter: impl Layouter<F>,
+        instance: Column<Instance>,
+        row: usize,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        self.merkle().load_instance(layouter, instance, row)
+    }
+
+    /// Returns the root of a tree holding `value` at `key`.
+    pub fn inclusion_root(
+        &self,
+        layouter: impl Layouter<F>,
+        key: &AssignedCell<F, F>,
+        value: &AssignedCell<F, F>,
+        siblings: &[Value<F>],
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let mut hasher = self.hasher(layouter);
+        let leaf = hasher.hash_leaf(key, value)?;
+        hasher.root(key, leaf, siblings)
+    }
+
+    /// Returns the root of a tree without `key`.
+    pub fn exclusion_root(
+        &self,
+        layouter: impl Layouter<F>,
+        key: &AssignedCell<F, F>,
+        siblings: &[Value<F>],
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let mut hasher = self.hasher(layouter);
+        let leaf = hasher.empty_leaf()?;
+        hasher.root(key, leaf, siblings)
+    }
+
+    /// Returns `[old_root, new_root]` for setting `key` to `new_value` in a
+    /// tree whose leaf at `key` is `old_leaf`, which may be empty. Both roots
+    /// are computed over the same key bits and siblings, so the trees differ
+    /// in that leaf only.
+    pub fn update(
+        &self,
+        layouter: impl Layouter<F>,
+        key: &AssignedCell<F, F>,
+        old_leaf: Value<F>,
+        new_value: &AssignedCell<F, F>,
+        siblings: &[Value<F>],
+    ) -> Result<[AssignedCell<F, F>; 2], Error> {
+        let mut hasher = self.hasher(layouter);
+        let bits = hasher.key_bits(key)?;
+        let siblings = hasher.load_siblings(siblings)?;
+
+        let old_leaf = self.load_private(hasher.layouter.namespace(|| "old leaf"), old_leaf)?;
+        let old_root = path_nodes(&mut hasher, old_leaf, &siblings, &bits)?;
+
+        let new_leaf = hasher.hash_leaf(key, new_value)?;
+        let new_root = path_nodes(&mut hasher, new_leaf, &siblings, &bits)?;
+
+        Ok([old_root[DEPTH].clone(), new_root[DEPTH].clone()])
+    }
+
+    fn hasher<L: Layouter<F>>(&self, layouter: L) -> CircuitHasher<'_, F, L> {
+        CircuitHasher {
+            chip: self,
+            layouter,
+        }
+    }
+
+    fn merkle(&self) -> MerkleChip<F> {
+        MerkleChip::construct(self.config.merkle.clone())
+    }
+}
+
+/// `SmtHasher` over assigned cells, laying out each operation as it goes.
+struct CircuitHasher<'a, F: PrimeField, L: Layouter<F>> {
+    chip: &'a SmtChip<F>,
+    layouter: L,
+}
+
+impl<F: PrimeField, L: Layouter<F>> CircuitHasher<'_, F, L>
+where
+    P128Pow5T3: Spec<F, WIDTH, RATE>,
+{
+    fn load_siblings(&mut self, siblings: &[Value<F>]) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let merkle = self.chip.merkle();
+        siblings
+            .iter()
+            .enumerate()
+            .map(|(i, sibling)| {
+                merkle.load_private(
+                    self.layouter.namespace(|| format!("sibling {}", i)),
+                    *sibling,
+                )
+            })
+            .collect()
+    }
+
+    fn root(
+        &mut self,
+        key: &AssignedCell<F, F>,
+        leaf: AssignedCell<F, F>,
+        siblings: &[Value<F>],
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let bits = self.key_bits(key)?;
+        let siblings = self.load_siblings(siblings)?;
+        let mut nodes = path_nodes(self, leaf, &siblings, &bits)?;
+        Ok(nodes.pop().unwrap())
+    }
+}
+
+impl<F: PrimeField, L: Layouter<F>> SmtHasher for CircuitHasher<'_, F, L>
+where
+    P128Pow5T3: Spec<F, WIDTH, RATE>,
+{
+    type Node = AssignedCell<F, F>;
+    type Bit = AssignedCell<F, F>;
+    type Error = Error;
+
+    fn key_bits(&mut self, key: &AssignedCell<F, F>) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let bits = BitsChip::construct(self.chip.config.bits.clone());
+        bits.decompose(self.layouter.namespace(|| "key bits"), key, DEPTH)
+    }
+
+    fn empty_leaf(&mut self) -> Result<AssignedCell<F, F>, Error> {
+        let merkle = self.chip.merkle();
+        merkle.load_constant(self.layouter.namespace(|| "empty leaf"), F::ZERO)
+    }
+
+    fn hash_leaf(
+        &mut self,
+        key: &AssignedCell<F, F>,
+        value: &AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let poseidon = self.chip.merkle().poseidon();
+        poseidon.hash(
+            self.layouter.namespace(|| "hash leaf"),
+            &[key.clone(), value.clone()],
+        )
+    }
+
+    fn hash_node(
+        &mut self,
+        left: &AssignedCell<F, F>,
+        right: &AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let poseidon = self.chip.merkle().poseidon();
+        poseidon.hash(
+            self.layouter.namespace(|| "hash node"),
+            &[left.clone(), right.clone()],
+        )
+    }
+
+    fn order(
+        &mut self,
+        node: &AssignedCell<F, F>,
+        sibling: &AssignedCell<F, F>,
+        bit: &AssignedCell<F, F>,
+    ) -> Result<[AssignedCell<F, F>; 2], Error> {
+        self.chip
+            .merkle()
+            .swap(self.layouter.namespace(|| "order pair"), node, sibling, bit)
+    }
+}
@@ -0,0 +1,130 @@
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter},
+    pasta::group::ff::PrimeField,
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Selector},
+    poly::Rotation,
+};
+
+use crate::range::{RangeCheckChip, RangeCheckConfig};
+
+#[derive(Clone, Debug)]
+pub struct SortedConfig {
+    value: Column<Advice>,
+    gap: Column<Advice>,
+    selector: Selector,
+    range: RangeCheckConfig,
+}
+
+/// Strict ordering of a list, one entry per row:
+///
+/// value gap
+///
+/// with `gap = value_next - value - 1` on every row but the last. With the
+/// values and gaps all range-checked to `num_bits` below the modulus, no
+/// difference can wrap around, so every gap in range means every entry is
+/// smaller than the next.
+///
+/// The circuit must enable a constant column.
+#[derive(Clone, Debug)]
+pub struct SortedChip<F: PrimeField> {
+    config: SortedConfig,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: PrimeField> SortedChip<F> {
+    pub fn construct(config: SortedConfig) -> Self {
+        SortedChip {
+            config,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> SortedConfig {
+        let value = meta.advice_column();
+        let gap = meta.advice_column();
+        meta.enable_equality(value);
+        meta.enable_equality(gap);
+        let selector = meta.selector();
+
+        meta.create_gate("sorted gap", |meta| {
+            let selector = meta.query_selector(selector);
+            let cur = meta.query_advice(value, Rotation::cur());
+            let next = meta.query_advice(value, Rotation::next());
+            let gap = meta.query_advice(gap, Rotation::cur());
+            Constraints::with_selector(
+                selector,
+                vec![gap - (next - cur - Expression::Constant(F::ONE))],
+            )
+        });
+
+        let range = RangeCheckChip::configure(meta);
+
+        SortedConfig {
+            value,
+            gap,
+            selector,
+            range,
+        }
+    }
+
+    pub fn load_table(&self, layouter: impl Layouter<F>) -> Result<(), Error> {
+        self.range().load_table(layouter)
+    }
+
+    /// Constrains `values` to be strictly increasing and below
+    /// `2^num_bits`, which must be a width `RangeCheckChip` supports.
+    pub fn assert_increasing(
+        &self,
+        mut layouter: impl Layouter<F>,
+        values: &[AssignedCell<F, F>],
+        num_bits: usize,
+    ) -> Result<(), Error> {
+        let config = &self.config;
+        let gaps = layouter.assign_region(
+            || "sorted",
+            |mut region| {
+                let mut gaps = Vec::with_capacity(values.len().saturating_sub(1));
+                for (row, value) in values.iter().enumerate() {
+                    value.copy_advice(|| "value", &mut region, config.value, row)?;
+                    let Some(next) = values.get(row + 1) else {
+                        break;
+                    };
+                    config.selector.enable(&mut region, row)?;
+                    let gap = (next.value().copied() - value.value()).map(|gap| gap - F::ONE);
+                    gaps.push(region.assign_advice(|| "gap", config.gap, row, || gap)?);
+                }
+                Ok(gaps)
+            },
+        )?;
+
+        let range = self.range();
+        range.range_check(layouter.namespace(|| "values"), values, num_bits)?;
+        range.range_check(layouter.namespace(|| "gaps"), &gaps, num_bits)
+    }
+
+    /// Constrains `next = value + 1`: a gap of zero.
+    pub fn assert_successor(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: &AssignedCell<F, F>,
+        next: &AssignedCell<F, F>,
+    ) -> Result<(), Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "successor",
+            |mut region| {
+                config.selector.enable(&mut region, 0)?;
+                value.copy_advice(|| "value", &mut region, config.value, 0)?;
+                next.copy_advice(|| "next", &mut region, config.value, 1)?;
+                region.assign_advice_from_constant(|| "gap", config.gap, 0, F::ZERO)?;
+                Ok(())
+            },
+        )
+    }
+
+    fn range(&self) -> RangeCheckChip<F> {
+        RangeCheckChip::construct(self.config.range.clone())
+    }
+}